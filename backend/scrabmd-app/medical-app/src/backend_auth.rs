@@ -0,0 +1,331 @@
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use lambda_http::tracing::{error, info};
+use serde::Deserialize;
+use serde_json::json;
+use std::env;
+use std::fs;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use chrono::Utc;
+use uuid::Uuid;
+use crate::scrab_errors::ScrabError;
+
+const CLIENT_ASSERTION_TYPE: &str = "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+
+// Scope requested when `BACKEND_SCOPE` is not set
+const DEFAULT_SCOPE: &str = "system/*.read";
+
+// Lifetime of the signed client assertion (the spec allows at most 5 minutes)
+const ASSERTION_LIFETIME_SECS: i64 = 300;
+
+// Fetch a new token this long before the cached one expires
+const EXPIRY_MARGIN_SECS: i64 = 60;
+
+// Time to open a connection to the token endpoint
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Time a token request may take, it delays the hook that needs the token
+const TOKEN_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Client registered with the EHR for SMART Backend Services
+pub struct BackendServiceConfig {
+    pub client_id: String,
+    pub fhir_server: String,
+    pub token_endpoint: String,
+    pub scope: String,
+    pub kid: Option<String>,
+    pub algorithm: Algorithm,
+    pub key: EncodingKey,
+    /// Time a token request may take
+    pub timeout: Duration,
+}
+
+impl BackendServiceConfig {
+    /// Client signing its assertions with a PKCS#8 RSA (RS384) or P-384 (ES384) private key
+    pub fn from_pem(
+        client_id: &str,
+        fhir_server: &str,
+        token_endpoint: &str,
+        pem: &[u8],
+    ) -> Result<Self, ScrabError> {
+        let (algorithm, key) = match EncodingKey::from_rsa_pem(pem) {
+            Ok(key) => (Algorithm::RS384, key),
+            Err(_) => (Algorithm::ES384, EncodingKey::from_ec_pem(pem)
+                .map_err(|e| ScrabError::Configuration(format!("Invalid backend private key: {}", e)))?),
+        };
+
+        Ok(Self {
+            client_id: client_id.to_string(),
+            fhir_server: fhir_server.trim_end_matches('/').to_string(),
+            token_endpoint: token_endpoint.to_string(),
+            scope: DEFAULT_SCOPE.to_string(),
+            kid: None,
+            algorithm,
+            key,
+            timeout: TOKEN_REQUEST_TIMEOUT,
+        })
+    }
+
+    /// Key id of the public key in the JWKS registered with the EHR
+    pub fn with_kid(mut self, kid: &str) -> Self {
+        self.kid = Some(kid.to_string());
+        self
+    }
+
+    pub fn with_scope(mut self, scope: &str) -> Self {
+        self.scope = scope.to_string();
+        self
+    }
+
+    /// Load from `BACKEND_CLIENT_ID`, `BACKEND_FHIR_SERVER`, `BACKEND_TOKEN_ENDPOINT`,
+    /// `BACKEND_PRIVATE_KEY_FILE` and the optional `BACKEND_KEY_ID` and `BACKEND_SCOPE`
+    pub fn from_env() -> Result<Self, ScrabError> {
+        let var = |name: &str| env::var(name)
+            .map_err(|_| ScrabError::Configuration(format!("{} must be set", name)));
+
+        let pem = fs::read(var("BACKEND_PRIVATE_KEY_FILE")?)?;
+        let mut config = Self::from_pem(
+            &var("BACKEND_CLIENT_ID")?,
+            &var("BACKEND_FHIR_SERVER")?,
+            &var("BACKEND_TOKEN_ENDPOINT")?,
+            &pem,
+        )?;
+        if let Ok(kid) = env::var("BACKEND_KEY_ID") {
+            config = config.with_kid(&kid);
+        }
+        if let Ok(scope) = env::var("BACKEND_SCOPE") {
+            config = config.with_scope(&scope);
+        }
+        Ok(config)
+    }
+
+    /// Shared config, loaded once per Lambda instance
+    pub fn shared() -> Result<&'static BackendServiceConfig, ScrabError> {
+        static CONFIG: OnceLock<BackendServiceConfig> = OnceLock::new();
+        if let Some(config) = CONFIG.get() {
+            return Ok(config);
+        }
+        let config = Self::from_env()?;
+        Ok(CONFIG.get_or_init(|| config))
+    }
+
+    /// Whether tokens of this client may be sent to the FHIR server
+    pub fn serves(&self, fhir_server: &str) -> bool {
+        self.fhir_server == fhir_server.trim_end_matches('/')
+    }
+
+    /// Signed JWT authenticating the client at the token endpoint
+    pub fn client_assertion(&self, now: i64) -> Result<String, ScrabError> {
+        let mut header = Header::new(self.algorithm);
+        header.kid = self.kid.clone();
+
+        let claims = json!({
+            "iss": self.client_id,
+            "sub": self.client_id,
+            "aud": self.token_endpoint,
+            "exp": now + ASSERTION_LIFETIME_SECS,
+            "jti": Uuid::new_v4().to_string(),
+        });
+
+        encode(&header, &claims, &self.key)
+            .map_err(|e| ScrabError::Authentication(format!("Error signing client assertion: {}", e)))
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ TOKEN ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<i64>,
+}
+
+/// Access token of the backend client, reused until it is about to expire
+#[derive(Default)]
+pub struct BackendTokenCache {
+    token: Mutex<Option<(String, i64)>>,
+}
+
+impl BackendTokenCache {
+    pub fn shared() -> &'static BackendTokenCache {
+        static CACHE: OnceLock<BackendTokenCache> = OnceLock::new();
+        CACHE.get_or_init(BackendTokenCache::default)
+    }
+
+    fn get(&self, now: i64) -> Result<Option<String>, ScrabError> {
+        let token = self.token.lock()
+            .map_err(|e| ScrabError::GenericError(e.to_string()))?;
+        Ok(token.as_ref()
+            .filter(|(_, expires_at)| expires_at - EXPIRY_MARGIN_SECS > now)
+            .map(|(access_token, _)| access_token.clone()))
+    }
+
+    fn store(&self, access_token: &str, expires_at: i64) -> Result<(), ScrabError> {
+        let mut token = self.token.lock()
+            .map_err(|e| ScrabError::GenericError(e.to_string()))?;
+        *token = Some((access_token.to_string(), expires_at));
+        Ok(())
+    }
+}
+
+/// Exchange a client assertion for an access token with the `client_credentials` grant
+pub async fn request_backend_token(
+    config: &BackendServiceConfig,
+    now: i64,
+) -> Result<(String, i64), ScrabError> {
+    let client = reqwest::Client::builder()
+        .use_rustls_tls()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(config.timeout)
+        .build()?;
+
+    let assertion = config.client_assertion(now)?;
+    let params = [
+        ("grant_type", "client_credentials"),
+        ("scope", config.scope.as_str()),
+        ("client_assertion_type", CLIENT_ASSERTION_TYPE),
+        ("client_assertion", assertion.as_str()),
+    ];
+
+    let response = client.post(&config.token_endpoint)
+        .header("Accept", "application/json")
+        .form(&params)
+        .send()
+        .await?;
+
+    let status = response.status();
+    let body = response.text().await?;
+    if !status.is_success() {
+        return Err(ScrabError::Authentication(format!("Token request rejected with {}: {}", status, body)));
+    }
+
+    let token: TokenResponse = serde_json::from_str(&body)?;
+    // Without expires_in the token is only kept for the lifetime of an assertion
+    let expires_at = now + token.expires_in.unwrap_or(ASSERTION_LIFETIME_SECS);
+    Ok((token.access_token, expires_at))
+}
+
+/// Cached access token of the backend client, fetching a new one when needed
+pub async fn backend_access_token(
+    config: &BackendServiceConfig,
+    cache: &BackendTokenCache,
+    now: i64,
+) -> Result<String, ScrabError> {
+    if let Some(access_token) = cache.get(now)? {
+        return Ok(access_token);
+    }
+
+    let (access_token, expires_at) = request_backend_token(config, now).await?;
+    info!("Backend services token issued for {} until {}", config.client_id, expires_at);
+    cache.store(&access_token, expires_at)?;
+    Ok(access_token)
+}
+
+/// Token of the backend client for a FHIR server, when one is configured for it
+pub async fn backend_token_for(fhir_server: &str) -> Option<String> {
+    let config = BackendServiceConfig::shared().ok()?;
+    if !config.serves(fhir_server) {
+        return None;
+    }
+
+    backend_access_token(config, BackendTokenCache::shared(), Utc::now().timestamp())
+        .await
+        .inspect_err(|e| error!("Error getting a backend services token: {:?}", e))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{decode, DecodingKey, Validation};
+    use jsonwebtoken::jwk::JwkSet;
+    use scrab_testing::MockServer;
+
+    const JWKS: &str = include_str!("../tests/fixtures/cds-client-jwks.json");
+    const RSA_KEY: &str = include_str!("../tests/fixtures/cds-client-rsa.pem");
+    const EC_KEY: &str = include_str!("../tests/fixtures/cds-client-ec.pem");
+
+    const CLIENT_ID: &str = "scrab-backend";
+    const FHIR_SERVER: &str = "https://ehr.example.org/fhir";
+    const NOW: i64 = 1_700_000_000;
+
+    fn form_value(body: &str, name: &str) -> Option<String> {
+        url::form_urlencoded::parse(body.as_bytes())
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.to_string())
+    }
+
+    #[test]
+    fn signs_client_assertions() {
+        let jwks: JwkSet = serde_json::from_str(JWKS).unwrap();
+
+        for (pem, kid, algorithm) in [
+            (RSA_KEY, "cds-client-rs384", Algorithm::RS384),
+            (EC_KEY, "cds-client-es384", Algorithm::ES384),
+        ] {
+            let config = BackendServiceConfig::from_pem(CLIENT_ID, FHIR_SERVER, "https://ehr.example.org/token", pem.as_bytes())
+                .unwrap()
+                .with_kid(kid);
+            assert_eq!(config.algorithm, algorithm);
+
+            let assertion = config.client_assertion(Utc::now().timestamp()).unwrap();
+            let key = DecodingKey::from_jwk(jwks.find(kid).unwrap()).unwrap();
+            let mut validation = Validation::new(algorithm);
+            validation.set_audience(&["https://ehr.example.org/token"]);
+            validation.set_issuer(&[CLIENT_ID]);
+            let claims = decode::<serde_json::Value>(&assertion, &key, &validation).unwrap().claims;
+            assert_eq!(claims["sub"], CLIENT_ID);
+            assert!(claims["jti"].as_str().is_some_and(|jti| !jti.is_empty()));
+        }
+
+        assert!(BackendServiceConfig::from_pem(CLIENT_ID, FHIR_SERVER, "", b"not a key").is_err());
+    }
+
+    #[tokio::test]
+    async fn caches_backend_token_until_expiry() {
+        let server = MockServer::json(
+            r#"{"access_token":"system-token","token_type":"bearer","expires_in":3600,"scope":"system/*.read"}"#,
+        ).await;
+        let config = BackendServiceConfig::from_pem(CLIENT_ID, FHIR_SERVER, &server.url("/token"), RSA_KEY.as_bytes())
+            .unwrap()
+            .with_kid("cds-client-rs384");
+        assert!(config.serves("https://ehr.example.org/fhir/"));
+        assert!(!config.serves("https://other.example.org/fhir"));
+
+        let cache = BackendTokenCache::default();
+        assert_eq!(backend_access_token(&config, &cache, NOW).await.unwrap(), "system-token");
+        assert_eq!(backend_access_token(&config, &cache, NOW + 1800).await.unwrap(), "system-token");
+        assert_eq!(server.requests().len(), 1);
+
+        let body = server.requests()[0].clone();
+        assert_eq!(form_value(&body, "grant_type").as_deref(), Some("client_credentials"));
+        assert_eq!(form_value(&body, "scope").as_deref(), Some("system/*.read"));
+        assert_eq!(form_value(&body, "client_assertion_type").as_deref(), Some(CLIENT_ASSERTION_TYPE));
+        assert!(form_value(&body, "client_assertion").is_some());
+
+        // Close to expiry a new token is requested
+        backend_access_token(&config, &cache, NOW + 3590).await.unwrap();
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn rejects_token_errors() {
+        let server = MockServer::start("401 Unauthorized", "application/json", r#"{"error":"invalid_client"}"#).await;
+        let config = BackendServiceConfig::from_pem(CLIENT_ID, FHIR_SERVER, &server.url("/token"), EC_KEY.as_bytes()).unwrap();
+
+        let result = backend_access_token(&config, &BackendTokenCache::default(), NOW).await;
+        assert!(matches!(result, Err(ScrabError::Authentication(_))));
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_a_token_endpoint_that_does_not_answer() {
+        let server = MockServer::silent().await;
+        let mut config = BackendServiceConfig::from_pem(CLIENT_ID, FHIR_SERVER, &server.url("/token"), EC_KEY.as_bytes()).unwrap();
+        config.timeout = Duration::from_millis(200);
+
+        match request_backend_token(&config, NOW).await {
+            Err(ScrabError::RequestFailed(e)) => assert!(e.is_timeout(), "{}", e),
+            other => panic!("expected a timeout, got {:?}", other.map(|(token, _)| token)),
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use serde_json::json;
+use scrab_cds::{
+    Action, Card, Coding, Indicator, Link, Resource, SelectionBehavior, Source, Suggestion,
+};
+use url::form_urlencoded;
+use uuid::Uuid;
+use crate::med_rules::{Duplicate, HighRiskMatch, Overlap};
+
+pub const SOURCE_LABEL: &str = "MediCompass";
+const APP_LABEL: &str = "MediCompass App";
+const OVERRIDE_REASON_SYSTEM: &str = "urn:medicompass:cds:override-reason";
+
+// Reasons a clinician can pick when dismissing a suggestion card
+const OVERRIDE_REASONS: &[(&str, &str)] = &[
+    ("already-reviewed", "Already reviewed with the patient"),
+    ("benefit-outweighs-risk", "Clinical benefit outweighs the risk"),
+    ("patient-tolerates", "Patient has tolerated this combination before"),
+    ("not-applicable", "Recommendation does not apply to this patient"),
+];
+
+fn new_uuid() -> String {
+    Uuid::new_v4().to_string()
+}
+
+/// Card without suggestions
+pub fn info_card(summary: &str, indicator: Indicator, detail: &str) -> Card {
+    Card::new(summary, indicator, Source::new(SOURCE_LABEL))
+        .with_uuid(&new_uuid())
+        .with_detail(detail)
+}
+
+/// Card the clinician can act on with one of the given suggestions
+pub fn suggestion_card(
+    summary: &str,
+    indicator: Indicator,
+    detail: &str,
+    suggestions: Vec<Suggestion>,
+) -> Card {
+    let mut card = info_card(summary, indicator, detail);
+
+    let override_reasons: Vec<Coding> = OVERRIDE_REASONS
+        .iter()
+        .map(|(code, display)| Coding::new(OVERRIDE_REASON_SYSTEM, code, display))
+        .collect();
+
+    if !suggestions.is_empty() {
+        card = card.with_suggestions(suggestions, SelectionBehavior::AtMostOne);
+    }
+    card.with_override_reasons(override_reasons)
+}
+
+/// Critical card for two medications that form a high-risk combination
+pub fn high_risk_card(high_risk: &HighRiskMatch) -> Card {
+    let first_class = high_risk.first.drug_class().map(|c| c.name).unwrap_or_default();
+    let second_class = high_risk.second.drug_class().map(|c| c.name).unwrap_or_default();
+
+    info_card(
+        &format!("High-risk combination: {} with {}", high_risk.first.display_name(), high_risk.second.display_name()),
+        Indicator::Critical,
+        &format!(
+            "{} ({}) and {} ({}) together: {}. Confirm the combination is intended and monitor the patient.",
+            high_risk.first.display_name(), first_class, high_risk.second.display_name(), second_class, high_risk.combination.risk,
+        ),
+    )
+}
+
+/// Card shown in degraded mode when the LLM backend did not answer
+pub fn degraded_card(analysis: &str) -> Card {
+    info_card(
+        "AI analysis unavailable",
+        Indicator::Warning,
+        &format!(
+            "The AI {} could not be completed because the language model is not responding. \
+            Only the rule-based checks were applied, so review the medications manually.",
+            analysis,
+        ),
+    )
+}
+
+/// Card shown when the LLM narrative did not arrive within the latency budget
+pub fn pending_card(summary: &str, analysis: &str) -> Card {
+    info_card(
+        summary,
+        Indicator::Info,
+        &format!(
+            "The AI {} is taking longer than usual. \
+            Open the MediCompass App to read it once it is ready.",
+            analysis,
+        ),
+    )
+}
+
+/// Link to the MediCompass SMART app
+pub fn app_link(smart_app_uri: &str) -> Link {
+    Link::smart(APP_LABEL, smart_app_uri)
+}
+
+/// Link to the narrative cached for a hook call, `token` is its signed launch token
+pub fn narrative_link(smart_app_uri: &str, hook_instance: &str, token: &str) -> Link {
+    let query: String = form_urlencoded::Serializer::new(String::new())
+        .append_pair("hookInstance", hook_instance)
+        .append_pair("token", token)
+        .finish();
+    Link::smart(APP_LABEL, &format!("{}?{}", smart_app_uri, query))
+        .with_app_context(hook_instance)
+}
+
+pub fn suggestion(label: &str, is_recommended: bool, actions: Vec<Action>) -> Suggestion {
+    Suggestion::new(label, &new_uuid(), is_recommended, actions)
+}
+
+/// MedicationRequest copy with status `stopped`, used to discontinue an order
+fn stopped_medication_request(resource: &Resource, reason: &str) -> Resource {
+    let mut stopped = resource.clone();
+    stopped.data.insert("status".to_string(), json!("stopped"));
+    stopped.data.insert("statusReason".to_string(), json!({ "text": reason }));
+    stopped
+}
+
+/// Warning card for a duplicated medication.
+/// Draft orders are removed with a `delete` action and active
+/// MedicationRequests are discontinued with an `update` action.
+pub fn duplicate_card(duplicate: &Duplicate) -> Card {
+    let candidate = &duplicate.candidate;
+    let existing = &duplicate.existing;
+
+    let (summary, detail, reason) = match &duplicate.overlap {
+        Overlap::SameMedication => (
+            format!("Duplicate medication: {}", candidate.display_name()),
+            format!(
+                "The patient is already taking {} ({}). Review whether the new order is needed.",
+                existing.display_name(), existing.status,
+            ),
+            "Duplicate medication".to_string(),
+        ),
+        Overlap::SameClass(class) => (
+            format!("Duplicate {} therapy: {}", class, candidate.display_name()),
+            format!(
+                "{} and {} are both {} medications. Review whether both should be continued.",
+                candidate.display_name(), existing.display_name(), class,
+            ),
+            format!("Duplicate {} therapy", class),
+        ),
+    };
+
+    let mut suggestions = Vec::new();
+
+    let candidate_is_request = candidate.resource.resource_type == "MedicationRequest";
+    if let (true, Some(reference)) = (candidate_is_request, &candidate.reference) {
+        if candidate.status == "draft" {
+            suggestions.push(suggestion(
+                &format!("Remove draft order for {}", candidate.display_name()),
+                true,
+                vec![Action::delete(&format!("Remove draft order for {}", candidate.display_name()), vec![reference.clone()])],
+            ));
+        } else {
+            suggestions.push(suggestion(
+                &format!("Discontinue {}", candidate.display_name()),
+                true,
+                vec![Action::update(
+                    &format!("Discontinue {}", candidate.display_name()),
+                    stopped_medication_request(&candidate.resource, &reason),
+                )],
+            ));
+        }
+    }
+
+    if existing.resource.resource_type == "MedicationRequest" && existing.is_active() {
+        suggestions.push(suggestion(
+            &format!("Discontinue {} and keep {}", existing.display_name(), candidate.display_name()),
+            false,
+            vec![Action::update(
+                &format!("Discontinue {}", existing.display_name()),
+                stopped_medication_request(&existing.resource, &reason),
+            )],
+        ));
+    }
+
+    suggestion_card(&summary, Indicator::Warning, &detail, suggestions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::med_rules::{MedicationItem, find_duplicates, find_high_risk};
+    use scrab_cds::ActionType;
+
+    fn medication(resource_type: &str, id: &str, status: &str, name: &str) -> MedicationItem {
+        let resource: Resource = serde_json::from_value(json!({
+            "resourceType": resource_type,
+            "id": id,
+            "status": status,
+            "medicationCodeableConcept": { "text": name }
+        })).unwrap();
+        MedicationItem::from_resource(&resource, None).unwrap()
+    }
+
+    fn duplicate(candidate: MedicationItem, existing: MedicationItem) -> Card {
+        let duplicates = find_duplicates(&[candidate], &[existing]);
+        assert_eq!(duplicates.len(), 1);
+        duplicate_card(&duplicates[0])
+    }
+
+    #[test]
+    fn removes_duplicate_draft_orders() {
+        let card = duplicate(
+            medication("MedicationRequest", "draft-1", "draft", "Atorvastatin 20 MG"),
+            medication("MedicationStatement", "stat-1", "active", "Atorvastatin 20 MG"),
+        );
+        assert_eq!(card.summary, "Duplicate medication: Atorvastatin 20 MG");
+        assert_eq!(card.indicator, Indicator::Warning);
+        assert_eq!(card.selection_behavior, Some(SelectionBehavior::AtMostOne));
+
+        // A statement cannot be discontinued, only the draft is removed
+        assert_eq!(card.suggestions.len(), 1);
+        let remove = &card.suggestions[0];
+        assert_eq!(remove.is_recommended, Some(true));
+        assert_eq!(remove.actions[0].action_type, ActionType::Delete);
+        assert_eq!(remove.actions[0].resource_id, Some(vec!["MedicationRequest/draft-1".to_string()]));
+    }
+
+    #[test]
+    fn discontinues_duplicate_active_requests() {
+        let card = duplicate(
+            medication("MedicationRequest", "req-2", "active", "Naproxen 250 MG"),
+            medication("MedicationRequest", "req-1", "active", "Ibuprofen 400 MG"),
+        );
+        assert_eq!(card.summary, "Duplicate NSAID therapy: Naproxen 250 MG");
+
+        let labels: Vec<&str> = card.suggestions.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, vec!["Discontinue Naproxen 250 MG", "Discontinue Ibuprofen 400 MG and keep Naproxen 250 MG"]);
+        assert_eq!(card.suggestions[1].is_recommended, Some(false));
+
+        for (suggestion, id) in card.suggestions.iter().zip(["req-2", "req-1"]) {
+            let action = &suggestion.actions[0];
+            assert_eq!(action.action_type, ActionType::Update);
+            let stopped = action.resource.as_ref().unwrap();
+            assert_eq!(stopped.id.as_deref(), Some(id));
+            assert_eq!(stopped.get("status"), Some(&json!("stopped")));
+            assert_eq!(stopped.get("statusReason"), Some(&json!({ "text": "Duplicate NSAID therapy" })));
+        }
+    }
+
+    #[test]
+    fn suggestion_cards_offer_override_reasons() {
+        let card = suggestion_card("Summary", Indicator::Warning, "Detail", vec![]);
+        assert!(card.suggestions.is_empty());
+        assert_eq!(card.selection_behavior, None);
+
+        let codes: Vec<&str> = card.override_reasons.iter().filter_map(|c| c.code.as_deref()).collect();
+        assert_eq!(codes, OVERRIDE_REASONS.iter().map(|(code, _)| *code).collect::<Vec<_>>());
+        assert!(card.override_reasons.iter().all(|c| c.system.as_deref() == Some(OVERRIDE_REASON_SYSTEM)));
+
+        // Informational cards have nothing to override
+        let info = info_card("Summary", Indicator::Info, "Detail");
+        assert!(info.override_reasons.is_empty());
+        assert!(info.uuid.is_some());
+    }
+
+    #[test]
+    fn high_risk_cards_are_critical() {
+        let drafts = [medication("MedicationRequest", "draft-1", "draft", "Sertraline 50 MG")];
+        let active = [medication("MedicationRequest", "req-1", "active", "Warfarin 5 MG")];
+        let card = high_risk_card(&find_high_risk(&drafts, &active)[0]);
+
+        assert_eq!(card.indicator, Indicator::Critical);
+        assert_eq!(card.summary, "High-risk combination: Sertraline 50 MG with Warfarin 5 MG");
+        assert!(card.detail.unwrap().contains("Increased risk of bleeding"));
+    }
+
+    #[test]
+    fn narrative_link_passes_the_hook_instance() {
+        let link = narrative_link("https://app.example.org/v1/launch", "hook 1", "signed");
+        assert_eq!(link.url, "https://app.example.org/v1/launch?hookInstance=hook+1&token=signed");
+        assert_eq!(link.app_context.as_deref(), Some("hook 1"));
+        assert_eq!(app_link("https://app.example.org/v1/launch").app_context, None);
+    }
+}
@@ -0,0 +1,395 @@
+use aws_sdk_dynamodb as dynamodb;
+use aws_sdk_dynamodb::types::AttributeValue;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use jsonwebtoken::jwk::JwkSet;
+use lambda_http::tracing::{error, warn};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::sync::OnceLock;
+use chrono::Utc;
+use crate::scrab_errors::ScrabError;
+
+// Algorithms a CDS client may sign with (the spec requires ES384 and RS384)
+const ALLOWED_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::ES384,
+    Algorithm::ES256,
+    Algorithm::RS384,
+    Algorithm::RS256,
+];
+
+// Clock skew tolerated on exp/iat
+const LEEWAY_SECS: u64 = 30;
+
+/// Claims of the JWT sent by the CDS client in `Authorization: Bearer`
+#[derive(Debug, Deserialize, Clone)]
+pub struct CdsClientClaims {
+    pub iss: String,
+    pub sub: Option<String>,
+    pub exp: i64,
+    pub jti: String,
+}
+
+/// Issuer claim read before the signature is checked, to pick the issuer's keys
+#[derive(Debug, Deserialize)]
+struct UnverifiedIssuer {
+    iss: String,
+}
+
+/// Trusted EHR issuers, each with the keys its tokens are signed with
+pub struct CdsAuthConfig {
+    pub issuers: HashMap<String, JwkSet>,
+    pub audiences: Vec<String>,
+}
+
+impl CdsAuthConfig {
+    /// Load from `CDS_TRUSTED_ISSUERS`, comma separated `issuer|jwks_file` entries,
+    /// and the optional extra audiences in `CDS_AUDIENCE`
+    pub fn from_env() -> Result<Self, ScrabError> {
+        let trusted_issuers = env::var("CDS_TRUSTED_ISSUERS")
+            .map_err(|_| ScrabError::Configuration("CDS_TRUSTED_ISSUERS must be set".to_string()))?;
+
+        let mut issuers = HashMap::new();
+        for entry in trusted_issuers.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (issuer, jwks_file) = entry.split_once('|')
+                .ok_or_else(|| ScrabError::Configuration(format!("{} has no JWKS file", entry)))?;
+            let jwks: JwkSet = serde_json::from_str(&fs::read_to_string(jwks_file.trim())?)?;
+            issuers.insert(issuer.trim().trim_end_matches('/').to_string(), jwks);
+        }
+
+        let audiences = env::var("CDS_AUDIENCE")
+            .map(|a| split_list(&a))
+            .unwrap_or_default();
+
+        Ok(Self { issuers, audiences })
+    }
+
+    /// Shared config, loaded once per Lambda instance
+    pub fn shared() -> Result<&'static CdsAuthConfig, ScrabError> {
+        static CONFIG: OnceLock<CdsAuthConfig> = OnceLock::new();
+        if let Some(config) = CONFIG.get() {
+            return Ok(config);
+        }
+        let config = Self::from_env()?;
+        Ok(CONFIG.get_or_init(|| config))
+    }
+
+    /// Trusted issuer of a token and its keys
+    fn issuer_keys(&self, iss: &str) -> Option<(&str, &JwkSet)> {
+        self.issuers.get_key_value(iss.trim_end_matches('/'))
+            .map(|(issuer, jwks)| (issuer.as_str(), jwks))
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value.split(',')
+        .map(|s| s.trim().trim_end_matches('/').to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ JTI STORE ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Remembers the `jti` of accepted tokens until they expire, across Lambda instances
+pub trait JtiStore {
+    /// Returns false when the jti was already used by a token that is still valid
+    async fn check_and_store(&self, jti: &str, exp: i64, now: i64) -> Result<bool, ScrabError>;
+}
+
+/// DynamoDB store, `jti` is the partition key and `expires_at` the TTL attribute
+pub struct DynamoJtiStore {
+    client: dynamodb::Client,
+    table_name: String,
+}
+
+impl DynamoJtiStore {
+    pub async fn new(table_name: &str) -> Self {
+        let config = aws_config::load_from_env().await;
+        Self {
+            client: dynamodb::Client::new(&config),
+            table_name: table_name.to_string(),
+        }
+    }
+
+    /// Store of the `CDS_JTI_TABLE` table, its client is built once per instance
+    pub async fn shared() -> Result<&'static DynamoJtiStore, ScrabError> {
+        static STORE: OnceLock<DynamoJtiStore> = OnceLock::new();
+        if let Some(store) = STORE.get() {
+            return Ok(store);
+        }
+
+        let table_name = env::var("CDS_JTI_TABLE")
+            .map_err(|_| ScrabError::Configuration("CDS_JTI_TABLE must be set".to_string()))?;
+        let store = DynamoJtiStore::new(&table_name).await;
+        Ok(STORE.get_or_init(|| store))
+    }
+}
+
+impl JtiStore for DynamoJtiStore {
+    async fn check_and_store(&self, jti: &str, exp: i64, now: i64) -> Result<bool, ScrabError> {
+        // TTL deletion lags behind, so an expired item is overwritten rather than trusted
+        let result = self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .item("jti", AttributeValue::S(jti.to_string()))
+            .item("expires_at", AttributeValue::N((exp + LEEWAY_SECS as i64).to_string()))
+            .condition_expression("attribute_not_exists(jti) OR expires_at < :now")
+            .expression_attribute_values(":now", AttributeValue::N(now.to_string()))
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                let e = e.into_service_error();
+                if e.is_conditional_check_failed_exception() {
+                    Ok(false)
+                } else {
+                    Err(ScrabError::ServiceUnavailable(format!("jti store: {}", dynamodb::Error::from(e))))
+                }
+            }
+        }
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ VERIFICATION ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Issuer of a token, before its signature is checked
+fn unverified_issuer(token: &str) -> Result<String, ScrabError> {
+    let mut validation = Validation::default();
+    validation.insecure_disable_signature_validation();
+    validation.validate_exp = false;
+    validation.validate_aud = false;
+    validation.required_spec_claims.clear();
+
+    decode::<UnverifiedIssuer>(token, &DecodingKey::from_secret(&[]), &validation)
+        .map(|data| data.claims.iss)
+        .map_err(|e| ScrabError::Authentication(format!("Invalid token: {}", e)))
+}
+
+/// Verify the bearer token of a hook call against the keys of its issuer.
+/// `audience` is the URL of the CDS service that was called.
+pub async fn verify_bearer_token(
+    authorization: Option<&str>,
+    audience: &str,
+    config: &CdsAuthConfig,
+    store: &impl JtiStore,
+) -> Result<CdsClientClaims, ScrabError> {
+    let token = authorization
+        .and_then(|value| value.strip_prefix("Bearer ").or_else(|| value.strip_prefix("bearer ")))
+        .map(|token| token.trim())
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| ScrabError::Authentication("Missing bearer token".to_string()))?;
+
+    let header = decode_header(token)
+        .map_err(|e| ScrabError::Authentication(format!("Invalid token header: {}", e)))?;
+
+    if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+        return Err(ScrabError::Authentication(format!("Algorithm {:?} is not allowed", header.alg)));
+    }
+
+    let iss = unverified_issuer(token)?;
+    let (issuer, jwks) = config.issuer_keys(&iss)
+        .ok_or_else(|| ScrabError::Authentication(format!("Issuer {} is not trusted", iss)))?;
+
+    let jwk = match &header.kid {
+        Some(kid) => jwks.find(kid),
+        None if jwks.keys.len() == 1 => jwks.keys.first(),
+        None => None,
+    }.ok_or_else(|| ScrabError::Authentication(format!("No matching key in the JWKS of {}", issuer)))?;
+
+    let key = DecodingKey::from_jwk(jwk)
+        .map_err(|e| ScrabError::Configuration(format!("Invalid JWK of {}: {}", issuer, e)))?;
+
+    let mut audiences = vec![audience.trim_end_matches('/').to_string()];
+    audiences.extend(config.audiences.iter().cloned());
+
+    let mut validation = Validation::new(header.alg);
+    validation.leeway = LEEWAY_SECS;
+    validation.set_issuer(&[issuer, iss.as_str()]);
+    validation.set_audience(&audiences);
+    validation.set_required_spec_claims(&["iss", "aud", "exp"]);
+
+    let claims = decode::<CdsClientClaims>(token, &key, &validation)
+        .map_err(|e| ScrabError::Authentication(format!("Invalid token: {}", e)))?
+        .claims;
+
+    if claims.jti.trim().is_empty() {
+        return Err(ScrabError::Authentication("Token has an empty jti".to_string()));
+    }
+
+    // A jti only has to be unique per issuer
+    let jti_key = format!("{}#{}", issuer, claims.jti);
+    if !store.check_and_store(&jti_key, claims.exp, Utc::now().timestamp()).await? {
+        warn!("Replayed token jti {} from {}", claims.jti, claims.iss);
+        return Err(ScrabError::Authentication("Token jti was already used".to_string()));
+    }
+
+    Ok(claims)
+}
+
+/// Verify a hook call with the shared config and replay store
+pub async fn authorize_hook_call(
+    authorization: Option<&str>,
+    audience: &str,
+) -> Result<CdsClientClaims, ScrabError> {
+    // A JWKS file that cannot be read or parsed is a configuration error, not a bad token
+    let config = CdsAuthConfig::shared()
+        .map_err(|e| match e {
+            ScrabError::Configuration(_) => e,
+            other => ScrabError::Configuration(format!("CDS_TRUSTED_ISSUERS: {}", other)),
+        })
+        .inspect_err(|e| error!("CDS client authentication is not configured: {:?}", e))?;
+    let store = DynamoJtiStore::shared().await.inspect_err(|e| {
+        error!("CDS client replay protection is not configured: {:?}", e);
+    })?;
+    verify_bearer_token(authorization, audience, config, store).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    const JWKS: &str = include_str!("../tests/fixtures/cds-client-jwks.json");
+    const RSA_KEY: &str = include_str!("../tests/fixtures/cds-client-rsa.pem");
+    const EC_KEY: &str = include_str!("../tests/fixtures/cds-client-ec.pem");
+
+    const ISSUER: &str = "https://ehr.example.org";
+    const EC_ONLY_ISSUER: &str = "https://other-ehr.example.org";
+    const AUDIENCE: &str = "https://cds.example.org/v1/cds-services/medication";
+
+    /// Process-local store standing in for DynamoDB
+    #[derive(Default)]
+    struct MemoryJtiStore {
+        seen: Mutex<HashMap<String, i64>>,
+    }
+
+    impl JtiStore for MemoryJtiStore {
+        async fn check_and_store(&self, jti: &str, exp: i64, now: i64) -> Result<bool, ScrabError> {
+            let mut seen = self.seen.lock().unwrap();
+            seen.retain(|_, expires| *expires + LEEWAY_SECS as i64 >= now);
+            Ok(seen.insert(jti.to_string(), exp).is_none())
+        }
+    }
+
+    fn config() -> CdsAuthConfig {
+        let jwks: JwkSet = serde_json::from_str(JWKS).unwrap();
+        let ec_only = JwkSet {
+            keys: jwks.keys.iter().filter(|k| k.common.key_id.as_deref() == Some("cds-client-es384")).cloned().collect(),
+        };
+        CdsAuthConfig {
+            issuers: HashMap::from([(ISSUER.to_string(), jwks), (EC_ONLY_ISSUER.to_string(), ec_only)]),
+            audiences: vec![],
+        }
+    }
+
+    fn rsa_token(claims: serde_json::Value) -> String {
+        let mut header = Header::new(Algorithm::RS384);
+        header.kid = Some("cds-client-rs384".to_string());
+        let key = EncodingKey::from_rsa_pem(RSA_KEY.as_bytes()).unwrap();
+        encode(&header, &claims, &key).unwrap()
+    }
+
+    fn ec_token(claims: serde_json::Value) -> String {
+        let mut header = Header::new(Algorithm::ES384);
+        header.kid = Some("cds-client-es384".to_string());
+        let key = EncodingKey::from_ec_pem(EC_KEY.as_bytes()).unwrap();
+        encode(&header, &claims, &key).unwrap()
+    }
+
+    fn claims(jti: &str) -> serde_json::Value {
+        let now = Utc::now().timestamp();
+        json!({
+            "iss": ISSUER,
+            "sub": "client-id",
+            "aud": AUDIENCE,
+            "iat": now,
+            "exp": now + 300,
+            "jti": jti,
+        })
+    }
+
+    async fn verify(token: &str, store: &MemoryJtiStore) -> Result<CdsClientClaims, ScrabError> {
+        let header = format!("Bearer {}", token);
+        verify_bearer_token(Some(&header), AUDIENCE, &config(), store).await
+    }
+
+    #[tokio::test]
+    async fn accepts_rs384_token() {
+        let store = MemoryJtiStore::default();
+        let verified = verify(&rsa_token(claims("jti-1")), &store).await.unwrap();
+        assert_eq!(verified.iss, ISSUER);
+        assert_eq!(verified.jti, "jti-1");
+        assert!(store.seen.lock().unwrap().contains_key("https://ehr.example.org#jti-1"));
+    }
+
+    #[tokio::test]
+    async fn accepts_es384_token() {
+        assert!(verify(&ec_token(claims("jti-ec")), &MemoryJtiStore::default()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn rejects_replayed_jti() {
+        let store = MemoryJtiStore::default();
+        assert!(verify(&rsa_token(claims("jti-2")), &store).await.is_ok());
+        assert!(verify(&rsa_token(claims("jti-2")), &store).await.is_err());
+
+        // The same jti from another issuer is a different token
+        let mut other = claims("jti-2");
+        other["iss"] = json!(EC_ONLY_ISSUER);
+        assert!(verify(&ec_token(other), &store).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn rejects_untrusted_issuer() {
+        let mut untrusted = claims("jti-3");
+        untrusted["iss"] = json!("https://unknown.example.org");
+        assert!(verify(&rsa_token(untrusted), &MemoryJtiStore::default()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_keys_of_another_issuer() {
+        // The RSA key is trusted for ISSUER only
+        let mut other = claims("jti-8");
+        other["iss"] = json!(EC_ONLY_ISSUER);
+        let result = verify(&rsa_token(other), &MemoryJtiStore::default()).await;
+        assert!(matches!(result, Err(ScrabError::Authentication(_))));
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_audience() {
+        let mut other = claims("jti-4");
+        other["aud"] = json!("https://cds.example.org/v1/cds-services/other");
+        assert!(verify(&rsa_token(other), &MemoryJtiStore::default()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_expired_token() {
+        let mut expired = claims("jti-5");
+        expired["exp"] = json!(Utc::now().timestamp() - 600);
+        assert!(verify(&rsa_token(expired), &MemoryJtiStore::default()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_jti() {
+        let mut no_jti = claims("jti-6");
+        no_jti.as_object_mut().unwrap().remove("jti");
+        assert!(verify(&rsa_token(no_jti), &MemoryJtiStore::default()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_kid_and_missing_header() {
+        let mut header = Header::new(Algorithm::RS384);
+        header.kid = Some("other-key".to_string());
+        let key = EncodingKey::from_rsa_pem(RSA_KEY.as_bytes()).unwrap();
+        let token = encode(&header, &claims("jti-7"), &key).unwrap();
+        assert!(verify(&token, &MemoryJtiStore::default()).await.is_err());
+
+        let result = verify_bearer_token(None, AUDIENCE, &config(), &MemoryJtiStore::default()).await;
+        assert!(matches!(result, Err(ScrabError::Authentication(_))));
+    }
+}
@@ -0,0 +1,107 @@
+use lambda_http::tracing::error;
+use std::time::Duration;
+use crate::system_actions::SystemActionKind;
+use scrab_cds::{Prefetch, PrefetchTemplate, ServiceDescriptor};
+
+/// CDS service advertised by the discovery endpoint
+pub struct CdsService {
+    pub id: &'static str,
+    pub hook: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    pub prefetch: &'static [(&'static str, &'static str)],
+    /// Prefetch keys the service cannot run without (answered with 412 when missing)
+    pub required_prefetch: &'static [&'static str],
+    /// Time the LLM narrative may take before the rule-based cards are returned without it
+    pub latency_budget_ms: u64,
+    /// Resources created through systemActions when a high-risk combination is found
+    pub system_actions: &'static [SystemActionKind],
+}
+
+pub const PATIENT_VIEW_PREFETCH: &[(&str, &str)] = &[
+    ("patient", "Patient/{{context.patientId}}"),
+    ("medication", "Medication?patient={{context.patientId}}"),
+    ("medications_stat", "MedicationStatement?patient={{context.patientId}}"),
+    ("medication_req", "MedicationRequest?patient={{context.patientId}}"),
+];
+
+pub const ORDER_PREFETCH: &[(&str, &str)] = &[
+    ("patient", "Patient/{{context.patientId}}"),
+    ("medications_stat", "MedicationStatement?patient={{context.patientId}}&status=active"),
+    ("medication_req", "MedicationRequest?patient={{context.patientId}}&status=active"),
+];
+
+pub const SERVICES: &[CdsService] = &[
+    CdsService {
+        id: "medication",
+        hook: "patient-view",
+        title: "Patient Medication",
+        description: "Patient medication description",
+        prefetch: PATIENT_VIEW_PREFETCH,
+        required_prefetch: &["medications_stat"],
+        latency_budget_ms: 1500,
+        system_actions: &[],
+    },
+    CdsService {
+        id: "medication-order-select",
+        hook: "order-select",
+        title: "Medication Order Review",
+        description: "Checks the selected draft orders against the patient's active medications",
+        prefetch: ORDER_PREFETCH,
+        required_prefetch: &["medications_stat", "medication_req"],
+        latency_budget_ms: 1000,
+        system_actions: &[],
+    },
+    CdsService {
+        id: "medication-order-sign",
+        hook: "order-sign",
+        title: "Medication Order Sign Check",
+        description: "Checks all draft orders against the patient's active medications before signing",
+        prefetch: ORDER_PREFETCH,
+        required_prefetch: &["medications_stat", "medication_req"],
+        latency_budget_ms: 2000,
+        system_actions: &[SystemActionKind::Flag, SystemActionKind::Task],
+    },
+];
+
+/// Find a service by the id used in the `cds-services/{id}` path
+pub fn find_service(id: &str) -> Option<&'static CdsService> {
+    SERVICES.iter().find(|service| service.id == id)
+}
+
+impl CdsService {
+    pub fn latency_budget(&self) -> Duration {
+        Duration::from_millis(self.latency_budget_ms)
+    }
+
+    /// Required prefetch keys that are still missing after the fallback fetch
+    pub fn missing_prefetch(&self, prefetch: &Prefetch) -> Vec<&'static str> {
+        self.required_prefetch
+            .iter()
+            .filter(|key| prefetch.is_missing(key))
+            .copied()
+            .collect()
+    }
+
+    /// Descriptor for the discovery endpoint. Templates that do not parse are
+    /// left out so the EHR is never asked for a prefetch we cannot expand.
+    pub fn descriptor(&self) -> ServiceDescriptor {
+        ServiceDescriptor {
+            hook: self.hook.to_string(),
+            title: Some(self.title.to_string()),
+            description: self.description.to_string(),
+            id: self.id.to_string(),
+            prefetch: self.prefetch
+                .iter()
+                .filter_map(|(key, template)| match PrefetchTemplate::parse(template) {
+                    Ok(template) => Some((key.to_string(), template.to_string())),
+                    Err(e) => {
+                        error!("Invalid prefetch template for {}: {}", key, e);
+                        None
+                    }
+                })
+                .collect(),
+            usage_requirements: None,
+        }
+    }
+}
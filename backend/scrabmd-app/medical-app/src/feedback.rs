@@ -0,0 +1,466 @@
+use aws_sdk_dynamodb as dynamodb;
+use aws_sdk_dynamodb::types::AttributeValue;
+use chrono::{DateTime, Utc};
+use lambda_http::tracing::{error, info};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::sync::OnceLock;
+use uuid::Uuid;
+use crate::scrab_errors::ScrabError;
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ FEEDBACK REQUEST ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Body of `POST cds-services/{id}/feedback`
+#[derive(Debug, Deserialize)]
+pub struct FeedbackRequest {
+    pub feedback: Vec<FeedbackItem>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedbackItem {
+    pub card: String,
+    pub outcome: String,
+    pub accepted_suggestions: Option<Vec<AcceptedSuggestion>>,
+    pub override_reason: Option<OverrideReason>,
+    pub outcome_timestamp: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AcceptedSuggestion {
+    pub id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverrideReason {
+    pub reason: Option<ReasonCoding>,
+    pub user_comment: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReasonCoding {
+    pub code: Option<String>,
+    pub display: Option<String>,
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ STORED OUTCOME ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Feedback outcome as it is persisted in the store
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct FeedbackOutcome {
+    pub id: String,
+    pub service_id: String,
+    pub card_uuid: String,
+    pub outcome: String,
+    pub accepted_suggestions: Vec<String>,
+    pub override_reason: Option<String>,
+    pub user_comment: Option<String>,
+    pub outcome_timestamp: String,
+    pub received_at: String,
+}
+
+impl FeedbackItem {
+    fn validate(&self) -> Result<(), ScrabError> {
+        if self.card.trim().is_empty() {
+            return Err(ScrabError::Validation("Feedback card uuid is empty".to_string()));
+        }
+
+        let accepted = self.accepted_suggestions.as_ref().is_some_and(|s| !s.is_empty());
+
+        match self.outcome.as_str() {
+            "accepted" => {
+                if !accepted {
+                    return Err(ScrabError::Validation(format!(
+                        "Card {} is accepted but has no acceptedSuggestions", self.card
+                    )));
+                }
+                if self.override_reason.is_some() {
+                    return Err(ScrabError::Validation(format!(
+                        "Card {} is accepted but has an overrideReason", self.card
+                    )));
+                }
+            }
+            "overridden" => {
+                if accepted {
+                    return Err(ScrabError::Validation(format!(
+                        "Card {} is overridden but has acceptedSuggestions", self.card
+                    )));
+                }
+            }
+            other => {
+                return Err(ScrabError::Validation(format!("Unknown outcome: {}", other)));
+            }
+        }
+
+        if DateTime::parse_from_rfc3339(&self.outcome_timestamp).is_err() {
+            return Err(ScrabError::Validation(format!(
+                "Invalid outcomeTimestamp: {}", self.outcome_timestamp
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn to_outcome(&self, service_id: &str) -> FeedbackOutcome {
+        let override_reason = self.override_reason.as_ref()
+            .and_then(|o| o.reason.as_ref())
+            .and_then(|r| r.code.clone().or_else(|| r.display.clone()));
+
+        FeedbackOutcome {
+            id: Uuid::new_v4().to_string(),
+            service_id: service_id.to_string(),
+            card_uuid: self.card.clone(),
+            outcome: self.outcome.clone(),
+            accepted_suggestions: self.accepted_suggestions.iter().flatten()
+                .fold(vec![], |mut ids, a| {
+                    // Stored as a DynamoDB string set, which cannot repeat an id
+                    if !ids.contains(&a.id) {
+                        ids.push(a.id.clone());
+                    }
+                    ids
+                }),
+            override_reason,
+            user_comment: self.override_reason.as_ref().and_then(|o| o.user_comment.clone()),
+            outcome_timestamp: self.outcome_timestamp.clone(),
+            received_at: Utc::now().to_rfc3339(),
+        }
+    }
+}
+
+/// Parse and validate a feedback body, all items must be valid
+pub fn parse_feedback(
+    body: &str,
+    service_id: &str,
+) -> Result<Vec<FeedbackOutcome>, ScrabError> {
+    let request: FeedbackRequest = serde_json::from_str(body)
+        .map_err(|e| ScrabError::Validation(format!("Invalid feedback body: {}", e)))?;
+
+    if request.feedback.is_empty() {
+        return Err(ScrabError::Validation("Feedback list is empty".to_string()));
+    }
+
+    request.feedback.iter()
+        .map(|item| {
+            item.validate()?;
+            Ok(item.to_outcome(service_id))
+        })
+        .collect()
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ ACCEPTANCE REPORT ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AcceptanceReport {
+    pub service_id: String,
+    pub total: u32,
+    pub accepted: u32,
+    pub overridden: u32,
+    pub acceptance_rate: f64,
+    pub override_reasons: BTreeMap<String, u32>,
+}
+
+impl AcceptanceReport {
+    pub fn from_outcomes(service_id: &str, outcomes: &[FeedbackOutcome]) -> Self {
+        let mut report = AcceptanceReport {
+            service_id: service_id.to_string(),
+            ..Default::default()
+        };
+
+        for outcome in outcomes.iter().filter(|o| o.service_id == service_id) {
+            report.total += 1;
+            if outcome.outcome == "accepted" {
+                report.accepted += 1;
+            } else {
+                report.overridden += 1;
+                let reason = outcome.override_reason.clone().unwrap_or_else(|| "unspecified".to_string());
+                *report.override_reasons.entry(reason).or_insert(0) += 1;
+            }
+        }
+
+        if report.total > 0 {
+            report.acceptance_rate = report.accepted as f64 / report.total as f64;
+        }
+        report
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ FEEDBACK STORE ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Storage backend for feedback outcomes
+pub trait FeedbackStore {
+    async fn save(&self, outcomes: &[FeedbackOutcome]) -> Result<(), ScrabError>;
+
+    async fn list(&self, service_id: &str) -> Result<Vec<FeedbackOutcome>, ScrabError>;
+
+    async fn report(&self, service_id: &str) -> Result<AcceptanceReport, ScrabError> {
+        let outcomes = self.list(service_id).await?;
+        Ok(AcceptanceReport::from_outcomes(service_id, &outcomes))
+    }
+}
+
+/// DynamoDB store, `service_id` is the partition key and `id` the sort key
+pub struct DynamoFeedbackStore {
+    client: dynamodb::Client,
+    table_name: String,
+}
+
+impl DynamoFeedbackStore {
+    pub async fn new(table_name: &str) -> Self {
+        let config = aws_config::load_from_env().await;
+        Self {
+            client: dynamodb::Client::new(&config),
+            table_name: table_name.to_string(),
+        }
+    }
+
+    /// Store of the `FEEDBACK_TABLE` table, its client is built once per instance.
+    /// Fails when the table is not configured, feedback would otherwise be lost.
+    pub async fn shared() -> Result<&'static DynamoFeedbackStore, ScrabError> {
+        static STORE: OnceLock<DynamoFeedbackStore> = OnceLock::new();
+        if let Some(store) = STORE.get() {
+            return Ok(store);
+        }
+
+        let table_name = env::var("FEEDBACK_TABLE")
+            .map_err(|_| ScrabError::Configuration("FEEDBACK_TABLE is not set".to_string()))?;
+        let store = DynamoFeedbackStore::new(&table_name).await;
+        Ok(STORE.get_or_init(|| store))
+    }
+
+    fn to_item(outcome: &FeedbackOutcome) -> HashMap<String, AttributeValue> {
+        let mut item = HashMap::new();
+        item.insert("service_id".to_string(), AttributeValue::S(outcome.service_id.clone()));
+        item.insert("id".to_string(), AttributeValue::S(outcome.id.clone()));
+        item.insert("card_uuid".to_string(), AttributeValue::S(outcome.card_uuid.clone()));
+        item.insert("outcome".to_string(), AttributeValue::S(outcome.outcome.clone()));
+        item.insert("outcome_timestamp".to_string(), AttributeValue::S(outcome.outcome_timestamp.clone()));
+        item.insert("received_at".to_string(), AttributeValue::S(outcome.received_at.clone()));
+        if !outcome.accepted_suggestions.is_empty() {
+            item.insert("accepted_suggestions".to_string(), AttributeValue::Ss(outcome.accepted_suggestions.clone()));
+        }
+        if let Some(reason) = &outcome.override_reason {
+            item.insert("override_reason".to_string(), AttributeValue::S(reason.clone()));
+        }
+        if let Some(comment) = &outcome.user_comment {
+            item.insert("user_comment".to_string(), AttributeValue::S(comment.clone()));
+        }
+        item
+    }
+
+    fn from_item(item: &HashMap<String, AttributeValue>) -> FeedbackOutcome {
+        let get_s = |key: &str| item.get(key).and_then(|av| av.as_s().ok().map(|s| s.to_string()));
+        FeedbackOutcome {
+            id: get_s("id").unwrap_or_default(),
+            service_id: get_s("service_id").unwrap_or_default(),
+            card_uuid: get_s("card_uuid").unwrap_or_default(),
+            outcome: get_s("outcome").unwrap_or_default(),
+            accepted_suggestions: item.get("accepted_suggestions")
+                .and_then(|av| av.as_ss().ok().cloned())
+                .unwrap_or_default(),
+            override_reason: get_s("override_reason"),
+            user_comment: get_s("user_comment"),
+            outcome_timestamp: get_s("outcome_timestamp").unwrap_or_default(),
+            received_at: get_s("received_at").unwrap_or_default(),
+        }
+    }
+}
+
+impl FeedbackStore for DynamoFeedbackStore {
+    async fn save(&self, outcomes: &[FeedbackOutcome]) -> Result<(), ScrabError> {
+        for outcome in outcomes {
+            self.client
+                .put_item()
+                .table_name(&self.table_name)
+                .set_item(Some(Self::to_item(outcome)))
+                .send()
+                .await
+                .map_err(|e| ScrabError::RequestError(dynamodb::Error::from(e).to_string()))?;
+        }
+        Ok(())
+    }
+
+    async fn list(&self, service_id: &str) -> Result<Vec<FeedbackOutcome>, ScrabError> {
+        let items: Result<Vec<_>, _> = self.client
+            .query()
+            .table_name(&self.table_name)
+            .key_condition_expression("service_id = :service_id")
+            .expression_attribute_values(":service_id", AttributeValue::S(service_id.to_string()))
+            .into_paginator()
+            .items()
+            .send()
+            .collect()
+            .await;
+
+        let items = items.map_err(|e| ScrabError::RequestError(dynamodb::Error::from(e).to_string()))?;
+        Ok(items.iter().map(Self::from_item).collect())
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ HANDLERS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Validate the feedback sent by the EHR and store it in the `FEEDBACK_TABLE` DynamoDB table
+pub async fn save_feedback(
+    body: &str,
+    service_id: &str,
+) -> Result<usize, ScrabError> {
+    let outcomes = parse_feedback(body, service_id)?;
+    let saved = match DynamoFeedbackStore::shared().await {
+        Ok(store) => store.save(&outcomes).await.map(|_| outcomes.len()),
+        Err(e) => Err(e),
+    };
+
+    match &saved {
+        Ok(count) => info!("Saved {} feedback outcomes for {}", count, service_id),
+        Err(e) => error!("Error saving feedback: {:?}", e),
+    }
+    saved
+}
+
+/// Aggregate acceptance report for one service
+pub async fn get_acceptance_report(
+    service_id: &str,
+) -> Result<AcceptanceReport, ScrabError> {
+    DynamoFeedbackStore::shared().await?.report(service_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const TIMESTAMP: &str = "2025-03-31T12:00:00Z";
+
+    fn accepted(card: &str) -> serde_json::Value {
+        json!({
+            "card": card,
+            "outcome": "accepted",
+            "acceptedSuggestions": [{ "id": "suggestion-1" }],
+            "outcomeTimestamp": TIMESTAMP
+        })
+    }
+
+    fn overridden(card: &str, reason: Option<&str>) -> serde_json::Value {
+        let mut item = json!({ "card": card, "outcome": "overridden", "outcomeTimestamp": TIMESTAMP });
+        if let Some(code) = reason {
+            item["overrideReason"] = json!({
+                "reason": { "code": code, "system": "urn:medicompass:cds:override-reason" },
+                "userComment": "Discussed with the patient"
+            });
+        }
+        item
+    }
+
+    fn parse(items: Vec<serde_json::Value>) -> Result<Vec<FeedbackOutcome>, ScrabError> {
+        parse_feedback(&json!({ "feedback": items }).to_string(), "medication")
+    }
+
+    /// Process-local store standing in for DynamoDB
+    #[derive(Default)]
+    struct MemoryFeedbackStore {
+        outcomes: std::sync::Mutex<Vec<FeedbackOutcome>>,
+    }
+
+    impl FeedbackStore for MemoryFeedbackStore {
+        async fn save(&self, outcomes: &[FeedbackOutcome]) -> Result<(), ScrabError> {
+            self.outcomes.lock().unwrap().extend(outcomes.iter().cloned());
+            Ok(())
+        }
+
+        async fn list(&self, service_id: &str) -> Result<Vec<FeedbackOutcome>, ScrabError> {
+            let stored = self.outcomes.lock().unwrap();
+            Ok(stored.iter().filter(|o| o.service_id == service_id).cloned().collect())
+        }
+    }
+
+    #[test]
+    fn parses_accepted_and_overridden_feedback() {
+        let outcomes = parse(vec![accepted("card-1"), overridden("card-2", Some("already-reviewed"))]).unwrap();
+
+        assert_eq!(outcomes[0].card_uuid, "card-1");
+        assert_eq!(outcomes[0].accepted_suggestions, vec!["suggestion-1"]);
+        assert_eq!(outcomes[1].override_reason.as_deref(), Some("already-reviewed"));
+        assert_eq!(outcomes[1].user_comment.as_deref(), Some("Discussed with the patient"));
+        assert!(outcomes.iter().all(|o| o.service_id == "medication"));
+    }
+
+    #[test]
+    fn stores_each_accepted_suggestion_once() {
+        let mut repeated = accepted("card-1");
+        repeated["acceptedSuggestions"] = json!([{ "id": "suggestion-1" }, { "id": "suggestion-2" }, { "id": "suggestion-1" }]);
+
+        let outcomes = parse(vec![repeated, overridden("card-2", None)]).unwrap();
+        assert_eq!(outcomes[0].accepted_suggestions, vec!["suggestion-1", "suggestion-2"]);
+
+        let item = DynamoFeedbackStore::to_item(&outcomes[1]);
+        assert!(!item.contains_key("accepted_suggestions"), "empty string sets are rejected by DynamoDB");
+    }
+
+    #[test]
+    fn rejects_invalid_feedback() {
+        let mut accepted_without_suggestions = accepted("card-1");
+        accepted_without_suggestions["acceptedSuggestions"] = json!([]);
+
+        let mut overridden_with_suggestions = overridden("card-1", None);
+        overridden_with_suggestions["acceptedSuggestions"] = json!([{ "id": "suggestion-1" }]);
+
+        let mut accepted_with_override = accepted("card-1");
+        accepted_with_override["overrideReason"] = json!({ "userComment": "No" });
+
+        let mut unknown_outcome = accepted("card-1");
+        unknown_outcome["outcome"] = json!("ignored");
+
+        let mut invalid_timestamp = accepted("card-1");
+        invalid_timestamp["outcomeTimestamp"] = json!("yesterday");
+
+        for (case, item) in [
+            ("accepted without acceptedSuggestions", accepted_without_suggestions),
+            ("overridden with acceptedSuggestions", overridden_with_suggestions),
+            ("accepted with an overrideReason", accepted_with_override),
+            ("unknown outcome", unknown_outcome),
+            ("invalid outcomeTimestamp", invalid_timestamp),
+            ("empty card uuid", accepted(" ")),
+        ] {
+            // One invalid item rejects the whole body
+            let result = parse(vec![accepted("card-0"), item]);
+            assert!(matches!(result, Err(ScrabError::Validation(_))), "accepted {}", case);
+        }
+
+        assert!(parse(vec![]).is_err());
+        assert!(parse_feedback("not json", "medication").is_err());
+    }
+
+    #[test]
+    fn counts_outcomes_in_acceptance_report() {
+        let mut outcomes = parse(vec![
+            accepted("card-1"),
+            accepted("card-2"),
+            accepted("card-3"),
+            overridden("card-4", Some("not-applicable")),
+            overridden("card-5", Some("not-applicable")),
+            overridden("card-6", None),
+        ]).unwrap();
+        outcomes.extend(parse_feedback(&json!({ "feedback": [accepted("card-7")] }).to_string(), "medication-order-sign").unwrap());
+
+        let report = AcceptanceReport::from_outcomes("medication", &outcomes);
+        assert_eq!((report.total, report.accepted, report.overridden), (6, 3, 3));
+        assert_eq!(report.acceptance_rate, 0.5);
+        assert_eq!(report.override_reasons.get("not-applicable"), Some(&2));
+        assert_eq!(report.override_reasons.get("unspecified"), Some(&1));
+
+        let empty = AcceptanceReport::from_outcomes("medication-order-select", &outcomes);
+        assert_eq!((empty.total, empty.acceptance_rate), (0, 0.0));
+    }
+
+    #[tokio::test]
+    async fn reports_stored_outcomes() {
+        let store = MemoryFeedbackStore::default();
+        store.save(&parse(vec![accepted("card-1"), overridden("card-2", None)]).unwrap()).await.unwrap();
+
+        let report = store.report("medication").await.unwrap();
+        assert_eq!((report.total, report.accepted), (2, 1));
+        assert_eq!(store.report("medication-order-sign").await.unwrap().total, 0);
+    }
+}
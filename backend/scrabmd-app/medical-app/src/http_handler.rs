@@ -1,8 +1,18 @@
-use lambda_http::{Body, Request, Error, Response};
+use lambda_http::{Body, Request, RequestExt, Error, Response};
+use lambda_http::http::Method;
 use lambda_http::tracing::{error, info};
 use crate::libs::manage_hook_data;
+use crate::order_review::manage_order_hook;
+use crate::cds_services::{CdsService, SERVICES, find_service};
+use crate::feedback::{save_feedback, get_acceptance_report};
+use crate::cds_auth::authorize_hook_call;
+use crate::prefetch::complete_prefetch;
+use crate::narrative_cache::launch_narrative;
+use crate::launch_token::LaunchSigner;
 use crate::http_page::get_main_page;
+use crate::scrab_errors::ScrabError;
 use serde_json::json;
+use scrab_cds::{Discovery, HookRequest};
 use url::Url;
 
 pub(crate) async fn function_handler(event: Request) -> Result<Response<Body>, Error> {
@@ -10,11 +20,30 @@ pub(crate) async fn function_handler(event: Request) -> Result<Response<Body>, E
     info!("Event: {:?}", event);
     // info!("Body: {:?}", event.body());
     let api_url = event.uri().to_string();
-    let path_fm = event.uri().path();
+    // `/cds-services/` and `/cds-services` are the same endpoint
+    let path_fm = event.uri().path().trim_end_matches('/');
 
     if extract_uri_path(&api_url) == "launch" {
         info!("Services path launch");
-        let body = get_main_page("no json data");
+        // Narrative that missed the hook's latency budget, if the card linked one.
+        // It holds PHI, so the link must carry the launch token signed for its hook call.
+        let query = event.query_string_parameters_ref();
+        let narrative = match query.and_then(|q| q.first("hookInstance")) {
+            Some(hook_instance) => {
+                let token = query.and_then(|q| q.first("token")).unwrap_or_default();
+                if let Err(e) = LaunchSigner::shared().and_then(|signer| signer.verify(hook_instance, token)) {
+                    error!("Unauthorized narrative launch for {}: {:?}", hook_instance, e);
+                    return Ok(Response::builder()
+                        .status(401)
+                        .header("content-type", "application/json")
+                        .body(json!({ "error": "Unauthorized" }).to_string().into())
+                        .map_err(Box::new)?);
+                }
+                launch_narrative(hook_instance).await
+            }
+            None => None,
+        };
+        let body = get_main_page(narrative.as_deref());
         return Ok(Response::builder()
             .status(200)
             .header("content-type", "text/html")
@@ -34,81 +63,187 @@ pub(crate) async fn function_handler(event: Request) -> Result<Response<Body>, E
         Body::Text(text) => text,
         _ => ""
     };
-    let body_resp: String;
 
-    match path.as_str() {
-        "cds-services/0001" => {
-            info!("Services path cds-services-0001");
-            body_resp = handle_patient_view(&body_string, &api_url).await;
+    // Hook and feedback calls must carry a valid CDS client JWT
+    let service_id = path.strip_suffix("/feedback")
+        .or_else(|| path.strip_prefix("cds-services/"));
+    if service_id.and_then(find_service).is_some() || path == "cds-services/0001" {
+        let authorization = event.headers()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok());
+
+        match authorize_hook_call(authorization, &get_service_url(&api_url)).await {
+            Ok(claims) => info!("CDS client {} ({:?}) authorized", claims.iss, claims.sub),
+            Err(e) => {
+                let status = auth_error_status(&e);
+                if status != 401 {
+                    error!("Could not authorize the hook call: {:?}", e);
+                    return json_response(status, json!({ "error": "Authorization is unavailable" }).to_string());
+                }
+                error!("Unauthorized hook call: {:?}", e);
+                return Ok(Response::builder()
+                    .status(401)
+                    .header("content-type", "application/json")
+                    .header("www-authenticate", "Bearer")
+                    .body(json!({ "error": "Unauthorized" }).to_string().into())
+                    .map_err(Box::new)?);
+            }
         }
-        "cds-services/medication" => {
-            info!("Services path cds-services-medication");
-            body_resp = handle_patient_view(&body_string, &api_url).await;
+    }
+
+    if let Some(service_id) = path.strip_suffix("/feedback") {
+        info!("Services path feedback {}", service_id);
+        return handle_feedback(event.method(), body_string, service_id).await;
+    }
+
+    if path == "cds-services" || path.ends_with("/cds-services") {
+        info!("Services path cds-services");
+        return json_response(200, handle_discovery());
+    }
+
+    // The original patient-view endpoint is kept as an alias of "medication"
+    let service_id = if path == "cds-services/0001" {
+        Some("medication")
+    } else {
+        path.strip_prefix("cds-services/")
+    };
+
+    match service_id.and_then(find_service) {
+        Some(service) => {
+            info!("Services path cds-services-{}", service.id);
+            handle_hook(body_string, service, &api_url).await
         }
-        _ => {
-            info!("Services path cds-services-0001");
-            body_resp = handle_discovery();
+        None => {
+            let unknown = service_id.unwrap_or(&path);
+            handle_error(&ScrabError::UnknownService(unknown.to_string()))
         }
     }
-
-    Ok(Response::builder()
-        .status(200)
-        .header("content-type", "application/json")
-        .body(body_resp.into())
-        .map_err(Box::new)?)
 }
 
-// Function Handle Patient View
-pub async fn handle_patient_view(
+// Function Handle Hook call (patient-view, order-select, order-sign)
+pub async fn handle_hook(
     hook_data: &str,
+    service: &CdsService,
     api_url: &str,
-) -> String {
-    match manage_hook_data(hook_data, api_url).await {
-        Ok(body) => body,
-        Err(error) => {
-            error!("Error: {:?}", error);
-            handle_error()
+) -> Result<Response<Body>, Error> {
+    let mut request = match HookRequest::from_json(hook_data) {
+        Ok(request) => request,
+        Err(e) => {
+            return handle_error(&ScrabError::Validation(format!("Malformed hook request: {}", e)));
         }
+    };
+
+    if request.hook != service.hook {
+        return handle_error(&ScrabError::Validation(format!(
+            "Service {} does not handle the {} hook", service.id, request.hook
+        )));
+    }
+
+    complete_prefetch(&mut request, service).await;
+
+    let missing = service.missing_prefetch(&request.prefetch);
+    if !missing.is_empty() {
+        return handle_error(&ScrabError::MissingPrefetch(missing.join(", ")));
+    }
+
+    let response = match service.hook {
+        "order-select" | "order-sign" => manage_order_hook(&request, service, api_url).await,
+        _ => manage_hook_data(&request, service, api_url).await,
+    };
+
+    match response {
+        Ok(response) => json_response(200, response.to_json()),
+        Err(error) => handle_error(&error),
     }
 }
 
+// Function Handle Feedback
+pub async fn handle_feedback(
+    method: &Method,
+    hook_data: &str,
+    service_id: &str,
+) -> Result<Response<Body>, Error> {
+    let (status, body_resp) = if find_service(service_id).is_none() {
+        (404, json!({ "error": format!("Unknown service: {}", service_id) }))
+    } else if method == Method::GET {
+        match get_acceptance_report(service_id).await {
+            Ok(report) => (200, json!(report)),
+            Err(error) => {
+                error!("Error: {:?}", error);
+                (500, json!({ "error": "Could not build the acceptance report" }))
+            }
+        }
+    } else if method != Method::POST {
+        (405, json!({ "error": format!("Method {} not allowed", method) }))
+    } else {
+        match save_feedback(hook_data, service_id).await {
+            Ok(_) => (200, json!({})),
+            Err(ScrabError::Validation(message)) => (400, json!({ "error": message })),
+            Err(error) => {
+                error!("Error: {:?}", error);
+                (500, json!({ "error": "Could not store the feedback" }))
+            }
+        }
+    };
+
+    Ok(Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(body_resp.to_string().into())
+        .map_err(Box::new)?)
+}
+
 // Function Handle Discovery
 pub fn handle_discovery() -> String {
-    let body = json!({ 
-        "services": [
-            {
-                "hook": "patient-view",
-                "title": "Patient Medication",
-                "description": "Patient medication description",
-                "id": "medication",
-                "prefetch": {
-                    "patient": "Patient/{{context.patientId}}",
-                    "medication": "Medication?patient={{context.patientId}}",
-                    "medications_stat": "MedicationStatement?patient={{context.patientId}}",
-                    "medication_req": "MedicationRequest?patient={{context.patientId}}"
-                }
-            }
-        ]
-    });
-    
-    body.to_string()
+    let discovery = Discovery::new(
+        SERVICES.iter().map(CdsService::descriptor).collect()
+    );
+
+    discovery.to_value().to_string()
 }
 
-fn handle_error() -> String {
-       
-    let body = json!({ 
-        "cards": [
-            {
-                "summary": "patient-view",
-                "indicator": "info",
-                "source": {
-                    "label": "No event"
-                }
-            }
-        ]
-    });
+// Map a hook failure to its CDS Hooks status code
+fn handle_error(error: &ScrabError) -> Result<Response<Body>, Error> {
+    let status = match error {
+        ScrabError::Validation(_) | ScrabError::InvalidResponseFormat(_) => 400,
+        ScrabError::UnknownService(_) => 404,
+        ScrabError::MissingPrefetch(_) => 412,
+        ScrabError::ServiceUnavailable(_) => 503,
+        _ => 500,
+    };
+    error!("Hook call failed with {}: {:?}", status, error);
 
-    body.to_string()
+    json_response(status, json!({ "error": error.to_string() }).to_string())
+}
+
+// Only bad credentials are the caller's fault, a broken configuration or jti store is ours
+fn auth_error_status(error: &ScrabError) -> u16 {
+    match error {
+        ScrabError::Authentication(_) => 401,
+        ScrabError::ServiceUnavailable(_) => 503,
+        _ => 500,
+    }
+}
+
+fn json_response(status: u16, body: String) -> Result<Response<Body>, Error> {
+    Ok(Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(body.into())
+        .map_err(Box::new)?)
+}
+
+// Service endpoint URL used as the expected JWT audience
+fn get_service_url(uri: &str) -> String {
+    match Url::parse(uri) {
+        Ok(mut parsed_url) => {
+            parsed_url.set_query(None);
+            let url = parsed_url.to_string();
+            let url = url.trim_end_matches('/');
+            url.strip_suffix("/feedback").unwrap_or(url).to_string()
+        }
+        Err(_) => uri.to_string(),
+    }
 }
 
 fn extract_uri_path(uri: &str) -> String {
@@ -117,7 +252,7 @@ fn extract_uri_path(uri: &str) -> String {
         let path = parsed_url.path();
         
         // Extract just the last path segment
-        if let Some(last_segment) = path.split('/').last() {
+        if let Some(last_segment) = path.split('/').next_back() {
             if !last_segment.is_empty() {
                 println!("Path: /{}", last_segment);
                 return last_segment.to_string();
@@ -132,5 +267,110 @@ fn extract_uri_path(uri: &str) -> String {
             }
         }
     }
-    return "".to_string();
-}
\ No newline at end of file
+    "".to_string()
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_engine::llm_failure;
+    use lambda_http::http::StatusCode;
+    use scrab_cds::Indicator;
+    use std::collections::HashMap;
+
+    async fn get(uri: &str) -> Response<Body> {
+        let event = lambda_http::http::Request::builder()
+            .uri(uri)
+            .body(Body::Empty)
+            .unwrap();
+        function_handler(event).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn launch_requires_a_signed_token() {
+        let launch = |query: &[(&str, &str)]| {
+            let parameters: HashMap<String, String> = query.iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+            lambda_http::http::Request::builder()
+                .uri("https://cds.example.org/v1/launch")
+                .body(Body::Empty)
+                .unwrap()
+                .with_query_string_parameters(parameters)
+        };
+
+        for query in [&[("hookInstance", "hook-1")][..], &[("hookInstance", "hook-1"), ("token", "forged")]] {
+            let response = function_handler(launch(query)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{:?}", query);
+        }
+
+        // The app itself opens without a narrative
+        assert_eq!(function_handler(launch(&[])).await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn maps_errors_to_cds_hooks_status() {
+        let malformed = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+
+        for (error, status) in [
+            (ScrabError::Validation("Malformed hook request".into()), 400),
+            (ScrabError::InvalidResponseFormat(malformed), 400),
+            (ScrabError::UnknownService("unknown".into()), 404),
+            (ScrabError::MissingPrefetch("medications_stat".into()), 412),
+            (ScrabError::ServiceUnavailable("LLM timed out".into()), 503),
+            (ScrabError::GenericError("unexpected".into()), 500),
+        ] {
+            let response = handle_error(&error).unwrap();
+            assert_eq!(response.status().as_u16(), status, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn keeps_401_for_bad_credentials_only() {
+        for (error, status) in [
+            (ScrabError::Authentication("Token jti was already used".into()), 401),
+            (ScrabError::Configuration("CDS_TRUSTED_ISSUERS must be set".into()), 500),
+            (ScrabError::ServiceUnavailable("jti store: throttled".into()), 503),
+        ] {
+            assert_eq!(auth_error_status(&error), status, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn llm_failures_keep_the_rule_cards() {
+        let card = llm_failure("LLM timed out".into(), "order review", true, false).unwrap().unwrap();
+        assert_eq!(card.summary, "AI analysis unavailable");
+        assert_eq!(card.indicator, Indicator::Warning);
+        assert!(card.detail.unwrap().contains("order review"));
+        assert!(llm_failure("LLM timed out".into(), "order review", true, true).unwrap().is_some());
+
+        // Without degraded mode the rule cards still answer, 503 only without them
+        assert_eq!(llm_failure("LLM timed out".into(), "order review", false, true).unwrap(), None);
+        let error = llm_failure("LLM timed out".into(), "order review", false, false).unwrap_err();
+        assert_eq!(handle_error(&error).unwrap().status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn feedback_only_accepts_post_and_get() {
+        for method in [Method::PUT, Method::DELETE, Method::PATCH] {
+            let response = handle_feedback(&method, "{}", "medication-order-sign").await.unwrap();
+            assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED, "{}", method);
+        }
+    }
+
+    #[tokio::test]
+    async fn normalizes_trailing_slashes() {
+        for (uri, status) in [
+            ("https://cds.example.org/v1/cds-services", StatusCode::OK),
+            ("https://cds.example.org/v1/cds-services/", StatusCode::OK),
+            ("https://cds.example.org/v1/cds-services/unknown/", StatusCode::NOT_FOUND),
+        ] {
+            assert_eq!(get(uri).await.status(), status, "{}", uri);
+        }
+
+        let discovery: serde_json::Value = match get("https://cds.example.org/v1/cds-services/").await.into_body() {
+            Body::Text(text) => serde_json::from_str(&text).unwrap(),
+            body => panic!("unexpected body {:?}", body),
+        };
+        assert_eq!(discovery["services"].as_array().unwrap().len(), SERVICES.len());
+    }
+}
@@ -1,6 +1,4 @@
-pub fn get_main_page(
-    json_data: &str,
-) -> String {
+pub fn get_main_page(analysis: Option<&str>) -> String {
     let response = r#"
     <!DOCTYPE html>
     <html lang="en">
@@ -223,6 +221,7 @@ pub fn get_main_page(
                 <h2 class="section-title">CURRENT MEDICATIONS</h2>
                 <div id="medicationsContainer"></div>
             </div>
+            <<analysis>>
         </div>
 
         <script>
@@ -315,5 +314,28 @@ pub fn get_main_page(
     "#.to_string();
 
     // let response_fmt = response.replace("<<patient_data>>", json_data);
-    response
+    response.replace("<<analysis>>", &get_analysis_section(analysis))
+}
+
+// AI narrative cached for the hook call that launched the app
+fn get_analysis_section(analysis: Option<&str>) -> String {
+    let Some(analysis) = analysis else {
+        return String::new();
+    };
+
+    let escaped = analysis
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\n', "<br>");
+
+    format!(
+        r#"<div class="medications">
+                <h2 class="section-title">AI MEDICATION ANALYSIS</h2>
+                <div class="medication-card">
+                    <div class="medication-details">{}</div>
+                </div>
+            </div>"#,
+        escaped,
+    )
 }
\ No newline at end of file
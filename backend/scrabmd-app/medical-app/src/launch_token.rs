@@ -0,0 +1,110 @@
+use chrono::Utc;
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::sync::OnceLock;
+use crate::scrab_errors::ScrabError;
+
+// How long the link of a pending card opens its narrative
+const LAUNCH_TOKEN_TTL_SECS: i64 = 30 * 60; // 30 minutes
+
+// Audience that keeps launch tokens from being used as any other HS256 token
+const LAUNCH_AUDIENCE: &str = "medicompass-launch";
+
+/// Claims of the token that lets a card link open the narrative of its hook call
+#[derive(Debug, Serialize, Deserialize)]
+struct LaunchClaims {
+    sub: String,
+    aud: String,
+    exp: i64,
+}
+
+/// HMAC key of the launch links, the narrative holds PHI and the link has no other credential
+pub struct LaunchSigner {
+    secret: Vec<u8>,
+}
+
+impl LaunchSigner {
+    pub fn new(secret: &[u8]) -> Self {
+        Self { secret: secret.to_vec() }
+    }
+
+    /// Load from `LAUNCH_TOKEN_SECRET`
+    pub fn from_env() -> Result<Self, ScrabError> {
+        let secret = env::var("LAUNCH_TOKEN_SECRET")
+            .ok()
+            .filter(|secret| !secret.trim().is_empty())
+            .ok_or_else(|| ScrabError::Configuration("LAUNCH_TOKEN_SECRET must be set".to_string()))?;
+        Ok(Self::new(secret.as_bytes()))
+    }
+
+    /// Shared signer, loaded once per Lambda instance
+    pub fn shared() -> Result<&'static LaunchSigner, ScrabError> {
+        static SIGNER: OnceLock<LaunchSigner> = OnceLock::new();
+        if let Some(signer) = SIGNER.get() {
+            return Ok(signer);
+        }
+        let signer = Self::from_env()?;
+        Ok(SIGNER.get_or_init(|| signer))
+    }
+
+    /// Short-lived HS256 token for the narrative of `hook_instance`
+    pub fn sign(&self, hook_instance: &str, now: i64) -> Result<String, ScrabError> {
+        let claims = LaunchClaims {
+            sub: hook_instance.to_string(),
+            aud: LAUNCH_AUDIENCE.to_string(),
+            exp: now + LAUNCH_TOKEN_TTL_SECS,
+        };
+        encode(&Header::new(Algorithm::HS256), &claims, &EncodingKey::from_secret(&self.secret))
+            .map_err(|e| ScrabError::GenericError(format!("Could not sign the launch token: {}", e)))
+    }
+
+    /// Check that `token` was signed for `hook_instance` and has not expired
+    pub fn verify(&self, hook_instance: &str, token: &str) -> Result<(), ScrabError> {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.leeway = 0;
+        validation.set_audience(&[LAUNCH_AUDIENCE]);
+        validation.set_required_spec_claims(&["exp", "aud", "sub"]);
+
+        let claims = decode::<LaunchClaims>(token, &DecodingKey::from_secret(&self.secret), &validation)
+            .map_err(|e| ScrabError::Authentication(format!("Invalid launch token: {}", e)))?
+            .claims;
+
+        if claims.sub != hook_instance {
+            return Err(ScrabError::Authentication("Launch token is for another hook call".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Launch token of a pending card, signed with `LAUNCH_TOKEN_SECRET`
+pub fn launch_token(hook_instance: &str) -> Result<String, ScrabError> {
+    LaunchSigner::shared()?.sign(hook_instance, Utc::now().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_token_of_the_hook_instance() {
+        let signer = LaunchSigner::new(b"launch-secret");
+        let token = signer.sign("hook-1", Utc::now().timestamp()).unwrap();
+        assert!(signer.verify("hook-1", &token).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_launch_tokens() {
+        let signer = LaunchSigner::new(b"launch-secret");
+        let now = Utc::now().timestamp();
+
+        let expired = signer.sign("hook-1", now - LAUNCH_TOKEN_TTL_SECS - 1).unwrap();
+        let other_key = LaunchSigner::new(b"other-secret").sign("hook-1", now).unwrap();
+        let other_hook = signer.sign("hook-2", now).unwrap();
+
+        for (case, token) in [("expired", expired), ("other key", other_key), ("other hook", other_hook)] {
+            assert!(matches!(signer.verify("hook-1", &token), Err(ScrabError::Authentication(_))), "accepted {}", case);
+        }
+        assert!(signer.verify("hook-1", "not-a-token").is_err());
+    }
+}
@@ -1,65 +1,47 @@
-use serde_json::{json, Value, from_str};
-use lambda_http::tracing::{error, warn, info};
-use crate::llm_engine::manage_medication;
+use chrono::Utc;
+use lambda_http::tracing::{warn, info};
+use scrab_cds::{Card, CdsResponse, HookRequest, Indicator, Prefetch};
+use scrab_fhir::{MedicationChoice, MedicationStatement};
+use crate::llm_engine::{degraded_mode_enabled, llm_failure};
+use crate::cards::{
+    app_link, duplicate_card, high_risk_card, info_card, narrative_link, pending_card,
+};
+use crate::cds_services::CdsService;
+use crate::narrative_cache::{Narrative, NarrativeJob, narrative_within_budget};
+use crate::launch_token::launch_token;
+use crate::med_rules::{extract_medication_items, find_duplicates_within, find_high_risk_within};
+use crate::system_actions::build_system_actions;
 use crate::scrab_errors::ScrabError;
 use url::Url;
 
-fn extract_medications_stat(json_str: &str) -> Vec<String> {
-    // Parse the JSON string into a serde_json::Value
-    let v: Value = match from_str(json_str) {
-        Ok(value) => value,
-        Err(e) => {
-            warn!("Failed to parse JSON: {}", e);
-            return vec![];
-        }
-    };
+/// Medications from `prefetch.medications_stat`.
+/// Returns None when the prefetch itself is missing, as opposed to an empty Bundle.
+fn extract_medications_stat(prefetch: &Prefetch) -> Option<Vec<String>> {
+    let bundle = prefetch.bundle("medications_stat")?;
 
-    // Navigate to the "prefetch.medications_stat.entry" array
-    let entries = match v.get("prefetch")
-        .and_then(|p| p.get("medications_stat"))
-        .and_then(|m| m.get("entry"))
-        .and_then(|e| e.as_array()) {
-        Some(array) => array,
-        None => {
-            warn!("No medications entry array found");
-            return vec![];
-        }
-    };
+    if bundle.entry.is_empty() {
+        warn!("No medications entry array found");
+    }
 
     // Extract details from each MedicationStatement resource
-    let medications: Vec<String> = entries.iter().filter_map(|entry| {
-        let resource = entry.get("resource")?;
-        
-        let name = resource.get("medicationCodeableConcept")
-            .and_then(|mc| mc.get("text"))
-            .and_then(|t| t.as_str())
+    let medications: Vec<String> = bundle.typed::<MedicationStatement>().map(|statement| {
+        let name = statement.medication.as_ref()
+            .and_then(MedicationChoice::display)
             .unwrap_or("Unknown");
 
-        let status = resource.get("status")
-            .and_then(|s| s.as_str())
-            .unwrap_or("Unknown");
+        let status = if statement.status.is_empty() { "Unknown" } else { &statement.status };
+
+        let effective = statement.effective.as_ref()
+            .map(|effective| effective.to_string())
+            .unwrap_or_else(|| "Unknown".to_string());
 
-        let effective = if let Some(date_time) = resource.get("effectiveDateTime").and_then(|dt| dt.as_str()) {
-            date_time.to_string()
-        } else if let Some(period) = resource.get("effectivePeriod").and_then(|p| p.as_object()) {
-            let start = period.get("start").and_then(|s| s.as_str()).unwrap_or("Unknown");
-            let end = period.get("end").and_then(|e| e.as_str()).unwrap_or("Unknown");
-            format!("from {} to {}", start, end)
-        } else {
-            "Unknown".to_string()
-        };
-
-        Some(format!("{} (status: {}, effective: {})", name, status, effective))
+        format!("{} (status: {}, effective: {})", name, status, effective)
     }).collect();
 
-    medications
+    Some(medications)
 }
 
-pub async fn manage_hook_data(
-    hook_data: &str,
-    api_url: &str,
-) -> Result<String, ScrabError> {
-
+pub fn get_smart_app_uri(api_url: &str) -> String {
     let mut base_url = String::new();
 
     if let Ok(parsed_url) = Url::parse(api_url) {
@@ -68,57 +50,76 @@ pub async fn manage_hook_data(
         if path_segments.len() >= 2 {
             // Build the base URL
             base_url = parsed_url.origin().ascii_serialization();
-            base_url.push_str("/");
+            base_url.push('/');
             base_url.push_str(path_segments[1]);
         }
     }
 
-    let smart_app_uri = format!("{}/launch", base_url);
+    format!("{}/launch", base_url)
+}
+
+pub async fn manage_hook_data(
+    request: &HookRequest,
+    service: &CdsService,
+    api_url: &str,
+) -> Result<CdsResponse, ScrabError> {
+
+    let smart_app_uri = get_smart_app_uri(api_url);
     info!("Smart App URI: {}", smart_app_uri);
 
-    let medications_stat = extract_medications_stat(hook_data);
+    // Rule-based duplicate therapy cards from the prefetched medications
+    let prefetch = &request.prefetch;
+    let mut medications = extract_medication_items(prefetch.bundle("medications_stat"));
+    medications.extend(extract_medication_items(prefetch.bundle("medication_req")));
+
+    let high_risk = find_high_risk_within(&medications);
+    let system_actions = build_system_actions(service.system_actions, &high_risk, &request.context, Utc::now());
+
+    let mut cards: Vec<Card> = find_duplicates_within(&medications)
+        .iter()
+        .map(duplicate_card)
+        .collect();
+    cards.extend(high_risk.iter().map(high_risk_card));
+
+    let medications_stat = extract_medications_stat(&request.prefetch);
     let mut medications_result = String::new();
-    let mut response: String;
-    
-    for med in medications_stat {
-        medications_result.push_str(&med);
-        medications_result.push_str("\n");
+
+    for med in medications_stat.iter().flatten() {
+        medications_result.push_str(med);
+        medications_result.push('\n');
     }
 
-    if !medications_result.is_empty() {
-        response = match manage_medication(&medications_result).await {
-            Ok(result) => result,
-            Err(e) => {
-                error!("Error managing medications: {:?}", e);
-                "The AI model does not want to work. Try again later. ".to_string()
+    let mut response = if medications_stat.is_none() {
+        warn!("Prefetch medications_stat is not available");
+        "The patient's medication list could not be retrieved from the EHR. ".to_string()
+    } else if !medications_result.is_empty() {
+        let job = NarrativeJob::Medication { medications: medications_result };
+
+        match narrative_within_budget(job, service.latency_budget(), &request.hook_instance, service.id).await {
+            Narrative::Ready(result) => result,
+            Narrative::Pending => {
+                let token = launch_token(&request.hook_instance)?;
+                cards.push(pending_card("Medication", "medication analysis")
+                    .with_links(vec![narrative_link(&smart_app_uri, &request.hook_instance, &token)]));
+                return Ok(CdsResponse::new(cards).with_system_actions(system_actions));
             }
-        };
+            Narrative::Failed(e) => {
+                let rule_output = !cards.is_empty() || !system_actions.is_empty();
+                if let Some(card) = llm_failure(e, "medication analysis", degraded_mode_enabled(), rule_output)? {
+                    cards.push(card.with_links(vec![app_link(&smart_app_uri)]));
+                }
+                return Ok(CdsResponse::new(cards).with_system_actions(system_actions));
+            }
+        }
     } else {
         info!("No medications found");
-        response = "No medications were found that the patient is currently taking. ".to_string();
-    }
+        "No medications were found that the patient is currently taking. ".to_string()
+    };
 
     response.push_str("\n\nClick the button if you want a more detailed report of the patient's medications.");
 
-    let body = json!({ 
-        "cards": [
-            {
-                "summary": "Medication",
-                "indicator": "info",
-                "source": {
-                    "label": "test service"
-                },
-                "detail": &response,
-                "links": [
-                    {
-                        "label": "MediCompass App",
-                        "url": &smart_app_uri,
-                        "type": "smart"
-                    }
-                ]
-            }
-        ]
-    });
+    cards.push(info_card("Medication", Indicator::Info, &response)
+        .with_links(vec![app_link(&smart_app_uri)]));
 
-    Ok(body.to_string())
-}
\ No newline at end of file
+    Ok(CdsResponse::new(cards).with_system_actions(system_actions))
+}
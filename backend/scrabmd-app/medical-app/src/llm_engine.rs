@@ -1,14 +1,54 @@
-use crate::gemini::chat::ChatGemini;
-use lambda_http::tracing::info;
+use scrab_gemini::chat::ChatGemini;
+use lambda_http::tracing::{error, info, warn};
+use scrab_cds::Card;
+use crate::cards::degraded_card;
+use crate::scrab_errors::ScrabError;
 // use serde_json::{Value, json};
 use chrono::prelude::*;
+use std::env;
+
+// Bounds of one Gemini call. A hook only waits for its latency budget, so a slow
+// model must fail fast enough for the late narrative to be stored before the
+// Lambda times out.
+const LLM_TIMEOUT_SECS: u64 = 20;
+const LLM_MAX_RETRIES: u32 = 1;
+
+/// When `CDS_DEGRADED_MODE` is enabled a failed LLM call is reported to the
+/// clinician with a card next to the rule cards
+pub fn degraded_mode_enabled() -> bool {
+    env::var("CDS_DEGRADED_MODE")
+        .is_ok_and(|value| matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+}
+
+/// Card for a failed LLM call in degraded mode. Otherwise the failure is only
+/// logged when the rules still answer the hook, and fails it with a 503 when
+/// there is no rule output to return
+pub fn llm_failure(
+    error: String,
+    analysis: &str,
+    degraded_mode: bool,
+    rule_output: bool,
+) -> Result<Option<Card>, ScrabError> {
+    if degraded_mode {
+        warn!("LLM unavailable, returning the degraded card: {}", error);
+        Ok(Some(degraded_card(analysis)))
+    } else if rule_output {
+        error!("Error in the {}, returning the rule cards only: {}", analysis, error);
+        Ok(None)
+    } else {
+        error!("Error in the {}: {}", analysis, error);
+        Err(ScrabError::ServiceUnavailable(error))
+    }
+}
 
 pub async fn manage_medication(
     medical_data: &str,
 ) -> Result<String, Box<dyn std::error::Error>> {
 
     info!("Medical data: {:?}", medical_data);
-    let llm = ChatGemini::new("gemini-2.0-flash");
+    let llm = ChatGemini::new("gemini-2.0-flash")
+        .with_timeout_sec(LLM_TIMEOUT_SECS)
+        .with_max_retries(LLM_MAX_RETRIES);
 
     let today = Local::now();
     let today_fmt = today.format("%B %-d, %Y");
@@ -40,17 +80,71 @@ pub async fn manage_medication(
 
     let mut med_result = String::new();
 
-    response.candidates.as_ref().map(|candidates| {
-        candidates.iter().for_each(|candidate| {
-            candidate.content.as_ref().map(|content| {
-                content.parts.iter().for_each(|part| {
-                    part.text.as_ref().map(|text| {
-                        med_result.push_str(text);
-                    });
-                });
-            });
-        });
-    });
+    let parts = response.candidates.iter()
+        .flatten()
+        .filter_map(|candidate| candidate.content.as_ref())
+        .flat_map(|content| content.parts.iter());
+
+    for text in parts.filter_map(|part| part.text.as_ref()) {
+        med_result.push_str(text);
+    }
 
     Ok(med_result.replace("*", "").replace("#", ""))
-}
\ No newline at end of file
+}
+pub async fn review_draft_orders(
+    draft_orders: &str,
+    active_medications: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+
+    info!("Draft orders: {:?}", draft_orders);
+    let llm = ChatGemini::new("gemini-2.0-flash")
+        .with_timeout_sec(LLM_TIMEOUT_SECS)
+        .with_max_retries(LLM_MAX_RETRIES);
+
+    let today = Local::now();
+    let today_fmt = today.format("%B %-d, %Y");
+
+    let prompt = format!(
+        "Review the draft orders below against the medications the patient is currently taking, assuming today's date is {}. \
+        \n \
+        # Output Format \
+        \n \
+        - A short list of clinically relevant findings for the ordering clinician. \
+        - Keep each finding to one or two sentences. \
+        \n \
+        # Notes \
+        \n \
+        - Look for therapeutic duplications, drug-drug interactions and contraindicated combinations.\n \
+        - For ServiceRequest orders, mention only findings related to the current medications.\n \
+        - If nothing relevant is found, answer exactly: No issues found.\n \
+        \n\n \
+        # Draft Orders \n \
+        {} \
+        \n\n \
+        # Current Medications \n \
+        {}",
+        today_fmt,
+        draft_orders,
+        active_medications,
+    );
+
+    let response = llm
+        .invoke(&prompt)
+        .await?;
+
+    let mut review_result = String::new();
+
+    if let Some(candidates) = &response.candidates {
+        for candidate in candidates {
+            if let Some(content) = &candidate.content {
+                for part in &content.parts {
+                    if let Some(text) = &part.text {
+                        review_result.push_str(text);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(review_result.replace("*", "").replace("#", ""))
+}
@@ -1,10 +1,20 @@
 use lambda_http::{run, service_fn, tracing, Error};
 mod http_handler;
 mod libs;
-mod gemini;
 mod llm_engine;
 mod scrab_errors;
 mod http_page;
+mod cds_services;
+mod cards;
+mod feedback;
+mod cds_auth;
+mod backend_auth;
+mod prefetch;
+mod med_rules;
+mod order_review;
+mod narrative_cache;
+mod launch_token;
+mod system_actions;
 use http_handler::function_handler;
 
 #[tokio::main]
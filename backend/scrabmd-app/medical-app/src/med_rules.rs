@@ -0,0 +1,525 @@
+use scrab_cds::{Bundle, Resource};
+use scrab_fhir::{
+    Medication, MedicationChoice, MedicationRequest, MedicationStatement, RXNORM_SYSTEM,
+};
+
+// Name shown for a medication that has neither a display nor a resolvable Medication
+pub const UNKNOWN_MEDICATION: &str = "Unknown medication";
+
+/// Therapeutic class matched by ingredient name
+pub struct DrugClass {
+    pub name: &'static str,
+    pub ingredients: &'static [&'static str],
+}
+
+pub const DRUG_CLASSES: &[DrugClass] = &[
+    DrugClass {
+        name: "NSAID",
+        ingredients: &[
+            "ibuprofen", "naproxen", "diclofenac", "ketorolac", "meloxicam",
+            "celecoxib", "indomethacin", "etodolac", "nabumetone", "piroxicam",
+        ],
+    },
+    DrugClass {
+        name: "Anticoagulant",
+        ingredients: &[
+            "warfarin", "apixaban", "rivaroxaban", "dabigatran", "edoxaban",
+            "heparin", "enoxaparin",
+        ],
+    },
+    DrugClass {
+        name: "Antiplatelet",
+        ingredients: &["aspirin", "clopidogrel", "prasugrel", "ticagrelor"],
+    },
+    DrugClass {
+        name: "ACE inhibitor",
+        ingredients: &["lisinopril", "enalapril", "ramipril", "captopril", "benazepril"],
+    },
+    DrugClass {
+        name: "Statin",
+        ingredients: &["atorvastatin", "simvastatin", "rosuvastatin", "pravastatin", "lovastatin"],
+    },
+    DrugClass {
+        name: "SSRI",
+        ingredients: &["fluoxetine", "sertraline", "citalopram", "escitalopram", "paroxetine"],
+    },
+    DrugClass {
+        name: "Opioid",
+        ingredients: &["morphine", "oxycodone", "hydrocodone", "fentanyl", "tramadol", "codeine"],
+    },
+];
+
+/// Pair of drug classes that should not be combined without review
+#[derive(Debug)]
+pub struct HighRiskCombination {
+    pub classes: (&'static str, &'static str),
+    pub risk: &'static str,
+}
+
+pub const HIGH_RISK_COMBINATIONS: &[HighRiskCombination] = &[
+    HighRiskCombination {
+        classes: ("NSAID", "Anticoagulant"),
+        risk: "Increased risk of serious bleeding",
+    },
+    HighRiskCombination {
+        classes: ("Antiplatelet", "Anticoagulant"),
+        risk: "Increased risk of serious bleeding",
+    },
+    HighRiskCombination {
+        classes: ("SSRI", "Anticoagulant"),
+        risk: "Increased risk of bleeding",
+    },
+    HighRiskCombination {
+        classes: ("Opioid", "SSRI"),
+        risk: "Risk of serotonin syndrome",
+    },
+];
+
+/// Medication extracted from a MedicationStatement or MedicationRequest
+#[derive(Debug, Clone, PartialEq)]
+pub struct MedicationItem {
+    pub reference: Option<String>,
+    /// None when the medication has no display and its Medication could not be resolved
+    pub name: Option<String>,
+    pub rxnorm: Option<String>,
+    /// `Medication/{id}` of a `medicationReference` to a resource outside the order
+    pub medication: Option<String>,
+    pub status: String,
+    /// References of the orders a MedicationStatement records (`basedOn`)
+    pub based_on: Vec<String>,
+    pub resource: Resource,
+}
+
+impl MedicationItem {
+    /// Medication of a statement or request, resolving a `medicationReference`
+    /// against the contained resources and the other entries of `bundle`
+    pub fn from_resource(resource: &Resource, bundle: Option<&Bundle>) -> Option<Self> {
+        let (medication, contained, status, based_on) = match resource.resource_type.as_str() {
+            "MedicationStatement" => {
+                let statement: MedicationStatement = resource.to_typed()?;
+                let based_on = statement.based_on.into_iter().filter_map(|r| r.reference).collect();
+                (statement.medication, statement.contained, statement.status, based_on)
+            }
+            "MedicationRequest" => {
+                let request: MedicationRequest = resource.to_typed()?;
+                (request.medication, request.contained, request.status, vec![])
+            }
+            _ => return None,
+        };
+
+        let reference = match &medication {
+            Some(MedicationChoice::Reference(reference)) => reference.reference.as_deref(),
+            _ => None,
+        };
+        let referenced = reference.and_then(|reference| referenced_medication(reference, &contained, bundle));
+
+        let name = medication.as_ref()
+            .and_then(MedicationChoice::display)
+            .map(str::to_string)
+            .or_else(|| referenced.as_ref().and_then(Medication::display));
+
+        let rxnorm = medication.as_ref()
+            .and_then(|m| m.code_in(RXNORM_SYSTEM))
+            .or_else(|| referenced.as_ref()?.code.as_ref()?.code_in(RXNORM_SYSTEM))
+            .map(|code| code.to_string());
+
+        let status = if status.is_empty() { "unknown".to_string() } else { status };
+
+        Some(Self {
+            reference: resource.reference(),
+            name,
+            rxnorm,
+            medication: reference.and_then(medication_key),
+            status,
+            based_on,
+            resource: resource.clone(),
+        })
+    }
+
+    /// Name to show on cards
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(UNKNOWN_MEDICATION)
+    }
+
+    /// Whether the patient takes, or is about to take, the medication.
+    /// Drafts count so the orders under review are compared with each other.
+    pub fn is_active(&self) -> bool {
+        matches!(self.status.as_str(), "active" | "intended" | "on-hold" | "draft")
+    }
+
+    pub fn drug_class(&self) -> Option<&'static DrugClass> {
+        let name = self.name.as_ref()?.to_lowercase();
+        DRUG_CLASSES.iter().find(|class| {
+            class.ingredients.iter().any(|ingredient| name.contains(ingredient))
+        })
+    }
+}
+
+/// Reason why a draft order overlaps with an active medication
+#[derive(Debug, Clone, PartialEq)]
+pub enum Overlap {
+    SameMedication,
+    SameClass(&'static str),
+}
+
+/// A medication that duplicates something the patient is already taking.
+/// `candidate` is the one a clinician would most likely drop.
+#[derive(Debug, Clone)]
+pub struct Duplicate {
+    pub candidate: MedicationItem,
+    pub existing: MedicationItem,
+    pub overlap: Overlap,
+}
+
+/// `Medication/{id}` of a reference to a Medication outside the resource
+fn medication_key(reference: &str) -> Option<String> {
+    if reference.starts_with('#') {
+        return None;
+    }
+    let id = reference.split("/_history/").next()?.rsplit_once("Medication/")?.1;
+    (!id.is_empty() && !id.contains('/')).then(|| format!("Medication/{}", id))
+}
+
+/// Medication a reference points to, from the contained resources or the bundle
+fn referenced_medication(reference: &str, contained: &[serde_json::Value], bundle: Option<&Bundle>) -> Option<Medication> {
+    if let Some(id) = reference.strip_prefix('#') {
+        return contained.iter()
+            .find(|resource| resource["resourceType"] == "Medication" && resource["id"] == id)
+            .and_then(|resource| serde_json::from_value(resource.clone()).ok());
+    }
+
+    let key = medication_key(reference)?;
+    bundle?.resources_of("Medication")
+        .find(|resource| resource.reference().as_deref() == Some(key.as_str()))?
+        .to_typed()
+}
+
+/// Collect medication items from the entries of a FHIR Bundle
+pub fn extract_medication_items(bundle: Option<&Bundle>) -> Vec<MedicationItem> {
+    bundle
+        .map(|b| b.resources().filter_map(|r| MedicationItem::from_resource(r, Some(b))).collect())
+        .unwrap_or_default()
+}
+
+/// `Type/id` of a relative or absolute reference, without its version
+fn relative_reference(reference: &str) -> Option<&str> {
+    let reference = reference.split("/_history/").next()?;
+    let mut segments = reference.rsplitn(3, '/');
+    let (id, resource_type) = (segments.next()?, segments.next()?);
+    Some(&reference[reference.len() - id.len() - resource_type.len() - 1..])
+}
+
+/// Whether a MedicationStatement and a MedicationRequest are two records of one
+/// prescription: the statement is based on the request, or both carry the same RxNorm
+/// code. A draft order is not prescribed yet, only an explicit `basedOn` links it.
+fn same_prescription(a: &MedicationItem, b: &MedicationItem) -> bool {
+    let (statement, request) = match (a.resource.resource_type.as_str(), b.resource.resource_type.as_str()) {
+        ("MedicationStatement", "MedicationRequest") => (a, b),
+        ("MedicationRequest", "MedicationStatement") => (b, a),
+        _ => return false,
+    };
+
+    let based_on_request = request.reference.as_deref().is_some_and(|reference| {
+        statement.based_on.iter().any(|based_on| relative_reference(based_on) == Some(reference))
+    });
+    let same_code = request.status != "draft" && statement.rxnorm.is_some() && statement.rxnorm == request.rxnorm;
+    based_on_request || same_code
+}
+
+fn find_overlap(a: &MedicationItem, b: &MedicationItem) -> Option<Overlap> {
+    if a.reference.is_some() && a.reference == b.reference {
+        return None;
+    }
+    if same_prescription(a, b) {
+        return None;
+    }
+
+    // Missing names and codes never match, two unnamed drugs are not the same drug
+    let same_code = a.rxnorm.is_some() && a.rxnorm == b.rxnorm;
+    let same_medication = a.medication.is_some() && a.medication == b.medication;
+    let same_name = match (&a.name, &b.name) {
+        (Some(name_a), Some(name_b)) => name_a.eq_ignore_ascii_case(name_b),
+        _ => false,
+    };
+
+    if same_code || same_medication || same_name {
+        return Some(Overlap::SameMedication);
+    }
+
+    match (a.drug_class(), b.drug_class()) {
+        (Some(class_a), Some(class_b)) if class_a.name == class_b.name => {
+            Some(Overlap::SameClass(class_a.name))
+        }
+        _ => None,
+    }
+}
+
+/// Compare draft orders with the active medication list
+pub fn find_duplicates(
+    drafts: &[MedicationItem],
+    active: &[MedicationItem],
+) -> Vec<Duplicate> {
+    let mut duplicates = Vec::new();
+
+    for draft in drafts {
+        for current in active.iter().filter(|m| m.is_active()) {
+            if let Some(overlap) = find_overlap(draft, current) {
+                duplicates.push(Duplicate {
+                    candidate: draft.clone(),
+                    existing: current.clone(),
+                    overlap,
+                });
+            }
+        }
+    }
+
+    duplicates
+}
+
+/// Find overlapping pairs inside a single medication list.
+/// The later entry of each pair is reported as the candidate.
+pub fn find_duplicates_within(medications: &[MedicationItem]) -> Vec<Duplicate> {
+    let active: Vec<&MedicationItem> = medications.iter().filter(|m| m.is_active()).collect();
+    let mut duplicates = Vec::new();
+
+    for (i, first) in active.iter().enumerate() {
+        for second in active.iter().skip(i + 1) {
+            if let Some(overlap) = find_overlap(second, first) {
+                duplicates.push(Duplicate {
+                    candidate: (*second).clone(),
+                    existing: (*first).clone(),
+                    overlap,
+                });
+            }
+        }
+    }
+
+    duplicates
+}
+
+/// Two medications the patient would take together that form a high-risk combination
+#[derive(Debug, Clone)]
+pub struct HighRiskMatch {
+    pub first: MedicationItem,
+    pub second: MedicationItem,
+    pub combination: &'static HighRiskCombination,
+}
+
+fn find_combination(a: &MedicationItem, b: &MedicationItem) -> Option<&'static HighRiskCombination> {
+    if a.reference.is_some() && a.reference == b.reference {
+        return None;
+    }
+    let (class_a, class_b) = (a.drug_class()?.name, b.drug_class()?.name);
+
+    HIGH_RISK_COMBINATIONS.iter().find(|combination| {
+        combination.classes == (class_a, class_b) || combination.classes == (class_b, class_a)
+    })
+}
+
+/// High-risk combinations between draft orders and the active medication list
+pub fn find_high_risk(
+    drafts: &[MedicationItem],
+    active: &[MedicationItem],
+) -> Vec<HighRiskMatch> {
+    let mut matches = Vec::new();
+
+    for draft in drafts {
+        for current in active.iter().filter(|m| m.is_active()) {
+            if let Some(combination) = find_combination(draft, current) {
+                matches.push(HighRiskMatch {
+                    first: draft.clone(),
+                    second: current.clone(),
+                    combination,
+                });
+            }
+        }
+    }
+
+    matches
+}
+
+/// High-risk combinations inside a single medication list
+pub fn find_high_risk_within(medications: &[MedicationItem]) -> Vec<HighRiskMatch> {
+    let active: Vec<&MedicationItem> = medications.iter().filter(|m| m.is_active()).collect();
+    let mut matches = Vec::new();
+
+    for (i, first) in active.iter().enumerate() {
+        for second in active.iter().skip(i + 1) {
+            if let Some(combination) = find_combination(first, second) {
+                matches.push(HighRiskMatch {
+                    first: (*first).clone(),
+                    second: (*second).clone(),
+                    combination,
+                });
+            }
+        }
+    }
+
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    const RXNORM_WARFARIN: &str = "855332";
+
+    fn resource(value: Value) -> Resource {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn request(id: &str, status: &str, medication: &str, value: Value) -> Value {
+        let mut request = json!({ "resourceType": "MedicationRequest", "id": id, "status": status });
+        request[medication] = value;
+        request
+    }
+
+    fn item(id: &str, status: &str, name: &str) -> MedicationItem {
+        let value = request(id, status, "medicationCodeableConcept", json!({ "text": name }));
+        MedicationItem::from_resource(&resource(value), None).unwrap()
+    }
+
+    fn referenced(id: &str, status: &str, reference: &str) -> MedicationItem {
+        let value = request(id, status, "medicationReference", json!({ "reference": reference }));
+        MedicationItem::from_resource(&resource(value), None).unwrap()
+    }
+
+    #[test]
+    fn unnamed_medications_are_not_duplicates() {
+        let draft = referenced("draft-1", "draft", "Medication/med-1");
+        let other = referenced("active-1", "active", "Medication/med-2");
+        assert_eq!(draft.name, None);
+        assert_eq!(draft.display_name(), UNKNOWN_MEDICATION);
+        assert!(draft.drug_class().is_none());
+        assert!(find_duplicates(std::slice::from_ref(&draft), &[other]).is_empty());
+
+        // The same Medication resource is the same drug, named or not
+        let same = referenced("active-2", "active", "https://ehr.example.org/fhir/Medication/med-1/_history/2");
+        let duplicates = find_duplicates(&[draft], &[same]);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].overlap, Overlap::SameMedication);
+    }
+
+    #[test]
+    fn resolves_contained_and_bundled_medications() {
+        let mut contained = request("req-1", "active", "medicationReference", json!({ "reference": "#med" }));
+        contained["contained"] = json!([{
+            "resourceType": "Medication",
+            "id": "med",
+            "code": { "text": "Warfarin 5 MG Oral Tablet" }
+        }]);
+        let contained = MedicationItem::from_resource(&resource(contained), None).unwrap();
+        assert_eq!(contained.name.as_deref(), Some("Warfarin 5 MG Oral Tablet"));
+        assert_eq!(contained.medication, None);
+        assert_eq!(contained.drug_class().map(|c| c.name), Some("Anticoagulant"));
+
+        let bundle: Bundle = serde_json::from_value(json!({
+            "resourceType": "Bundle",
+            "type": "searchset",
+            "entry": [
+                { "resource": request("req-2", "active", "medicationReference", json!({ "reference": "Medication/med-2" })) },
+                { "resource": {
+                    "resourceType": "Medication",
+                    "id": "med-2",
+                    "code": { "coding": [{ "system": RXNORM_SYSTEM, "code": RXNORM_WARFARIN, "display": "Warfarin" }] }
+                } }
+            ]
+        })).unwrap();
+        let items = extract_medication_items(Some(&bundle));
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name.as_deref(), Some("Warfarin"));
+        assert_eq!(items[0].rxnorm.as_deref(), Some(RXNORM_WARFARIN));
+        assert_eq!(items[0].medication.as_deref(), Some("Medication/med-2"));
+    }
+
+    #[test]
+    fn draft_orders_count_as_active() {
+        assert!(item("draft-1", "draft", "Ibuprofen").is_active());
+        assert!(item("active-1", "active", "Ibuprofen").is_active());
+        assert!(item("hold-1", "on-hold", "Ibuprofen").is_active());
+        assert!(!item("stopped-1", "stopped", "Ibuprofen").is_active());
+        assert!(!item("done-1", "completed", "Ibuprofen").is_active());
+
+        // Two drafts of one order are compared with each other
+        let drafts = [item("draft-1", "draft", "Ibuprofen 400 MG"), item("draft-2", "draft", "ibuprofen 400 mg")];
+        assert_eq!(find_duplicates_within(&drafts).len(), 1);
+    }
+
+    #[test]
+    fn finds_duplicates_of_active_medications() {
+        let drafts = [
+            item("draft-1", "draft", "Lisinopril 10 MG"),
+            item("draft-2", "draft", "Naproxen 250 MG"),
+            item("draft-3", "draft", "Metformin 500 MG"),
+        ];
+        let active = [
+            item("active-1", "active", "lisinopril 10 mg"),
+            item("active-2", "active", "Ibuprofen 400 MG"),
+            item("stopped-1", "stopped", "Metformin 500 MG"),
+        ];
+
+        let duplicates = find_duplicates(&drafts, &active);
+        let found: Vec<(&str, &str, &Overlap)> = duplicates.iter()
+            .map(|d| (d.candidate.display_name(), d.existing.display_name(), &d.overlap))
+            .collect();
+        assert_eq!(found, vec![
+            ("Lisinopril 10 MG", "lisinopril 10 mg", &Overlap::SameMedication),
+            ("Naproxen 250 MG", "Ibuprofen 400 MG", &Overlap::SameClass("NSAID")),
+        ]);
+
+        // An order is not a duplicate of itself
+        assert!(find_duplicates(&drafts[..1], &drafts[..1]).is_empty());
+    }
+
+    #[test]
+    fn statement_of_an_order_is_not_a_duplicate() {
+        let order = request("req-1", "active", "medicationCodeableConcept", json!({ "text": "Lisinopril 10 MG" }));
+        let statement = |id: &str, based_on: Value, coding: Value| resource(json!({
+            "resourceType": "MedicationStatement",
+            "id": id,
+            "status": "active",
+            "basedOn": based_on,
+            "medicationCodeableConcept": { "text": "lisinopril 10 mg", "coding": coding }
+        }));
+
+        let recorded = statement("stat-1", json!([{ "reference": "https://ehr.example.org/fhir/MedicationRequest/req-1" }]), json!([]));
+        let same_code = statement("stat-2", json!([]), json!([{ "system": RXNORM_SYSTEM, "code": "314076" }]));
+        let unrelated = statement("stat-3", json!([]), json!([]));
+
+        let mut order_with_code = order.clone();
+        order_with_code["medicationCodeableConcept"]["coding"] = json!([{ "system": RXNORM_SYSTEM, "code": "314076" }]);
+
+        for (order, statement) in [(order.clone(), recorded), (order_with_code, same_code)] {
+            let items = [
+                MedicationItem::from_resource(&statement, None).unwrap(),
+                MedicationItem::from_resource(&resource(order), None).unwrap(),
+            ];
+            assert!(find_duplicates_within(&items).is_empty());
+        }
+
+        // A statement that does not record the order still duplicates it
+        let items = [
+            MedicationItem::from_resource(&unrelated, None).unwrap(),
+            MedicationItem::from_resource(&resource(order), None).unwrap(),
+        ];
+        assert_eq!(find_duplicates_within(&items).len(), 1);
+    }
+
+    #[test]
+    fn coded_draft_duplicates_a_statement_with_its_code() {
+        let coding = json!({ "text": "Lisinopril 10 MG", "coding": [{ "system": RXNORM_SYSTEM, "code": "314076" }] });
+        let draft = MedicationItem::from_resource(&resource(request("draft-1", "draft", "medicationCodeableConcept", coding.clone())), None).unwrap();
+        let statement = MedicationItem::from_resource(&resource(json!({
+            "resourceType": "MedicationStatement",
+            "id": "stat-1",
+            "status": "active",
+            "medicationCodeableConcept": coding
+        })), None).unwrap();
+
+        let duplicates = find_duplicates(std::slice::from_ref(&draft), std::slice::from_ref(&statement));
+        assert_eq!(duplicates.len(), 1);
+        assert!(matches!(duplicates[0].overlap, Overlap::SameMedication));
+        assert_eq!(find_duplicates_within(&[statement, draft]).len(), 1);
+    }
+}
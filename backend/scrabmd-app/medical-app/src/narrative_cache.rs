@@ -0,0 +1,422 @@
+use aws_sdk_dynamodb as dynamodb;
+use aws_sdk_dynamodb::types::AttributeValue;
+use chrono::{Duration as ChronoDuration, Utc};
+use lambda_http::tracing::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::time::timeout;
+use crate::llm_engine::{manage_medication, review_draft_orders};
+use crate::scrab_errors::ScrabError;
+
+// How long a narrative stays available for the SMART app launch
+const NARRATIVE_TTL_HOURS: i64 = 24;
+
+/// LLM analysis of a hook call, stored with a pending narrative so the
+/// launch can rerun it when the late call never finished
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "analysis", rename_all = "kebab-case")]
+pub enum NarrativeJob {
+    Medication { medications: String },
+    OrderReview { orders: String, medications: String },
+}
+
+impl NarrativeJob {
+    pub async fn run(self) -> Result<String, String> {
+        let result = match &self {
+            NarrativeJob::Medication { medications } => manage_medication(medications).await,
+            NarrativeJob::OrderReview { orders, medications } => review_draft_orders(orders, medications).await,
+        };
+        result.map_err(|e| e.to_string())
+    }
+}
+
+/// LLM narrative that missed the latency budget of a hook call
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedNarrative {
+    pub hook_instance: String,
+    pub service_id: String,
+    /// None while the LLM call is pending
+    pub narrative: Option<String>,
+    pub job: NarrativeJob,
+    pub created_at: String,
+}
+
+/// Outcome of an LLM call raced against the service latency budget
+#[derive(Debug, PartialEq)]
+pub enum Narrative {
+    Ready(String),
+    Failed(String),
+    /// Stored as pending under the hook instance, the launch shows or reruns it
+    Pending,
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ NARRATIVE STORE ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Storage backend for narratives that arrived after the hook response
+pub trait NarrativeStore {
+    fn save(&self, narrative: &CachedNarrative) -> impl Future<Output = Result<(), ScrabError>> + Send;
+
+    fn get(&self, hook_instance: &str) -> impl Future<Output = Result<Option<CachedNarrative>, ScrabError>> + Send;
+}
+
+/// DynamoDB store, `hook_instance` is the partition key and `expires_at` the TTL attribute
+pub struct DynamoNarrativeStore {
+    client: dynamodb::Client,
+    table_name: String,
+}
+
+impl DynamoNarrativeStore {
+    pub async fn new(table_name: &str) -> Self {
+        let config = aws_config::load_from_env().await;
+        Self {
+            client: dynamodb::Client::new(&config),
+            table_name: table_name.to_string(),
+        }
+    }
+
+    /// Store of the `NARRATIVE_TABLE` table, shared by all instances so a
+    /// narrative cached by one is found by the launch on another
+    pub async fn shared() -> Result<&'static DynamoNarrativeStore, ScrabError> {
+        static STORE: OnceLock<DynamoNarrativeStore> = OnceLock::new();
+        if let Some(store) = STORE.get() {
+            return Ok(store);
+        }
+
+        let table_name = env::var("NARRATIVE_TABLE")
+            .map_err(|_| ScrabError::Configuration("NARRATIVE_TABLE is not set".to_string()))?;
+        let store = DynamoNarrativeStore::new(&table_name).await;
+        Ok(STORE.get_or_init(|| store))
+    }
+}
+
+impl NarrativeStore for DynamoNarrativeStore {
+    async fn save(&self, narrative: &CachedNarrative) -> Result<(), ScrabError> {
+        let expires_at = Utc::now() + ChronoDuration::hours(NARRATIVE_TTL_HOURS);
+
+        let mut put = self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .item("hook_instance", AttributeValue::S(narrative.hook_instance.clone()))
+            .item("service_id", AttributeValue::S(narrative.service_id.clone()))
+            .item("job", AttributeValue::S(serde_json::to_string(&narrative.job)?))
+            .item("created_at", AttributeValue::S(narrative.created_at.clone()))
+            .item("expires_at", AttributeValue::N(expires_at.timestamp().to_string()));
+        if let Some(text) = &narrative.narrative {
+            put = put.item("narrative", AttributeValue::S(text.clone()));
+        }
+
+        put.send()
+            .await
+            .map_err(|e| ScrabError::RequestError(dynamodb::Error::from(e).to_string()))?;
+        Ok(())
+    }
+
+    async fn get(&self, hook_instance: &str) -> Result<Option<CachedNarrative>, ScrabError> {
+        let output = self.client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("hook_instance", AttributeValue::S(hook_instance.to_string()))
+            .send()
+            .await
+            .map_err(|e| ScrabError::RequestError(dynamodb::Error::from(e).to_string()))?;
+
+        let Some(item) = output.item else {
+            return Ok(None);
+        };
+        let get_s = |key: &str| item.get(key).and_then(|av| av.as_s().ok().map(|s| s.to_string()));
+
+        Ok(Some(CachedNarrative {
+            hook_instance: hook_instance.to_string(),
+            service_id: get_s("service_id").unwrap_or_default(),
+            narrative: get_s("narrative"),
+            job: serde_json::from_str(&get_s("job").unwrap_or_default())?,
+            created_at: get_s("created_at").unwrap_or_default(),
+        }))
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ HANDLERS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Narrative of a hook call for the launch page. A narrative that is still
+/// pending is rerun here, as the late call may never have finished: the
+/// Lambda instance can be frozen or recycled once the hook response is sent.
+pub async fn launch_narrative_with<S, F, Fut>(store: &S, hook_instance: &str, run: F) -> Option<String>
+where
+    S: NarrativeStore,
+    F: FnOnce(NarrativeJob) -> Fut,
+    Fut: Future<Output = Result<String, String>>,
+{
+    let mut cached = match store.get(hook_instance).await {
+        Ok(Some(cached)) => cached,
+        Ok(None) => return None,
+        Err(e) => {
+            error!("Error reading cached narrative {}: {:?}", hook_instance, e);
+            return None;
+        }
+    };
+
+    if cached.narrative.is_some() {
+        return cached.narrative;
+    }
+
+    info!("Narrative of {} is still pending, running it for the launch", hook_instance);
+    match run(cached.job.clone()).await {
+        Ok(narrative) => {
+            cached.narrative = Some(narrative);
+            if let Err(e) = store.save(&cached).await {
+                error!("Error caching narrative: {:?}", e);
+            }
+            cached.narrative
+        }
+        Err(e) => {
+            error!("LLM call for {} failed at launch: {}", hook_instance, e);
+            None
+        }
+    }
+}
+
+/// Narrative of a hook call for the launch page, from the `NARRATIVE_TABLE` DynamoDB table
+pub async fn launch_narrative(hook_instance: &str) -> Option<String> {
+    match DynamoNarrativeStore::shared().await {
+        Ok(store) => launch_narrative_with(store, hook_instance, NarrativeJob::run).await,
+        Err(e) => {
+            error!("Narrative cache is not configured: {:?}", e);
+            None
+        }
+    }
+}
+
+/// Run `llm_call` in the background and wait at most `budget` for it.
+/// When the budget runs out the job is stored as pending before the hook
+/// answers, and the call keeps running to fill in the narrative. The
+/// background call is best effort: when the instance is frozen before it
+/// finishes, the launch reruns the stored job.
+pub async fn narrative_within_budget_with<S, F>(
+    store: &'static S,
+    llm_call: F,
+    job: NarrativeJob,
+    budget: Duration,
+    hook_instance: &str,
+    service_id: &str,
+) -> Narrative
+where
+    S: NarrativeStore + Sync,
+    F: Future<Output = Result<String, String>> + Send + 'static,
+{
+    let mut task = tokio::spawn(llm_call);
+
+    match timeout(budget, &mut task).await {
+        Ok(Ok(Ok(narrative))) => Narrative::Ready(narrative),
+        Ok(Ok(Err(e))) => Narrative::Failed(e),
+        Ok(Err(e)) => Narrative::Failed(e.to_string()),
+        Err(_) => {
+            warn!("LLM missed the {:?} budget of {}, caching it for {}", budget, service_id, hook_instance);
+            let mut pending = CachedNarrative {
+                hook_instance: hook_instance.to_string(),
+                service_id: service_id.to_string(),
+                narrative: None,
+                job,
+                created_at: Utc::now().to_rfc3339(),
+            };
+
+            if let Err(e) = store.save(&pending).await {
+                task.abort();
+                return Narrative::Failed(format!("Could not store the pending narrative: {}", e));
+            }
+
+            tokio::spawn(async move {
+                pending.narrative = match task.await {
+                    Ok(Ok(narrative)) => Some(narrative),
+                    Ok(Err(e)) => {
+                        error!("Late LLM call for {} failed: {}", pending.hook_instance, e);
+                        return;
+                    }
+                    Err(e) => {
+                        error!("Late LLM call for {} panicked: {}", pending.hook_instance, e);
+                        return;
+                    }
+                };
+
+                match store.save(&pending).await {
+                    Ok(_) => info!("Cached late narrative for {}", pending.hook_instance),
+                    Err(e) => error!("Error caching narrative: {:?}", e),
+                }
+            });
+
+            Narrative::Pending
+        }
+    }
+}
+
+/// Run `llm_call` without a narrative cache, a call that misses `budget`
+/// cannot be completed later and fails
+pub async fn narrative_uncached<F>(llm_call: F, budget: Duration) -> Narrative
+where
+    F: Future<Output = Result<String, String>>,
+{
+    match timeout(budget, llm_call).await {
+        Ok(Ok(narrative)) => Narrative::Ready(narrative),
+        Ok(Err(e)) => Narrative::Failed(e),
+        Err(_) => Narrative::Failed(format!("LLM missed the {:?} budget and no narrative cache is configured", budget)),
+    }
+}
+
+/// [`narrative_within_budget_with`] the `NARRATIVE_TABLE` DynamoDB table, or
+/// [`narrative_uncached`] when the table is not configured
+pub async fn narrative_within_budget(
+    job: NarrativeJob,
+    budget: Duration,
+    hook_instance: &str,
+    service_id: &str,
+) -> Narrative {
+    let llm_call = job.clone().run();
+    match DynamoNarrativeStore::shared().await {
+        Ok(store) => narrative_within_budget_with(store, llm_call, job, budget, hook_instance, service_id).await,
+        Err(e) => {
+            warn!("Narrative cache is not configured, running the LLM uncached: {:?}", e);
+            narrative_uncached(llm_call, budget).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Process-local store standing in for DynamoDB
+    #[derive(Default)]
+    struct MemoryNarrativeStore {
+        narratives: Mutex<HashMap<String, CachedNarrative>>,
+    }
+
+    impl NarrativeStore for MemoryNarrativeStore {
+        async fn save(&self, narrative: &CachedNarrative) -> Result<(), ScrabError> {
+            self.narratives.lock().unwrap().insert(narrative.hook_instance.clone(), narrative.clone());
+            Ok(())
+        }
+
+        async fn get(&self, hook_instance: &str) -> Result<Option<CachedNarrative>, ScrabError> {
+            Ok(self.narratives.lock().unwrap().get(hook_instance).cloned())
+        }
+    }
+
+    fn store() -> &'static MemoryNarrativeStore {
+        Box::leak(Box::default())
+    }
+
+    fn job() -> NarrativeJob {
+        NarrativeJob::Medication { medications: "Lisinopril 10 mg (status: active)".to_string() }
+    }
+
+    #[tokio::test]
+    async fn returns_narrative_within_budget() {
+        let store = store();
+        let narrative = narrative_within_budget_with(
+            store,
+            async { Ok("Lisinopril 10 mg".to_string()) },
+            job(),
+            Duration::from_millis(500),
+            "hook-ready",
+            "medication",
+        ).await;
+
+        assert_eq!(narrative, Narrative::Ready("Lisinopril 10 mg".to_string()));
+        assert!(store.get("hook-ready").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn caches_narrative_that_misses_budget() {
+        let store = store();
+        let narrative = narrative_within_budget_with(
+            store,
+            async {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                Ok("Late narrative".to_string())
+            },
+            job(),
+            Duration::from_millis(10),
+            "hook-late",
+            "medication",
+        ).await;
+        assert_eq!(narrative, Narrative::Pending);
+
+        // The job is stored before the hook answers
+        let pending = store.get("hook-late").await.unwrap().unwrap();
+        assert_eq!(pending.narrative, None);
+        assert_eq!(pending.job, job());
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let cached = store.get("hook-late").await.unwrap().unwrap();
+        assert_eq!(cached.narrative.as_deref(), Some("Late narrative"));
+        assert_eq!(cached.service_id, "medication");
+    }
+
+    #[tokio::test]
+    async fn reports_failed_llm_call() {
+        let narrative = narrative_within_budget_with(
+            store(),
+            async { Err("model unavailable".to_string()) },
+            job(),
+            Duration::from_millis(500),
+            "hook-failed",
+            "medication",
+        ).await;
+
+        assert_eq!(narrative, Narrative::Failed("model unavailable".to_string()));
+    }
+
+    #[tokio::test]
+    async fn runs_the_llm_without_a_cache() {
+        let ready = narrative_uncached(async { Ok("Lisinopril 10 mg".to_string()) }, Duration::from_millis(500)).await;
+        assert_eq!(ready, Narrative::Ready("Lisinopril 10 mg".to_string()));
+
+        let late = narrative_uncached(
+            async {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                Ok("Late narrative".to_string())
+            },
+            Duration::from_millis(10),
+        ).await;
+        assert!(matches!(late, Narrative::Failed(e) if e.contains("no narrative cache")));
+    }
+
+    #[tokio::test]
+    async fn launch_reruns_pending_narrative() {
+        let store = store();
+        let pending = CachedNarrative {
+            hook_instance: "hook-frozen".to_string(),
+            service_id: "medication-order-select".to_string(),
+            narrative: None,
+            job: NarrativeJob::OrderReview { orders: "Ibuprofen".to_string(), medications: "Warfarin".to_string() },
+            created_at: Utc::now().to_rfc3339(),
+        };
+        store.save(&pending).await.unwrap();
+
+        let rerun = |job: NarrativeJob| async move {
+            match job {
+                NarrativeJob::OrderReview { orders, medications } => Ok(format!("{} with {}", orders, medications)),
+                other => Err(format!("unexpected job {:?}", other)),
+            }
+        };
+        let narrative = launch_narrative_with(store, "hook-frozen", rerun).await;
+        assert_eq!(narrative.as_deref(), Some("Ibuprofen with Warfarin"));
+
+        // The rerun narrative is cached, the next launch does not call the LLM again
+        let cached = launch_narrative_with(store, "hook-frozen", |_| async { Err("called twice".to_string()) }).await;
+        assert_eq!(cached.as_deref(), Some("Ibuprofen with Warfarin"));
+
+        assert_eq!(launch_narrative_with(store, "hook-unknown", rerun).await, None);
+    }
+
+    #[test]
+    fn stores_jobs_as_tagged_json() {
+        let json = serde_json::to_value(job()).unwrap();
+        assert_eq!(json["analysis"], "medication");
+        assert_eq!(serde_json::from_value::<NarrativeJob>(json).unwrap(), job());
+    }
+}
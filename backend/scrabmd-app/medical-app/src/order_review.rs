@@ -0,0 +1,315 @@
+use scrab_cds::{Bundle, Card, CdsResponse, HookContext, HookRequest, Indicator, Resource, SystemAction};
+use scrab_fhir::{CodeableConcept, MedicationRequest};
+use chrono::{DateTime, Utc};
+use lambda_http::tracing::info;
+use crate::libs::get_smart_app_uri;
+use crate::llm_engine::{degraded_mode_enabled, llm_failure};
+use crate::cards::{
+    app_link, duplicate_card, high_risk_card, info_card, narrative_link, pending_card,
+};
+use crate::system_actions::build_system_actions;
+use crate::cds_services::CdsService;
+use crate::narrative_cache::{Narrative, NarrativeJob, narrative_within_budget};
+use crate::launch_token::launch_token;
+use crate::med_rules::{
+    MedicationItem, UNKNOWN_MEDICATION, extract_medication_items, find_duplicates, find_high_risk,
+};
+use crate::scrab_errors::ScrabError;
+
+const NO_ISSUES: &str = "No issues found";
+
+/// Resources from `context.draftOrders` that the hook asks us to review.
+/// For `order-select` only the orders listed in `context.selections` are kept.
+fn extract_draft_orders(context: &HookContext) -> Result<Vec<&Resource>, ScrabError> {
+    let orders = match context {
+        HookContext::OrderSelect(select) => select.draft_orders
+            .resources()
+            .filter(|resource| {
+                select.selections.is_empty()
+                    || resource.reference().is_some_and(|r| select.selections.contains(&r))
+            })
+            .collect(),
+        HookContext::OrderSign(sign) => sign.draft_orders.resources().collect(),
+        _ => return Err(ScrabError::Validation("Missing context.draftOrders".to_string())),
+    };
+
+    Ok(orders)
+}
+
+/// One line of the LLM prompt for a draft order; medications are named as the rules
+/// name them, resolving `medicationReference` against the draft orders
+fn describe_order(resource: &Resource, draft_orders: Option<&Bundle>) -> String {
+    let resource_type = &resource.resource_type;
+
+    if let Some(request) = resource.to_typed::<MedicationRequest>() {
+        let item = MedicationItem::from_resource(resource, draft_orders);
+        let name = item.as_ref().map_or(UNKNOWN_MEDICATION, MedicationItem::display_name);
+
+        let dosage = request.dosage_instruction.first()
+            .and_then(|d| d.text.as_deref())
+            .map(|text| format!(", dosage: {}", text))
+            .unwrap_or_default();
+
+        return format!("{}: {}{}", resource_type, name, dosage);
+    }
+
+    // Other orders (ServiceRequest, NutritionOrder, ...) only need their code
+    let name = resource.get("code")
+        .and_then(|code| serde_json::from_value::<CodeableConcept>(code.clone()).ok())
+        .and_then(|code| code.display().map(|d| d.to_string()))
+        .unwrap_or_else(|| "Unknown".to_string());
+
+    format!("{}: {}", resource_type, name)
+}
+
+fn describe_medication(item: &MedicationItem) -> String {
+    format!("{} (status: {})", item.display_name(), item.status)
+}
+
+/// Draft medication orders and active medications of an order hook, resolved
+/// against the draft orders and prefetch bundles
+fn order_medications(request: &HookRequest, draft_orders: &[&Resource]) -> (Vec<MedicationItem>, Vec<MedicationItem>) {
+    let drafts = draft_orders.iter()
+        .filter(|r| r.resource_type == "MedicationRequest")
+        .filter_map(|r| MedicationItem::from_resource(r, request.context.draft_orders()))
+        .collect();
+
+    let prefetch = &request.prefetch;
+    let mut active = extract_medication_items(prefetch.bundle("medications_stat"));
+    active.extend(extract_medication_items(prefetch.bundle("medication_req")));
+    active.retain(|m| m.is_active());
+
+    (drafts, active)
+}
+
+/// Rule-based cards and system actions for the draft orders, returned even when the LLM is not
+fn rule_cards(
+    request: &HookRequest,
+    service: &CdsService,
+    drafts: &[MedicationItem],
+    active: &[MedicationItem],
+    now: DateTime<Utc>,
+) -> (Vec<Card>, Vec<SystemAction>) {
+    let high_risk = find_high_risk(drafts, active);
+    let system_actions = build_system_actions(service.system_actions, &high_risk, &request.context, now);
+
+    let mut cards: Vec<Card> = find_duplicates(drafts, active)
+        .iter()
+        .map(duplicate_card)
+        .collect();
+    cards.extend(high_risk.iter().map(high_risk_card));
+
+    (cards, system_actions)
+}
+
+pub async fn manage_order_hook(
+    request: &HookRequest,
+    service: &CdsService,
+    api_url: &str,
+) -> Result<CdsResponse, ScrabError> {
+
+    let draft_orders = extract_draft_orders(&request.context)?;
+    info!("Draft orders to review: {}", draft_orders.len());
+
+    let (drafts, active) = order_medications(request, &draft_orders);
+    let (mut cards, system_actions) = rule_cards(request, service, &drafts, &active, Utc::now());
+
+    if !draft_orders.is_empty() {
+        let orders_text = draft_orders.iter()
+            .map(|order| describe_order(order, request.context.draft_orders()))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let medications_text = if active.is_empty() {
+            "No active medications recorded.".to_string()
+        } else {
+            active.iter()
+                .map(describe_medication)
+                .collect::<Vec<String>>()
+                .join("\n")
+        };
+
+        let job = NarrativeJob::OrderReview { orders: orders_text, medications: medications_text };
+        let smart_app_uri = get_smart_app_uri(api_url);
+
+        match narrative_within_budget(job, service.latency_budget(), &request.hook_instance, service.id).await {
+            Narrative::Ready(review) if !review.trim().starts_with(NO_ISSUES) => {
+                cards.push(
+                    info_card("Order review", Indicator::Info, &review)
+                        .with_links(vec![app_link(&smart_app_uri)])
+                );
+            }
+            Narrative::Ready(_) => info!("No issues found in draft orders"),
+            Narrative::Pending => {
+                let token = launch_token(&request.hook_instance)?;
+                cards.push(
+                    pending_card("Order review", "order review")
+                        .with_links(vec![narrative_link(&smart_app_uri, &request.hook_instance, &token)])
+                );
+            }
+            Narrative::Failed(e) => {
+                let rule_output = !cards.is_empty() || !system_actions.is_empty();
+                cards.extend(llm_failure(e, "order review", degraded_mode_enabled(), rule_output)?);
+            }
+        }
+    }
+
+    Ok(CdsResponse::new(cards).with_system_actions(system_actions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cds_services::find_service;
+    use scrab_fhir::RXNORM_SYSTEM;
+    use serde_json::{json, Value};
+
+    fn medication_request(id: &str, status: &str, name: &str) -> Value {
+        json!({
+            "resourceType": "MedicationRequest",
+            "id": id,
+            "status": status,
+            "medicationCodeableConcept": { "text": name }
+        })
+    }
+
+    fn bundle(resources: Vec<Value>) -> Value {
+        json!({
+            "resourceType": "Bundle",
+            "type": "collection",
+            "entry": resources.into_iter().map(|resource| json!({ "resource": resource })).collect::<Vec<_>>()
+        })
+    }
+
+    fn hook_request(hook: &str, selections: &[&str], drafts: Vec<Value>, active: Vec<Value>) -> HookRequest {
+        serde_json::from_value(json!({
+            "hook": hook,
+            "hookInstance": "d1577c69-dfbe-44ad-ba6d-3e05e953b2ea",
+            "context": {
+                "userId": "Practitioner/example",
+                "patientId": "1288992",
+                "selections": selections,
+                "draftOrders": bundle(drafts)
+            },
+            "prefetch": {
+                "medications_stat": bundle(vec![]),
+                "medication_req": bundle(active)
+            }
+        })).unwrap()
+    }
+
+    fn drafts() -> Vec<Value> {
+        vec![
+            medication_request("draft-1", "draft", "Ibuprofen 400 MG"),
+            medication_request("draft-2", "draft", "Atorvastatin 20 MG"),
+        ]
+    }
+
+    fn review(request: &HookRequest, service_id: &str) -> (Vec<Card>, Vec<SystemAction>) {
+        let service = find_service(service_id).unwrap();
+        let draft_orders = extract_draft_orders(&request.context).unwrap();
+        let (drafts, active) = order_medications(request, &draft_orders);
+        rule_cards(request, service, &drafts, &active, Utc::now())
+    }
+
+    #[test]
+    fn order_select_reviews_selected_orders_only() {
+        let request = hook_request("order-select", &["MedicationRequest/draft-1"], drafts(), vec![]);
+        let orders = extract_draft_orders(&request.context).unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].id.as_deref(), Some("draft-1"));
+
+        let request = hook_request("order-select", &[], drafts(), vec![]);
+        assert_eq!(extract_draft_orders(&request.context).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn order_sign_reviews_all_orders() {
+        let request = hook_request("order-sign", &[], drafts(), vec![]);
+        assert_eq!(extract_draft_orders(&request.context).unwrap().len(), 2);
+
+        let patient_view: HookRequest = serde_json::from_value(json!({
+            "hook": "patient-view",
+            "hookInstance": "1",
+            "context": { "userId": "Practitioner/example", "patientId": "1288992" }
+        })).unwrap();
+        assert!(matches!(extract_draft_orders(&patient_view.context), Err(ScrabError::Validation(_))));
+    }
+
+    #[test]
+    fn order_select_flags_duplicates_of_active_medications() {
+        let active = vec![
+            medication_request("active-1", "active", "Atorvastatin 20 MG"),
+            medication_request("stopped-1", "stopped", "Ibuprofen 400 MG"),
+        ];
+        let request = hook_request("order-select", &["MedicationRequest/draft-2"], drafts(), active);
+
+        let (cards, system_actions) = review(&request, "medication-order-select");
+        let summaries: Vec<&str> = cards.iter().map(|card| card.summary.as_str()).collect();
+        assert_eq!(summaries, vec!["Duplicate medication: Atorvastatin 20 MG"]);
+        assert!(system_actions.is_empty());
+    }
+
+    #[test]
+    fn order_sign_creates_system_actions_for_high_risk_combinations() {
+        let active = vec![medication_request("active-1", "active", "Warfarin 5 MG")];
+        let request = hook_request("order-sign", &[], drafts(), active);
+
+        let (cards, system_actions) = review(&request, "medication-order-sign");
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].indicator, Indicator::Critical);
+        assert!(cards[0].summary.contains("Ibuprofen 400 MG with Warfarin 5 MG"));
+
+        let created: Vec<&str> = system_actions.iter()
+            .filter_map(|action| action.resource.as_ref())
+            .map(|resource| resource.resource_type.as_str())
+            .collect();
+        assert_eq!(created, vec!["Flag", "Task"]);
+    }
+
+    #[test]
+    fn describes_orders_by_their_referenced_medication() {
+        let contained = json!({
+            "resourceType": "MedicationRequest",
+            "id": "draft-3",
+            "status": "draft",
+            "contained": [{ "resourceType": "Medication", "id": "med", "code": { "text": "Warfarin 5 MG" } }],
+            "medicationReference": { "reference": "#med" },
+            "dosageInstruction": [{ "text": "5 mg daily" }]
+        });
+        let external = json!({
+            "resourceType": "MedicationRequest",
+            "id": "draft-4",
+            "status": "draft",
+            "medicationReference": { "reference": "Medication/med-2" }
+        });
+        let medication = json!({ "resourceType": "Medication", "id": "med-2", "code": { "text": "Metformin 500 MG" } });
+        let request = hook_request("order-sign", &[], vec![contained, external, medication], vec![]);
+
+        let orders = extract_draft_orders(&request.context).unwrap();
+        let described: Vec<String> = orders.iter()
+            .filter(|order| order.resource_type == "MedicationRequest")
+            .map(|order| describe_order(order, request.context.draft_orders()))
+            .collect();
+        assert_eq!(described, [
+            "MedicationRequest: Warfarin 5 MG, dosage: 5 mg daily",
+            "MedicationRequest: Metformin 500 MG",
+        ]);
+
+        let unresolved = Resource { id: Some("draft-5".into()), ..orders[1].clone() };
+        let unresolved = describe_order(&unresolved, None);
+        assert_eq!(unresolved, "MedicationRequest: Unknown medication");
+    }
+
+    #[test]
+    fn flags_a_coded_draft_already_taken_as_a_statement() {
+        let coding = json!({ "text": "Atorvastatin 20 MG", "coding": [{ "system": RXNORM_SYSTEM, "code": "617310" }] });
+        let draft = json!({ "resourceType": "MedicationRequest", "id": "draft-1", "status": "draft", "medicationCodeableConcept": coding });
+        let statement = json!({ "resourceType": "MedicationStatement", "id": "stat-1", "status": "active", "medicationCodeableConcept": coding });
+        let request = hook_request("order-sign", &[], vec![draft], vec![statement]);
+
+        let (cards, _) = review(&request, "medication-order-sign");
+        let summaries: Vec<&str> = cards.iter().map(|card| card.summary.as_str()).collect();
+        assert_eq!(summaries, vec!["Duplicate medication: Atorvastatin 20 MG"]);
+    }
+}
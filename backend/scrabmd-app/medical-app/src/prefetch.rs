@@ -0,0 +1,80 @@
+use chrono::Utc;
+use lambda_http::tracing::{error, info, warn};
+use std::time::{Duration, Instant};
+use scrab_cds::{HookRequest, PrefetchTemplate, PrefetchValue};
+use scrab_fhir::FhirClient;
+use crate::backend_auth::backend_token_for;
+use crate::cds_services::CdsService;
+
+// Time the fallback fetches may take together, they run before the LLM latency budget
+const PREFETCH_BUDGET: Duration = Duration::from_secs(5);
+
+/// Fill the prefetch keys the EHR did not send by querying its FHIR server
+/// with the token in `fhirAuthorization`, or the backend services token of the
+/// app when the EHR granted none. Keys that cannot be fetched within
+/// [`PREFETCH_BUDGET`] are left missing.
+pub async fn complete_prefetch(request: &mut HookRequest, service: &CdsService) {
+    let deadline = Instant::now() + PREFETCH_BUDGET;
+    let missing: Vec<(&str, &str)> = service.prefetch
+        .iter()
+        .filter(|(key, _)| request.prefetch.is_missing(key))
+        .copied()
+        .collect();
+
+    if missing.is_empty() {
+        return;
+    }
+
+    let Some(fhir_server) = request.fhir_server.clone() else {
+        warn!("Prefetch keys {:?} missing and no fhirServer to fetch them from", missing);
+        return;
+    };
+    let access_token = match request.access_token() {
+        Some(access_token) => access_token.to_string(),
+        None => match backend_token_for(&fhir_server).await {
+            Some(access_token) => access_token,
+            None => {
+                warn!("Prefetch keys {:?} missing and no fhirAuthorization to fetch them", missing);
+                return;
+            }
+        },
+    };
+    let client = match FhirClient::new(&fhir_server, &access_token) {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Error creating the FHIR client: {}", e);
+            return;
+        }
+    };
+    let today = Utc::now().date_naive();
+
+    for (key, template) in missing {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            warn!("Prefetch budget spent, leaving {} missing", key);
+            continue;
+        }
+
+        let query = match PrefetchTemplate::parse(template)
+            .and_then(|template| template.expand(&request.context, today))
+        {
+            Ok(query) => query,
+            Err(e) => {
+                warn!("Could not expand prefetch template for {}: {}", key, e);
+                continue;
+            }
+        };
+
+        let fetched = client.clone().with_timeout(remaining).get(&query)
+            .await
+            .and_then(|data| Ok(serde_json::from_value::<PrefetchValue>(data)?));
+
+        match fetched {
+            Ok(value) => {
+                info!("Fetched missing prefetch {} from {}", key, query);
+                request.prefetch.insert(key, value);
+            }
+            Err(e) => error!("Error fetching prefetch {}: {:?}", key, e),
+        }
+    }
+}
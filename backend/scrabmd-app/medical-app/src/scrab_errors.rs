@@ -28,6 +28,9 @@ pub enum ScrabError {
 
     #[error("Request error: {0}")]
     RequestError(String),
+
+    #[error("FHIR error: {0}")]
+    Fhir(#[from] scrab_fhir::FhirError),
     
     #[error("IO error: {0}")]
     Io(#[from] io::Error),
@@ -40,6 +43,15 @@ pub enum ScrabError {
     
     #[error("Validation error: {0}")]
     Validation(String),
+
+    #[error("Unknown service: {0}")]
+    UnknownService(String),
+
+    #[error("Missing required prefetch: {0}")]
+    MissingPrefetch(String),
+
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
     
     #[error("{0}")]
     GenericError(String),
@@ -0,0 +1,216 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Map, Value};
+use scrab_cds::{Action, HookContext, Resource, SystemAction};
+use crate::med_rules::HighRiskMatch;
+
+const FLAG_CATEGORY_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/flag-category";
+
+/// FHIR resource a service creates on its own when a high-risk combination is found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemActionKind {
+    /// Drug `Flag` on the patient's record
+    Flag,
+    /// Review `Task` for the ordering user
+    Task,
+}
+
+fn reference(resource_type: &str, id: &str) -> Value {
+    // userId is already a reference, patientId and encounterId are bare ids
+    if id.contains('/') {
+        json!({ "reference": id })
+    } else {
+        json!({ "reference": format!("{}/{}", resource_type, id) })
+    }
+}
+
+fn new_resource(resource_type: &str, elements: Value) -> Resource {
+    let data: Map<String, Value> = match elements {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    };
+
+    Resource {
+        resource_type: resource_type.to_string(),
+        id: None,
+        data,
+    }
+}
+
+fn describe(high_risk: &HighRiskMatch) -> String {
+    format!(
+        "{} with {}: {}",
+        high_risk.first.display_name(), high_risk.second.display_name(), high_risk.combination.risk,
+    )
+}
+
+/// Active drug Flag for the patient, authored by the current user
+fn flag_resource(high_risk: &HighRiskMatch, context: &HookContext, now: &str) -> Resource {
+    let mut flag = json!({
+        "status": "active",
+        "category": [{
+            "coding": [{
+                "system": FLAG_CATEGORY_SYSTEM,
+                "code": "drug",
+                "display": "Drug"
+            }]
+        }],
+        "code": { "text": format!("High-risk medication combination: {}", describe(high_risk)) },
+        "period": { "start": now }
+    });
+
+    if let Some(patient_id) = context.patient_id() {
+        flag["subject"] = reference("Patient", patient_id);
+    }
+    if let Some(encounter_id) = context.encounter_id() {
+        flag["encounter"] = reference("Encounter", encounter_id);
+    }
+    if let Some(user_id) = context.user_id() {
+        flag["author"] = reference("Practitioner", user_id);
+    }
+
+    new_resource("Flag", flag)
+}
+
+/// Urgent review Task requested by the current user, focused on the first medication
+fn task_resource(high_risk: &HighRiskMatch, context: &HookContext, now: &str) -> Resource {
+    let mut task = json!({
+        "status": "requested",
+        "intent": "order",
+        "priority": "urgent",
+        "code": { "text": "Review high-risk medication combination" },
+        "description": describe(high_risk),
+        "authoredOn": now
+    });
+
+    if let Some(patient_id) = context.patient_id() {
+        task["for"] = reference("Patient", patient_id);
+    }
+    if let Some(encounter_id) = context.encounter_id() {
+        task["encounter"] = reference("Encounter", encounter_id);
+    }
+    if let Some(user_id) = context.user_id() {
+        task["requester"] = reference("Practitioner", user_id);
+    }
+    if let Some(focus) = &high_risk.first.reference {
+        task["focus"] = json!({ "reference": focus });
+    }
+
+    new_resource("Task", task)
+}
+
+/// `create` system actions for every high-risk combination, one per configured kind
+pub fn build_system_actions(
+    kinds: &[SystemActionKind],
+    high_risk: &[HighRiskMatch],
+    context: &HookContext,
+    now: DateTime<Utc>,
+) -> Vec<SystemAction> {
+    let now = now.to_rfc3339_opts(SecondsFormat::Secs, true);
+
+    high_risk.iter()
+        .flat_map(|high_risk| kinds.iter().map(move |kind| (kind, high_risk)))
+        .map(|(kind, high_risk)| match kind {
+            SystemActionKind::Flag => Action::create(
+                &format!("Flag high-risk combination: {}", describe(high_risk)),
+                flag_resource(high_risk, context, &now),
+            ),
+            SystemActionKind::Task => Action::create(
+                &format!("Request review of high-risk combination: {}", describe(high_risk)),
+                task_resource(high_risk, context, &now),
+            ),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::med_rules::{MedicationItem, find_high_risk, find_high_risk_within};
+    use chrono::TimeZone;
+    use scrab_cds::ActionType;
+
+    fn medication(id: &str, status: &str, name: &str) -> MedicationItem {
+        let resource: Resource = serde_json::from_value(json!({
+            "resourceType": "MedicationRequest",
+            "id": id,
+            "status": status,
+            "medicationCodeableConcept": { "text": name }
+        })).unwrap();
+        MedicationItem::from_resource(&resource, None).unwrap()
+    }
+
+    fn order_sign_context() -> HookContext {
+        HookContext::OrderSign(serde_json::from_value(json!({
+            "userId": "Practitioner/example",
+            "patientId": "1288992",
+            "encounterId": "89284",
+            "draftOrders": { "resourceType": "Bundle", "type": "collection" }
+        })).unwrap())
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, 31, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn flags_nsaid_with_anticoagulant() {
+        let drafts = vec![medication("draft-1", "draft", "Ibuprofen 400 MG Oral Tablet")];
+        let active = vec![medication("active-1", "active", "Warfarin Sodium 5 MG Oral Tablet")];
+        let high_risk = find_high_risk(&drafts, &active);
+        assert_eq!(high_risk.len(), 1);
+
+        let actions = build_system_actions(&[SystemActionKind::Flag], &high_risk, &order_sign_context(), now());
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].action_type, ActionType::Create);
+
+        let flag = actions[0].resource.as_ref().unwrap();
+        assert_eq!(flag.resource_type, "Flag");
+        assert_eq!(flag.get("status"), Some(&json!("active")));
+        assert_eq!(flag.get("subject"), Some(&json!({ "reference": "Patient/1288992" })));
+        assert_eq!(flag.get("encounter"), Some(&json!({ "reference": "Encounter/89284" })));
+        assert_eq!(flag.get("author"), Some(&json!({ "reference": "Practitioner/example" })));
+        assert_eq!(flag.get("period"), Some(&json!({ "start": "2025-03-31T12:00:00Z" })));
+        assert_eq!(flag.get("category").unwrap()[0]["coding"][0]["code"], "drug");
+    }
+
+    #[test]
+    fn requests_review_task_for_each_combination() {
+        let medications = vec![
+            medication("warfarin", "active", "Warfarin 5 MG"),
+            medication("aspirin", "active", "Aspirin 81 MG"),
+            medication("naproxen", "active", "Naproxen 250 MG"),
+        ];
+        let high_risk = find_high_risk_within(&medications);
+        assert_eq!(high_risk.len(), 2);
+
+        let context = HookContext::PatientView(serde_json::from_value(json!({
+            "userId": "PractitionerRole/role-1",
+            "patientId": "1288992"
+        })).unwrap());
+        let actions = build_system_actions(
+            &[SystemActionKind::Flag, SystemActionKind::Task],
+            &high_risk,
+            &context,
+            now(),
+        );
+        assert_eq!(actions.len(), 4);
+
+        let task = actions[1].resource.as_ref().unwrap();
+        assert_eq!(task.resource_type, "Task");
+        assert_eq!(task.get("for"), Some(&json!({ "reference": "Patient/1288992" })));
+        assert_eq!(task.get("requester"), Some(&json!({ "reference": "PractitionerRole/role-1" })));
+        assert_eq!(task.get("focus"), Some(&json!({ "reference": "MedicationRequest/warfarin" })));
+        assert_eq!(task.get("authoredOn"), Some(&json!("2025-03-31T12:00:00Z")));
+        assert!(task.get("encounter").is_none());
+    }
+
+    #[test]
+    fn skips_services_without_system_actions() {
+        let drafts = vec![medication("draft-1", "draft", "Ketorolac 10 MG")];
+        let active = vec![medication("active-1", "active", "Apixaban 5 MG")];
+        let high_risk = find_high_risk(&drafts, &active);
+
+        assert!(build_system_actions(&[], &high_risk, &order_sign_context(), now()).is_empty());
+        assert!(find_high_risk(&drafts, &[medication("a", "stopped", "Apixaban 5 MG")]).is_empty());
+    }
+}
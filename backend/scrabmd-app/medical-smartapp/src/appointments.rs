@@ -0,0 +1,166 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use lambda_runtime::tracing::error;
+use scrab_fhir::{Appointment as FhirAppointment, ReferenceResolver, SearchParams, SearchResult};
+use crate::libs::{Appointment, MainPageParams};
+use crate::references::reference_name;
+use crate::scrab_errors::ScrabError;
+
+pub async fn extract_appointments(
+    params: &MainPageParams,
+    resolver: &ReferenceResolver,
+) -> Vec<Appointment> {
+    match extract_appointments_handle(params, resolver).await {
+        Ok(appointments) => appointments,
+        Err(e) => {
+            error!("Error extracting appointments: {:?}", e);
+            vec![]
+        }
+    }
+}
+
+/// Upcoming appointments of the patient, soonest first
+pub async fn extract_appointments_handle(
+    params: &MainPageParams,
+    resolver: &ReferenceResolver,
+) -> Result<Vec<Appointment>, ScrabError> {
+    let client = resolver.client();
+    let now = Utc::now();
+
+    let result = client.search(&SearchParams::of::<FhirAppointment>()
+        .with_patient(&params.patient_id)
+        .with("date", &format!("ge{}", now.format("%Y-%m-%d")))
+        .with_include("Appointment:actor")
+    ).await?;
+
+    resolver.add_search(&result);
+
+    Ok(upcoming_appointments(&result, resolver, now).await)
+}
+
+/// Appointments of the search result that are still to come, soonest first
+pub async fn upcoming_appointments(
+    result: &SearchResult,
+    resolver: &ReferenceResolver,
+    now: DateTime<Utc>,
+) -> Vec<Appointment> {
+    let mut upcoming: Vec<(DateTime<FixedOffset>, FhirAppointment)> = result.resources::<FhirAppointment>()
+        .into_iter()
+        .filter(|a| !matches!(a.status.as_str(), "cancelled" | "noshow" | "entered-in-error" | "fulfilled"))
+        .filter_map(|a| Some((DateTime::parse_from_rfc3339(a.start.as_deref()?).ok()?, a)))
+        .filter(|(start, _)| *start > now)
+        .collect();
+    upcoming.sort_by_key(|(start, _)| *start);
+
+    let mut appointments = vec![];
+    for (start, appointment) in upcoming {
+        let mut providers = vec![];
+        for actor in appointment.actors("Practitioner") {
+            if let Some(name) = reference_name(resolver, actor, &[]).await {
+                providers.push(name);
+            }
+        }
+
+        let mut location = None;
+        for actor in appointment.actors("Location") {
+            location = reference_name(resolver, actor, &[]).await;
+            if location.is_some() {
+                break;
+            }
+        }
+
+        let a_type = appointment.appointment_type.as_ref()
+            .or(appointment.service_type.first())
+            .or(appointment.specialty.first())
+            .and_then(|concept| concept.display())
+            .map(str::to_string)
+            .or_else(|| appointment.description.clone());
+
+        appointments.push(Appointment {
+            date: start.format("%Y-%m-%d").to_string(),
+            time: start.format("%-I:%M %p").to_string(),
+            provider: if providers.is_empty() { "n/a".to_string() } else { providers.join(", ") },
+            a_type: a_type.unwrap_or_else(|| "Appointment".to_string()),
+            location: location.unwrap_or_else(|| "n/a".to_string()),
+        });
+    }
+
+    appointments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scrab_fhir::FhirClient;
+    use serde_json::{Value, json};
+
+    // Nothing listens on the discard port, names come from the included resources
+    fn resolver() -> ReferenceResolver {
+        ReferenceResolver::new(FhirClient::new("http://127.0.0.1:9/r4", "token").unwrap())
+    }
+
+    fn appointment(id: &str, status: &str, start: &str) -> Value {
+        json!({
+            "search": { "mode": "match" },
+            "resource": {
+                "resourceType": "Appointment",
+                "id": id,
+                "status": status,
+                "serviceType": [{ "text": format!("Visit {}", id) }],
+                "start": start,
+                "participant": [
+                    { "actor": { "reference": "Practitioner/pr-1" }, "status": "accepted" },
+                    { "actor": { "reference": "Location/loc-1" }, "status": "accepted" }
+                ]
+            }
+        })
+    }
+
+    fn search(entries: Vec<Value>) -> SearchResult {
+        let mut includes = vec![
+            json!({ "search": { "mode": "include" }, "resource": {
+                "resourceType": "Practitioner", "id": "pr-1", "name": [{ "family": "Chen", "given": ["Emily"], "prefix": ["Dr."] }]
+            } }),
+            json!({ "search": { "mode": "include" }, "resource": {
+                "resourceType": "Location", "id": "loc-1", "name": "Cardiology Clinic"
+            } }),
+        ];
+        includes.extend(entries);
+        let mut result = SearchResult::default();
+        result.add_page(&json!({ "resourceType": "Bundle", "type": "searchset", "entry": includes }));
+        result
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2026-11-01T12:00:00Z").unwrap().with_timezone(&Utc)
+    }
+
+    #[tokio::test]
+    async fn lists_only_future_appointments_soonest_first() {
+        let result = search(vec![
+            appointment("later", "booked", "2026-12-01T09:30:00-05:00"),
+            appointment("past", "booked", "2026-11-01T06:00:00-05:00"),
+            appointment("sooner", "booked", "2026-11-15T14:00:00-05:00"),
+            appointment("cancelled", "cancelled", "2026-11-10T09:00:00-05:00"),
+            appointment("undated", "proposed", "not a date"),
+        ]);
+        let resolver = resolver();
+        resolver.add_search(&result);
+
+        let appointments = upcoming_appointments(&result, &resolver, now()).await;
+        let types: Vec<&str> = appointments.iter().map(|a| a.a_type.as_str()).collect();
+        assert_eq!(types, ["Visit sooner", "Visit later"]);
+        assert_eq!((appointments[0].date.as_str(), appointments[0].time.as_str()), ("2026-11-15", "2:00 PM"));
+    }
+
+    #[tokio::test]
+    async fn names_the_practitioner_and_location() {
+        let result = search(vec![appointment("a1", "booked", "2026-11-15T14:00:00-05:00")]);
+        let resolver = resolver();
+        resolver.add_search(&result);
+
+        let appointments = upcoming_appointments(&result, &resolver, now()).await;
+        assert_eq!(appointments[0].provider, "Dr. Emily Chen");
+        assert_eq!(appointments[0].location, "Cardiology Clinic");
+        assert_eq!(resolver.reads(), 0);
+    }
+}
@@ -0,0 +1,201 @@
+use lambda_runtime::tracing::error;
+use scrab_fhir::{
+    CodeableConcept, Condition, FhirClient, ICD10_CM_SYSTEM, ICD10_SYSTEM, SNOMED_SYSTEM,
+    SearchParams,
+};
+use crate::libs::MainPageParams;
+use crate::scrab_errors::ScrabError;
+
+pub async fn extract_chronic_conditions(
+    params: &MainPageParams,
+) -> Vec<String> {
+    match extract_chronic_conditions_handle(params).await {
+        Ok(conditions) => conditions,
+        Err(e) => {
+            error!("Error extracting chronic conditions: {:?}", e);
+            vec![]
+        }
+    }
+}
+
+/// Chronic conditions of the patient from the problem list
+pub async fn extract_chronic_conditions_handle(
+    params: &MainPageParams,
+) -> Result<Vec<String>, ScrabError> {
+    let client = FhirClient::new(&params.iss, &params.access_token)?;
+
+    let result = client.search(&SearchParams::of::<Condition>()
+        .with_patient(&params.patient_id)
+        .with("category", "problem-list-item")
+        .with("clinical-status", "active,recurrence,relapse")
+    ).await?;
+
+    Ok(chronic_conditions(&result.resources::<Condition>()))
+}
+
+/// A chronic condition with the codes of all the Conditions grouped under it
+struct ChronicCondition {
+    name: String,
+    snomed: Vec<String>,
+    icd10: Vec<String>,
+}
+
+impl ChronicCondition {
+    fn label(&self) -> String {
+        let codes: Vec<String> = self.snomed.iter().map(|code| format!("SNOMED {}", code))
+            .chain(self.icd10.iter().map(|code| format!("ICD-10 {}", code)))
+            .collect();
+        if codes.is_empty() {
+            self.name.clone()
+        } else if self.name.is_empty() {
+            codes.join(", ")
+        } else {
+            format!("{} ({})", self.name, codes.join(", "))
+        }
+    }
+
+    fn matches(&self, name: &str, snomed: Option<&str>, icd10: Option<&str>) -> bool {
+        snomed.is_some_and(|code| self.snomed.iter().any(|c| c == code))
+            || icd10.is_some_and(|code| self.icd10.iter().any(|c| c == code))
+            || (!name.is_empty() && self.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Active, confirmed problem-list Conditions, one entry per code or name,
+/// e.g. `Essential hypertension (SNOMED 59621000)`. Conditions without a
+/// name are shown by their codes.
+pub fn chronic_conditions(conditions: &[Condition]) -> Vec<String> {
+    let mut grouped: Vec<ChronicCondition> = vec![];
+
+    for condition in conditions {
+        if !condition.has_category("problem-list-item")
+            || !condition.is_active()
+            || !condition.has_verification_status("confirmed")
+        {
+            continue;
+        }
+        let Some(code) = &condition.code else {
+            continue;
+        };
+        let name = code.display().map(without_semantic_tag).unwrap_or_default();
+        let snomed = code.code_in(SNOMED_SYSTEM);
+        let icd10 = icd10_code(code);
+        if name.is_empty() && snomed.is_none() && icd10.is_none() {
+            continue;
+        }
+
+        let index = match grouped.iter().position(|c| c.matches(&name, snomed, icd10)) {
+            Some(index) => index,
+            None => {
+                grouped.push(ChronicCondition { name, snomed: vec![], icd10: vec![] });
+                grouped.len() - 1
+            }
+        };
+        let entry = &mut grouped[index];
+        if let Some(snomed) = snomed
+            && !entry.snomed.iter().any(|c| c == snomed)
+        {
+            entry.snomed.push(snomed.to_string());
+        }
+        if let Some(icd10) = icd10
+            && !entry.icd10.iter().any(|c| c == icd10)
+        {
+            entry.icd10.push(icd10.to_string());
+        }
+    }
+
+    grouped.iter().map(ChronicCondition::label).collect()
+}
+
+fn icd10_code(code: &CodeableConcept) -> Option<&str> {
+    code.code_in(ICD10_CM_SYSTEM).or_else(|| code.code_in(ICD10_SYSTEM))
+}
+
+/// `Fever` from the SNOMED display `Fever (finding)`
+fn without_semantic_tag(display: &str) -> String {
+    let display = display.trim();
+    match display.rfind(" (") {
+        Some(start) if display.ends_with(')') => {
+            let tag = &display[start + 2..display.len() - 1];
+            if matches!(tag.to_lowercase().as_str(), "finding" | "disorder" | "disease" | "situation" | "morphologic abnormality") {
+                display[..start].to_string()
+            } else {
+                display.to_string()
+            }
+        }
+        _ => display.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scrab_cds::HookRequest;
+    use scrab_fhir::from_value;
+    use serde_json::{Value, json};
+
+    const CDS_EVENT: &str = include_str!("../tests/fixtures/cds-event-001.json");
+
+    fn problem(clinical_status: &str, verification_status: &str, code: Value) -> Condition {
+        from_value(json!({
+            "resourceType": "Condition",
+            "clinicalStatus": { "coding": [{ "system": "http://terminology.hl7.org/CodeSystem/condition-clinical", "code": clinical_status }] },
+            "verificationStatus": { "coding": [{ "system": "http://terminology.hl7.org/CodeSystem/condition-ver-status", "code": verification_status }] },
+            "category": [{ "coding": [{ "system": "http://terminology.hl7.org/CodeSystem/condition-category", "code": "problem-list-item" }] }],
+            "code": code
+        })).unwrap()
+    }
+
+    #[test]
+    fn groups_active_problem_list_conditions() {
+        let event = HookRequest::from_json(CDS_EVENT).unwrap();
+        let mut conditions: Vec<Condition> = event.prefetch.bundle("conditions").unwrap().typed().collect();
+
+        // A second problem-list entry for the fever, coded in ICD-10-CM as well
+        conditions.push(problem("active", "confirmed", json!({ "coding": [
+            { "system": SNOMED_SYSTEM, "code": "386661006", "display": "Fever" },
+            { "system": ICD10_CM_SYSTEM, "code": "R50.9" }
+        ] })));
+
+        let chronic = chronic_conditions(&conditions);
+        assert_eq!(chronic, vec![
+            "Fever (SNOMED 386661006, ICD-10 R50.9)",
+            "Chronic rejection of renal transplant (SNOMED 236578006)",
+            "Severe Hypothyroidism (SNOMED 83986005)",
+            "Essential hypertension (SNOMED 59621000)",
+        ]);
+    }
+
+    #[test]
+    fn excludes_resolved_and_unconfirmed_conditions() {
+        let conditions = vec![
+            problem("resolved", "confirmed", json!({ "text": "Acute bronchitis" })),
+            problem("active", "unconfirmed", json!({ "text": "Asthma" })),
+            problem("active", "refuted", json!({ "text": "Diabetes" })),
+            problem("recurrence", "confirmed", json!({ "text": "Migraine" })),
+        ];
+
+        assert_eq!(chronic_conditions(&conditions), vec!["Migraine"]);
+    }
+
+    #[test]
+    fn shows_unnamed_conditions_by_their_code() {
+        let conditions = vec![
+            problem("active", "confirmed", json!({ "coding": [{ "system": SNOMED_SYSTEM, "code": "44054006" }] })),
+            problem("active", "confirmed", json!({ "coding": [{ "system": ICD10_CM_SYSTEM, "code": "E11.9" }] })),
+            problem("active", "confirmed", json!({ "coding": [{ "system": ICD10_CM_SYSTEM, "code": "E11.9" }] })),
+            problem("active", "confirmed", json!({ "coding": [{ "system": "http://example.org/local", "code": "x1" }] })),
+        ];
+
+        assert_eq!(chronic_conditions(&conditions), vec!["SNOMED 44054006", "ICD-10 E11.9"]);
+    }
+
+    #[test]
+    fn strips_clinical_semantic_tags_only() {
+        assert_eq!(without_semantic_tag("Fever (finding)"), "Fever");
+        assert_eq!(without_semantic_tag(" Essential hypertension (disorder) "), "Essential hypertension");
+        assert_eq!(without_semantic_tag("Gastric ulcer (morphologic abnormality)"), "Gastric ulcer");
+        assert_eq!(without_semantic_tag("Vitamin D (substance)"), "Vitamin D (substance)");
+        assert_eq!(without_semantic_tag("Type 2 diabetes"), "Type 2 diabetes");
+    }
+}
@@ -5,16 +5,22 @@ use aws_lambda_events::event::apigw::{
 use http::header::{HeaderMap, HeaderValue};
 use aws_lambda_events::encodings::Body;
 use crate::http_page::{
-    get_error_page, redirect_url,get_server_error, session_out,
+    get_connect_page, get_error_page, redirect_url,get_server_error, session_out,
 };
 use crate::libs::MainPageParams;
+use crate::scrab_errors::ScrabError;
 use crate::intro_console::main_console_page;
-use crate::oidc_request::{
-    TokenResponse, get_token_accesss, discover_endpoints,
+use crate::oidc_request::{TokenResponse, get_token_accesss};
+use crate::smart_config::discover_configuration;
+use crate::id_token::{authorize_user, id_token_expected, validate_id_token};
+use crate::standalone::{
+    EHR_CAPABILITIES, EHR_LAUNCH_SCOPE, EHR_REQUIRED_SCOPES, STANDALONE_CAPABILITIES,
+    STANDALONE_LAUNCH_SCOPE, STANDALONE_REQUIRED_SCOPES, find_server, standalone_servers,
 };
 use crate::oidc_database::{
     SessionData, get_session_data, save_to_dynamo,
 };
+use crate::token_manager::{DynamoTokenStore, SESSION_LENGTH, session_access_token};
 use lambda_runtime::tracing::{error, info};
 use rand::Rng;
 use sha2::{Sha256, Digest};
@@ -22,9 +28,6 @@ use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
 use url::Url;
 use std::env;
 
-// Constant for the session length
-const SESSION_LENGTH: i64 = 1 * 60 * 60 * 1000; // 1 hour
-
 pub(crate) async fn function_handler(
     event: LambdaEvent<ApiGatewayV2httpRequest>,
 ) -> Result<ApiGatewayV2httpResponse, Error> {
@@ -52,9 +55,6 @@ pub(crate) async fn function_handler(
         "lang=en; Path=/; Max-Age=604800".to_string(),
     ];
 
-    // Get Smart App callback
-    let scope = "meldrx-api cds profile openid launch patient/*.*";
-    
     // Extract domain name
     let domain_name = request_context.domain_name
         .as_deref()
@@ -75,25 +75,80 @@ pub(crate) async fn function_handler(
         "GET /launch" => {
             info!("Route key: {}", route_key);
             // Get the IssuerUrl and Launch
-            let launch = query_params.first("launch").unwrap_or_default();
-            let client_id = query_params.first("client").unwrap_or_default();
+            let launch = query_params.first("launch");
             let issuer = query_params.first("iss").unwrap_or_default();
 
+            // An EHR launch brings its launch context, a standalone launch is
+            // only allowed against a configured server, picked on the landing page
+            let (client_id, scope, required_scopes, capabilities) = match launch {
+                Some(_) => (
+                    query_params.first("client").unwrap_or_default().to_string(),
+                    EHR_LAUNCH_SCOPE,
+                    EHR_REQUIRED_SCOPES,
+                    EHR_CAPABILITIES,
+                ),
+                None => {
+                    let servers = standalone_servers();
+                    match find_server(&servers, issuer) {
+                        Some(server) => (
+                            server.client_id.clone(),
+                            STANDALONE_LAUNCH_SCOPE,
+                            STANDALONE_REQUIRED_SCOPES,
+                            STANDALONE_CAPABILITIES,
+                        ),
+                        None => {
+                            if !issuer.is_empty() {
+                                error!("Standalone launch of an unknown server: {} [E464]", issuer);
+                            }
+                            let message = get_connect_page(&servers);
+                            let body = Body::Text(message);
+                            return Ok(ApiGatewayV2httpResponse {
+                                status_code: 200,
+                                headers,
+                                multi_value_headers: HeaderMap::new(),
+                                body: Some(body),
+                                cookies,
+                                is_base64_encoded: false}
+                            );
+                        }
+                    }
+                }
+            };
+
             let state: String = generate_random_state(16);
+            let nonce: String = generate_random_state(32);
 
-            // Discover auth endpoints
-            let (auth_endpoint, token_endpoint) = match discover_endpoints(issuer).await {
-                Ok(endpoints) => endpoints,
+            // Discover the SMART configuration of the server
+            let config = match discover_configuration(issuer, actual_time_epoch / 1000).await {
+                Ok(config) => config,
                 Err(e) => {
                     error!("Error getting auth endpoints: {} [E463]", e);
                     let message = get_error_page("E463");
                     let body = Body::Text(message);
                     return Ok(ApiGatewayV2httpResponse {
                         status_code: 463,
-                        headers: headers,
+                        headers,
                         multi_value_headers: HeaderMap::new(),
                         body: Some(body),
-                        cookies: cookies,
+                        cookies,
+                        is_base64_encoded: false}
+                    );
+                }
+            };
+
+            // Refuse a launch the server cannot serve, and drop the scopes it does not offer
+            let scope = match config.check_launch(capabilities, scope, required_scopes) {
+                Ok(scope) => scope,
+                Err(e) => {
+                    error!("Server {} cannot serve the launch: {} [E465]", issuer, e);
+                    let message = get_error_page("E465");
+                    let body = Body::Text(message);
+                    return Ok(ApiGatewayV2httpResponse {
+                        status_code: 465,
+                        headers,
+                        multi_value_headers: HeaderMap::new(),
+                        body: Some(body),
+                        cookies,
                         is_base64_encoded: false}
                     );
                 }
@@ -104,7 +159,7 @@ pub(crate) async fn function_handler(
             let code_challenge = generate_code_challenge(&code_verifier);
 
             // Parse the base endpoint URL
-            let base_url = Url::parse(&auth_endpoint)?;
+            let base_url = Url::parse(&config.authorization_endpoint)?;
 
             // Create a mutable URL for building the query
             let mut url = base_url.clone();
@@ -112,14 +167,18 @@ pub(crate) async fn function_handler(
             // Save state to DynamoDB
             let state_data = SessionData {
                 pk: state.clone(),
-                client_id: client_id.to_string().into(),
+                client_id: client_id.clone().into(),
+                scope: Some(scope.clone()),
                 code_verifier: code_verifier.into(),
                 code_challenge: code_challenge.clone().into(),
                 code_challenge_method: Some("S256".to_string()),
-                auth_endpoint: auth_endpoint.into(),
-                token_endpoint: token_endpoint.into(),
+                auth_endpoint: config.authorization_endpoint.clone().into(),
+                token_endpoint: config.token_endpoint.clone().into(),
                 iss: issuer.to_string().into(),
                 session_timeout: (actual_time_epoch + SESSION_LENGTH).into(),
+                nonce: nonce.clone().into(),
+                jwks_uri: config.jwks_uri.clone(),
+                oidc_issuer: config.issuer.clone(),
                 ..Default::default()
             };
 
@@ -132,16 +191,22 @@ pub(crate) async fn function_handler(
             }
 
             // Add all query parameters
-            url.query_pairs_mut()
+            let mut query = url.query_pairs_mut();
+            query
                 .append_pair("response_type", "code")
-                .append_pair("client_id", client_id)
-                .append_pair("scope", scope)
+                .append_pair("client_id", &client_id)
+                .append_pair("scope", &scope)
                 .append_pair("redirect_uri", &redirect_uri)
-                .append_pair("code_challenge", &code_challenge)
-                .append_pair("launch", launch)
+                .append_pair("code_challenge", &code_challenge);
+            if let Some(launch) = launch {
+                query.append_pair("launch", launch);
+            }
+            query
                 .append_pair("aud", issuer)
                 .append_pair("state", &state)
+                .append_pair("nonce", &nonce)
                 .append_pair("code_challenge_method", "S256");
+            drop(query);
         
             // Convert Url to string
             let link = url.to_string();
@@ -149,10 +214,10 @@ pub(crate) async fn function_handler(
             let body = Body::Text(message);
             return Ok(ApiGatewayV2httpResponse {
                 status_code: 200,
-                headers: headers,
+                headers,
                 multi_value_headers: HeaderMap::new(),
                 body: Some(body),
-                cookies: cookies,
+                cookies,
                 is_base64_encoded: false}
             );
         }
@@ -173,6 +238,10 @@ pub(crate) async fn function_handler(
             let mut auth_endpoint = String::new();
             let mut token_endpoint = String::new();
             let mut session_timeout: i64 = 0;
+            let mut nonce = String::new();
+            let mut scope = String::new();
+            let mut jwks_uri: Option<String> = None;
+            let mut oidc_issuer: Option<String> = None;
 
             match get_session_data(
                 state, 
@@ -180,6 +249,10 @@ pub(crate) async fn function_handler(
             ).await {
                 Ok(Some(sd)) => {
                     if let Some(av) = sd.iss { issuer = av.clone(); }
+                    if let Some(av) = sd.nonce { nonce = av.clone(); }
+                    if let Some(av) = sd.scope { scope = av.clone(); }
+                    jwks_uri = sd.jwks_uri;
+                    oidc_issuer = sd.oidc_issuer;
                     if let Some(av) = sd.client_id { client_id = av.clone(); }
                     if let Some(av) = sd.code_challenge { code_challenge = av.clone(); }
                     if let Some(av) = sd.code_challenge_method { code_challenge_method = av.clone(); }
@@ -208,23 +281,103 @@ pub(crate) async fn function_handler(
                     let body = Body::Text(message);
                     return Ok(ApiGatewayV2httpResponse {
                         status_code: 471,
-                        headers: headers,
+                        headers,
                         multi_value_headers: HeaderMap::new(),
                         body: Some(body),
-                        cookies: cookies,
+                        cookies,
                         is_base64_encoded: false}
                     );
                 }
             };
 
+            // ~~~~~~~~~~~~~~~~~~~~ ID TOKEN ~~~~~~~~~~~~~~~~~~~~~~~~~~
+            let mut fhir_user: Option<String> = None;
+            let id_token = match &token_resp.id_token {
+                Some(id_token) => Some(id_token),
+                None if id_token_expected(&scope, token_resp.scope.as_deref()) => {
+                    error!("No id_token in the response to an openid launch [E472]");
+                    let message = get_server_error("E472");
+                    let body = Body::Text(message);
+                    return Ok(ApiGatewayV2httpResponse {
+                        status_code: 472,
+                        headers,
+                        multi_value_headers: HeaderMap::new(),
+                        body: Some(body),
+                        cookies,
+                        is_base64_encoded: false}
+                    );
+                }
+                None => None,
+            };
+            if let Some(id_token) = id_token {
+                let validated = match &jwks_uri {
+                    Some(jwks_uri) => validate_id_token(
+                        id_token,
+                        jwks_uri,
+                        oidc_issuer.as_deref().unwrap_or(&issuer),
+                        &client_id,
+                        &nonce,
+                        actual_time_epoch / 1000,
+                    ).await,
+                    None => Err(ScrabError::Configuration("No jwks_uri to validate the id_token".to_string())),
+                };
+                match validated {
+                    Ok(claims) => fhir_user = claims.fhir_user,
+                    Err(e) => {
+                        error!("Error validating id_token: {} [E472]", e);
+                        let message = get_server_error("E472");
+                        let body = Body::Text(message);
+                        return Ok(ApiGatewayV2httpResponse {
+                            status_code: 472,
+                            headers,
+                            multi_value_headers: HeaderMap::new(),
+                            body: Some(body),
+                            cookies,
+                            is_base64_encoded: false}
+                        );
+                    }
+                }
+            }
+
+            // The patient of the EHR context, or the one picked in a standalone launch
+            let Some(patient_id) = token_resp.patient.clone() else {
+                error!("No patient in the token response [E473]");
+                let message = get_server_error("E473");
+                let body = Body::Text(message);
+                return Ok(ApiGatewayV2httpResponse {
+                    status_code: 473,
+                    headers,
+                    multi_value_headers: HeaderMap::new(),
+                    body: Some(body),
+                    cookies,
+                    is_base64_encoded: false}
+                );
+            };
             let token = token_resp.access_token.clone();
-            let patient_id = token_resp.patient.clone().unwrap_or_default();
+
+            // The user must be allowed to open the record of the patient in context
+            let granted_scope = token_resp.scope.clone().unwrap_or_else(|| scope.clone());
+            if let Err(e) = authorize_user(fhir_user.as_deref(), &granted_scope, &patient_id) {
+                error!("User not authorized: {} [E474]", e);
+                let message = get_server_error("E474");
+                let body = Body::Text(message);
+                return Ok(ApiGatewayV2httpResponse {
+                    status_code: 474,
+                    headers,
+                    multi_value_headers: HeaderMap::new(),
+                    body: Some(body),
+                    cookies,
+                    is_base64_encoded: false}
+                );
+            }
 
             let session_data = SessionData {
                 pk: state.to_string(),
                 access_token: Some(token.clone()),
                 expires_in: token_resp.expires_in,
-                scope: Some(scope.to_string()),
+                refresh_token: token_resp.refresh_token,
+                token_expires_at: token_resp.expires_in.map(|seconds| actual_time_epoch + i64::from(seconds) * 1000),
+                scope: Some(granted_scope),
                 token_type: token_resp.token_type,
                 id_token: token_resp.id_token,
                 session_state: Some(session_state.to_string()),
@@ -237,6 +390,10 @@ pub(crate) async fn function_handler(
                 iss: Some(issuer.clone()),
                 session_timeout: Some(session_timeout),
                 patient: Some(patient_id.clone()),
+                nonce: Some(nonce.clone()),
+                jwks_uri: jwks_uri.clone(),
+                oidc_issuer: oidc_issuer.clone(),
+                fhir_user: fhir_user.clone(),
             };
         
             match save_to_dynamo(
@@ -257,7 +414,7 @@ pub(crate) async fn function_handler(
 
             // Add all query parameters
             url.query_pairs_mut()
-                .append_pair("state", &state)
+                .append_pair("state", state)
                 .append_pair("code_challenge", &code_challenge)
                 .append_pair("code_challenge_method", &code_challenge_method);
 
@@ -267,10 +424,10 @@ pub(crate) async fn function_handler(
             let body = Body::Text(message);
             return Ok(ApiGatewayV2httpResponse {
                 status_code: 200,
-                headers: headers,
+                headers,
                 multi_value_headers: HeaderMap::new(),
                 body: Some(body),
-                cookies: cookies,
+                cookies,
                 is_base64_encoded: false}
             );
         }
@@ -281,36 +438,53 @@ pub(crate) async fn function_handler(
             // Extract parameters
             let state = query_params.first("state").unwrap_or_default();        
 
-            // Set mutable variables
-            let mut issuer = String::new();
-            let mut token = String::new();
-            let mut patient_id = String::new();
-            let mut session_timeout: i64 = 0;
-
-            match get_session_data(
+            // Load the session and renew its access token if it has expired
+            let mut session = match get_session_data(
                 state, 
                 &table_name
             ).await {
-                Ok(Some(sd)) => {
-                    if let Some(av) = sd.iss { issuer = av.clone(); }
-                    if let Some(av) = sd.access_token { token = av.clone(); }
-                    if let Some(av) = sd.patient { patient_id = av.clone(); }
-                    if let Some(av) = sd.session_timeout { session_timeout = av; }
+                Ok(Some(sd)) => sd,
+                Ok(None) => {
+                    error!("No session data found [E711]");
+                    SessionData::default()
                 },
-                Ok(None) => error!("No session data found [E711]"),
-                Err(e) => error!("Error retrieving session data: {:?} [E712]", e),
-            }
+                Err(e) => {
+                    error!("Error retrieving session data: {:?} [E712]", e);
+                    SessionData::default()
+                },
+            };
+
+            let store = DynamoTokenStore::new(&table_name);
+            let token = match session_access_token(&store, &mut session, actual_time_epoch).await {
+                Ok(token) => token,
+                Err(e) => {
+                    error!("Session timed out or its token renewal failed: {} [E713]", e);
+                    let message = session_out("E713");
+                    let body = Body::Text(message);
+                    return Ok(ApiGatewayV2httpResponse {
+                        status_code: 713,
+                        headers,
+                        multi_value_headers: HeaderMap::new(),
+                        body: Some(body),
+                        cookies,
+                        is_base64_encoded: false}
+                    );
+                }
+            };
+            let issuer = session.iss.clone().unwrap_or_default();
+            let patient_id = session.patient.clone().unwrap_or_default();
 
-            if session_timeout != 0 && session_timeout < actual_time_epoch {
-                error!("Session timeout [E713]");
-                let message = session_out("E713");
+            // Checked on every page, the session outlives the launch it was authorized at
+            if let Err(e) = authorize_user(session.fhir_user.as_deref(), session.scope.as_deref().unwrap_or_default(), &patient_id) {
+                error!("User not authorized: {} [E714]", e);
+                let message = get_server_error("E714");
                 let body = Body::Text(message);
                 return Ok(ApiGatewayV2httpResponse {
-                    status_code: 713,
-                    headers: headers,
+                    status_code: 714,
+                    headers,
                     multi_value_headers: HeaderMap::new(),
                     body: Some(body),
-                    cookies: cookies,
+                    cookies,
                     is_base64_encoded: false}
                 );
             }
@@ -319,6 +493,7 @@ pub(crate) async fn function_handler(
                 iss: issuer.clone(),
                 access_token: token.clone(),
                 patient_id: patient_id.clone(),
+                fhir_user: session.fhir_user.clone(),
             };
 
             let message = match main_console_page(&mpage_params).await {
@@ -331,10 +506,10 @@ pub(crate) async fn function_handler(
             let body = Body::Text(message);
             return Ok(ApiGatewayV2httpResponse {
                 status_code: 200,
-                headers: headers,
+                headers,
                 multi_value_headers: HeaderMap::new(),
                 body: Some(body),
-                cookies: cookies,
+                cookies,
                 is_base64_encoded: false}
             );
         }
@@ -347,10 +522,10 @@ pub(crate) async fn function_handler(
     let body = Body::Text(message);
     let resp = ApiGatewayV2httpResponse {
         status_code: 495,
-        headers: headers,
+        headers,
         multi_value_headers: HeaderMap::new(),
         body: Some(body),
-        cookies: cookies,
+        cookies,
         is_base64_encoded: false,
     };
     Ok(resp)
@@ -380,7 +555,7 @@ fn generate_code_challenge(code_verifier: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(code_verifier.as_bytes());
     let hash = hasher.finalize();
-    URL_SAFE_NO_PAD.encode(&hash)
+    URL_SAFE_NO_PAD.encode(hash)
 }
 
 // Verify that a code verifier matches a previously created challenge
@@ -1,3 +1,5 @@
+use crate::standalone::StandaloneServer;
+
 pub fn get_main_page(
     json_data: &str,
 ) -> String {
@@ -71,6 +73,12 @@ pub fn get_main_page(
                 margin-bottom: 5px;
             }
             
+            .logged-user {
+                color: #a0aec0;
+                font-size: 13px;
+                text-align: right;
+            }
+            
             .patient-details {
                 display: flex;
                 gap: 10px;
@@ -359,6 +367,21 @@ pub fn get_main_page(
                 font-size: 14px;
             }
             
+            .timeline-ai {
+                color: #a0aec0;
+                font-size: 13px;
+                font-style: italic;
+                margin-top: 4px;
+            }
+            
+            .timeline-ai-label {
+                font-style: normal;
+                font-size: 11px;
+                border: 1px solid #a0aec0;
+                border-radius: 4px;
+                padding: 0 4px;
+            }
+            
             .timeline-highlight .timeline-title {
                 color: var(--highlight-color);
             }
@@ -393,6 +416,7 @@ pub fn get_main_page(
                         <span id="emergencyContact"></span>
                     </div>
                 </div>
+                <div class="logged-user" id="loggedInUser"></div>
             </div>
             
             <div class="medical-info">
@@ -470,6 +494,9 @@ pub fn get_main_page(
                 document.getElementById('patientPhone').textContent = patientData.phone;
                 document.getElementById('patientEmail').textContent = patientData.email;
                 document.getElementById('emergencyContact').textContent = patientData.emergencyContact;
+                if (patientData.loggedInUser) {
+                    document.getElementById('loggedInUser').textContent = `Signed in as ${patientData.loggedInUser}`;
+                }
                 
                 // Allergies
                 const allergiesContainer = document.getElementById('allergiesContainer');
@@ -509,16 +536,19 @@ pub fn get_main_page(
                     medicationsContainer.appendChild(medCard);
                 });
                 
-                // Latest vital signs
-                const latestVitals = patientData.vitalSigns[patientData.vitalSigns.length - 1];
+                // Latest vital signs, each from the most recent reading that has it
+                const latestVital = key => {
+                    const vital = [...patientData.vitalSigns].reverse().find(v => v[key] != null);
+                    return vital ? vital[key] : 'n/a';
+                };
                 const vitalSignsGrid = document.getElementById('vitalSignsGrid');
                 
                 const vitalTypes = [
-                    { name: 'Heart Rate', value: latestVitals.heartRate, unit: '\xa0 bpm' },
-                    { name: 'Blood Pressure', value: latestVitals.bloodPressure, unit: '\xa0 mmHg' },
-                    { name: 'Temperature', value: latestVitals.temperature, unit: '\xa0 °F' },
-                    { name: 'Respiratory Rate', value: latestVitals.respiratoryRate, unit: '\xa0 bpm' },
-                    { name: 'Oxygen Saturation', value: latestVitals.oxygenSaturation, unit: '\xa0 %' }
+                    { name: 'Heart Rate', value: latestVital('heartRate'), unit: '\xa0 bpm' },
+                    { name: 'Blood Pressure', value: latestVital('bloodPressure'), unit: '\xa0 mmHg' },
+                    { name: 'Temperature', value: latestVital('temperature'), unit: '\xa0 °F' },
+                    { name: 'Respiratory Rate', value: latestVital('respiratoryRate'), unit: '\xa0 bpm' },
+                    { name: 'Oxygen Saturation', value: latestVital('oxygenSaturation'), unit: '\xa0 %' }
                 ];
                 
                 vitalTypes.forEach(vital => {
@@ -645,6 +675,17 @@ pub fn get_main_page(
                     
                     timelineContent.appendChild(timelineTitle);
                     timelineContent.appendChild(timelineDescription);
+
+                    if (item.aiDescription) {
+                        const timelineAi = document.createElement('div');
+                        timelineAi.className = 'timeline-ai';
+                        const aiLabel = document.createElement('span');
+                        aiLabel.className = 'timeline-ai-label';
+                        aiLabel.textContent = 'AI-generated';
+                        timelineAi.appendChild(aiLabel);
+                        timelineAi.appendChild(document.createTextNode(` ${item.aiDescription}`));
+                        timelineContent.appendChild(timelineAi);
+                    }
                     
                     timelineItem.appendChild(timelineYear);
                     timelineItem.appendChild(timelineContent);
@@ -704,7 +745,7 @@ pub fn get_main_page(
                 const padding = { top: 20, right: 30, bottom: 40, left: 40 };
                 
                 // Extract heart rate data
-                const heartRateData = patientData.vitalSigns.map(vital => ({
+                const heartRateData = patientData.vitalSigns.filter(vital => vital.heartRate != null).map(vital => ({
                     date: new Date(vital.date),
                     value: vital.heartRate
                 }));
@@ -714,7 +755,7 @@ pub fn get_main_page(
                 const maxValue = Math.max(...heartRateData.map(d => d.value)) + 5;
                 
                 // Scale functions
-                const xScale = (index) => padding.left + (index / (heartRateData.length - 1)) * (width - padding.left - padding.right);
+                const xScale = (index) => padding.left + (index / Math.max(heartRateData.length - 1, 1)) * (width - padding.left - padding.right);
                 const yScale = (value) => height - padding.bottom - ((value - minValue) / (maxValue - minValue)) * (height - padding.top - padding.bottom);
                 
                 // Draw grid lines
@@ -784,14 +825,13 @@ pub fn get_main_page(
                 const padding = { top: 20, right: 30, bottom: 40, left: 40 };
                 
                 // Extract blood pressure data
-                const bpData = patientData.vitalSigns.map(vital => {
-                    const [systolic, diastolic] = vital.bloodPressure.split('/').map(Number);
-                    return {
+                const bpData = patientData.vitalSigns
+                    .filter(vital => vital.systolic != null && vital.diastolic != null)
+                    .map(vital => ({
                         date: new Date(vital.date),
-                        systolic,
-                        diastolic
-                    };
-                });
+                        systolic: vital.systolic,
+                        diastolic: vital.diastolic
+                    }));
                 
                 // Find min and max values
                 const minSystolic = Math.min(...bpData.map(d => d.systolic)) - 5;
@@ -803,7 +843,7 @@ pub fn get_main_page(
                 const maxValue = Math.max(maxSystolic, maxDiastolic);
                 
                 // Scale functions
-                const xScale = (index) => padding.left + (index / (bpData.length - 1)) * (width - padding.left - padding.right);
+                const xScale = (index) => padding.left + (index / Math.max(bpData.length - 1, 1)) * (width - padding.left - padding.right);
                 const yScale = (value) => height - padding.bottom - ((value - minValue) / (maxValue - minValue)) * (height - padding.top - padding.bottom);
                 
                 // Draw grid lines
@@ -902,7 +942,7 @@ pub fn get_main_page(
                 const padding = { top: 20, right: 30, bottom: 40, left: 40 };
                 
                 // Extract temperature data
-                const tempData = patientData.vitalSigns.map(vital => ({
+                const tempData = patientData.vitalSigns.filter(vital => vital.temperature != null).map(vital => ({
                     date: new Date(vital.date),
                     value: vital.temperature
                 }));
@@ -912,7 +952,7 @@ pub fn get_main_page(
                 const maxValue = Math.max(...tempData.map(d => d.value)) + 0.5;
                 
                 // Scale functions
-                const xScale = (index) => padding.left + (index / (tempData.length - 1)) * (width - padding.left - padding.right);
+                const xScale = (index) => padding.left + (index / Math.max(tempData.length - 1, 1)) * (width - padding.left - padding.right);
                 const yScale = (value) => height - padding.bottom - ((value - minValue) / (maxValue - minValue)) * (height - padding.top - padding.bottom);
                 
                 // Draw grid lines
@@ -983,11 +1023,11 @@ pub fn get_main_page(
     </body></html>
     "#.to_string();
 
-    let response_fmt = response.replace("<json_data_placeholder>", json_data);
-    response_fmt
+    response.replace("<json_data_placeholder>", json_data)
 }
 
-pub fn get_connect_page(auth_link: &str) -> String {
+/// Landing page of a standalone launch, one button per configured FHIR server
+pub fn get_connect_page(servers: &[StandaloneServer]) -> String {
     
     let response = r#"
         <!DOCTYPE html>
@@ -995,7 +1035,7 @@ pub fn get_connect_page(auth_link: &str) -> String {
         <head>
             <meta charset="UTF-8">
             <meta name="viewport" content="width=device-width, initial-scale=1.0">
-            <title>Authorize App - MeldRx</title>
+            <title>Connect - ScrabMD</title>
             <style>
                 body {
                     font-family: 'Inter', -apple-system, BlinkMacSystemFont, Arial, sans-serif;
@@ -1042,6 +1082,11 @@ pub fn get_connect_page(auth_link: &str) -> String {
                     font-weight: 500;
                     transition: all 0.2s ease;
                 }
+                .server-list {
+                    display: flex;
+                    flex-direction: column;
+                    gap: 12px;
+                }
                 .button:hover {
                     background-color: #2563eb;
                     transform: translateY(-1px);
@@ -1094,9 +1139,9 @@ pub fn get_connect_page(auth_link: &str) -> String {
         </head>
         <body>
             <div class="container">
-                <h1>Authorize App for MeldRx</h1>
-                <p>Authorize the application to access your healthcare data via MeldRx. Your data will be handled securely and in compliance with HIPAA regulations.</p>
-                <a href="<authorize>" class="button">Authorize App</a>
+                <h1>Connect to a FHIR Server</h1>
+                <p>Choose the FHIR server to sign in to, then select the patient to open. Your data will be handled securely and in compliance with HIPAA regulations.</p>
+                <div class="server-list"><servers></div>
                 <div class="security-badge">
                     <svg width="16" height="16" viewBox="0 0 16 16" fill="none" xmlns="http://www.w3.org/2000/svg">
                         <path d="M8 0L2 2V7.5C2 11.5 4.5 15 8 16C11.5 15 14 11.5 14 7.5V2L8 0Z" fill="\#64748b"/>
@@ -1112,8 +1157,26 @@ pub fn get_connect_page(auth_link: &str) -> String {
         </html>
     "#;
 
-    let response_fmt = response.replace("<authorize>", auth_link);
-    response_fmt
+    let buttons: String = if servers.is_empty() {
+        "<p>No FHIR server is configured for a standalone launch.</p>".to_string()
+    } else {
+        servers.iter()
+            .map(|server| format!(
+                r#"<a href="{}" class="button">{}</a>"#,
+                escape_html(&server.launch_link()),
+                escape_html(&server.name),
+            ))
+            .collect()
+    };
+
+    response.replace("<servers>", &buttons)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
 }
 
 pub fn redirect_url(url: &str) -> String {
@@ -1312,9 +1375,8 @@ pub fn redirect_url(url: &str) -> String {
     </body>
     </html>
     "#;
-    
-    let response_fmt = response.replace("<url_to_redirect>", url);
-    response_fmt
+
+    response.replace("<url_to_redirect>", url)
 }
 
 pub fn get_error_page(error_code: &str) -> String {
@@ -1436,9 +1498,8 @@ pub fn get_error_page(error_code: &str) -> String {
         </body>
         </html>
     "#;
-    
-    let response_fmt = response.replace("<error_code>", error_code);
-    response_fmt
+
+    response.replace("<error_code>", error_code)
 }
 
 pub fn get_server_error(error_code: &str) -> String {
@@ -1560,9 +1621,8 @@ pub fn get_server_error(error_code: &str) -> String {
         </body>
         </html>
     "#;
-    
-    let response_fmt = response.replace("<error_code>", error_code);
-    response_fmt
+
+    response.replace("<error_code>", error_code)
 }
 
 pub fn session_out(error_code: &str) -> String {
@@ -1684,9 +1744,8 @@ pub fn session_out(error_code: &str) -> String {
         </body>
         </html>
     "#;
-    
-    let response_fmt = response.replace("<error_code>", error_code);
-    response_fmt
+
+    response.replace("<error_code>", error_code)
 }
 
 
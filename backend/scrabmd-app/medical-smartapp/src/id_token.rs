@@ -0,0 +1,294 @@
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use lambda_runtime::tracing::{error, info};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use crate::scrab_errors::ScrabError;
+
+// Algorithms the authorization server may sign the id_token with
+const ALLOWED_ALGORITHMS: &[Algorithm] = &[Algorithm::RS256, Algorithm::ES384];
+
+// Clock skew tolerated on exp/iat
+const LEEWAY_SECS: u64 = 30;
+
+// How long a fetched key set is reused for a jwks_uri
+const JWKS_TTL_SECS: i64 = 60 * 60; // 1 hour
+
+// Resource types the fhirUser of a SMART launch can be
+const USER_TYPES: &[&str] = &["Practitioner", "PractitionerRole", "Patient", "RelatedPerson", "Person"];
+
+/// Claims of the OpenID Connect id_token returned with the access token
+#[derive(Debug, Deserialize, Clone)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub nonce: Option<String>,
+    /// Reference to the logged-in user, e.g. `Practitioner/123`
+    #[serde(rename = "fhirUser")]
+    pub fhir_user: Option<String>,
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ JWKS ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Fetched key sets by `jwks_uri`, with the time they were fetched
+#[derive(Default)]
+pub struct JwksCache {
+    key_sets: Mutex<HashMap<String, (JwkSet, i64)>>,
+}
+
+impl JwksCache {
+    pub fn shared() -> &'static JwksCache {
+        static CACHE: OnceLock<JwksCache> = OnceLock::new();
+        CACHE.get_or_init(JwksCache::default)
+    }
+
+    pub fn get(&self, jwks_uri: &str, now: i64) -> Result<Option<JwkSet>, ScrabError> {
+        let key_sets = self.key_sets.lock()
+            .map_err(|e| ScrabError::GenericError(e.to_string()))?;
+        Ok(key_sets.get(jwks_uri)
+            .filter(|(_, fetched_at)| fetched_at + JWKS_TTL_SECS > now)
+            .map(|(jwks, _)| jwks.clone()))
+    }
+
+    pub fn insert(&self, jwks_uri: &str, jwks: &JwkSet, now: i64) -> Result<(), ScrabError> {
+        let mut key_sets = self.key_sets.lock()
+            .map_err(|e| ScrabError::GenericError(e.to_string()))?;
+        key_sets.insert(jwks_uri.to_string(), (jwks.clone(), now));
+        Ok(())
+    }
+}
+
+/// Fetch the keys the authorization server signs with from its `jwks_uri`
+pub async fn fetch_jwks(jwks_uri: &str) -> Result<JwkSet, ScrabError> {
+    let client = reqwest::Client::builder()
+        .use_rustls_tls()
+        .build()?;
+
+    let response = client.get(jwks_uri).send().await?;
+    if !response.status().is_success() {
+        return Err(ScrabError::Authentication(format!("JWKS request failed with {}", response.status())));
+    }
+    Ok(response.json().await?)
+}
+
+/// Verify the signature, `iss`, `aud`, `exp` and `nonce` of an id_token against a key set
+pub fn verify_id_token(
+    id_token: &str,
+    jwks: &JwkSet,
+    issuer: &str,
+    client_id: &str,
+    nonce: &str,
+) -> Result<IdTokenClaims, ScrabError> {
+    let header = decode_header(id_token)
+        .map_err(|e| ScrabError::Authentication(format!("Invalid id_token header: {}", e)))?;
+
+    if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+        return Err(ScrabError::Authentication(format!("Algorithm {:?} is not allowed", header.alg)));
+    }
+
+    let jwk = signing_key(jwks, header.kid.as_deref())
+        .ok_or_else(|| ScrabError::Authentication("No matching key in the JWKS".to_string()))?;
+
+    let key = DecodingKey::from_jwk(jwk)
+        .map_err(|e| ScrabError::Authentication(format!("Invalid JWK: {}", e)))?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.leeway = LEEWAY_SECS;
+    validation.set_issuer(&[issuer.trim_end_matches('/'), issuer]);
+    validation.set_audience(&[client_id]);
+    validation.set_required_spec_claims(&["iss", "aud", "exp", "sub"]);
+
+    let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+        .map_err(|e| ScrabError::Authentication(format!("Invalid id_token: {}", e)))?
+        .claims;
+
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err(ScrabError::Authentication("id_token nonce does not match the launch".to_string()));
+    }
+
+    Ok(claims)
+}
+
+/// Key of the set an id_token with header `kid` is signed with
+fn signing_key<'a>(jwks: &'a JwkSet, kid: Option<&str>) -> Option<&'a Jwk> {
+    match kid {
+        Some(kid) => jwks.find(kid),
+        None if jwks.keys.len() == 1 => jwks.keys.first(),
+        None => None,
+    }
+}
+
+/// Verify the id_token of a launch with the JWKS of [`JwksCache`], fetching it
+/// again when the token is signed with a key the cached set does not have
+pub async fn validate_id_token(
+    id_token: &str,
+    jwks_uri: &str,
+    issuer: &str,
+    client_id: &str,
+    nonce: &str,
+    now: i64,
+) -> Result<IdTokenClaims, ScrabError> {
+    let kid = decode_header(id_token).ok().and_then(|header| header.kid);
+    let cache = JwksCache::shared();
+
+    let jwks = match cache.get(jwks_uri, now)? {
+        Some(jwks) if signing_key(&jwks, kid.as_deref()).is_some() => jwks,
+        _ => {
+            let jwks = fetch_jwks(jwks_uri).await.inspect_err(|e| {
+                error!("Error fetching JWKS from {}: {:?}", jwks_uri, e);
+            })?;
+            cache.insert(jwks_uri, &jwks, now)?;
+            jwks
+        }
+    };
+
+    let claims = verify_id_token(id_token, &jwks, issuer, client_id, nonce)?;
+    info!("Validated id_token of {} from {}", claims.sub, claims.iss);
+    Ok(claims)
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ USER ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+fn has_scope(scope: &str, wanted: &str) -> bool {
+    scope.split_whitespace().any(|s| s == wanted)
+}
+
+/// Whether the token response must carry an id_token: `openid` was granted, or
+/// requested when the server does not return the granted scope
+pub fn id_token_expected(requested_scope: &str, granted_scope: Option<&str>) -> bool {
+    has_scope(granted_scope.unwrap_or(requested_scope), "openid")
+}
+
+/// Check that the logged-in `fhir_user` may open the record of `patient_id`. When
+/// `fhirUser` was granted the user must be known, and a Patient user only opens their own record.
+pub fn authorize_user(fhir_user: Option<&str>, scope: &str, patient_id: &str) -> Result<(), ScrabError> {
+    let Some(fhir_user) = fhir_user else {
+        if has_scope(scope, "fhirUser") {
+            return Err(ScrabError::Authentication("No fhirUser for a launch that was granted it".to_string()));
+        }
+        return Ok(());
+    };
+
+    // Relative `Practitioner/123` or absolute `https://ehr.example.org/fhir/Practitioner/123`
+    let mut segments = fhir_user.trim_end_matches('/').rsplit('/');
+    let (id, resource_type) = (segments.next().unwrap_or_default(), segments.next().unwrap_or_default());
+
+    if !USER_TYPES.contains(&resource_type) || id.is_empty() {
+        return Err(ScrabError::Authentication(format!("{} cannot use the app", fhir_user)));
+    }
+    if resource_type == "Patient" && id != patient_id {
+        return Err(ScrabError::Authentication(format!("{} cannot open the record of Patient/{}", fhir_user, patient_id)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    const JWKS: &str = include_str!("../tests/fixtures/id-token-jwks.json");
+    const RSA_KEY: &str = include_str!("../tests/fixtures/id-token-rsa.pem");
+    const EC_KEY: &str = include_str!("../tests/fixtures/id-token-ec.pem");
+
+    const ISSUER: &str = "https://auth.example.org";
+    const CLIENT_ID: &str = "scrab-smartapp";
+    const NONCE: &str = "nonce-1";
+
+    fn claims() -> serde_json::Value {
+        let now = Utc::now().timestamp();
+        json!({
+            "iss": ISSUER,
+            "sub": "user-1",
+            "aud": CLIENT_ID,
+            "iat": now,
+            "exp": now + 300,
+            "nonce": NONCE,
+            "fhirUser": "Practitioner/practitioner-1",
+        })
+    }
+
+    fn rsa_token(claims: serde_json::Value) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some("id-token-rs256".to_string());
+        let key = EncodingKey::from_rsa_pem(RSA_KEY.as_bytes()).unwrap();
+        encode(&header, &claims, &key).unwrap()
+    }
+
+    fn verify(token: &str) -> Result<IdTokenClaims, ScrabError> {
+        verify_id_token(token, &serde_json::from_str(JWKS).unwrap(), ISSUER, CLIENT_ID, NONCE)
+    }
+
+    #[test]
+    fn accepts_signed_id_tokens() {
+        let verified = verify(&rsa_token(claims())).unwrap();
+        assert_eq!(verified.fhir_user.as_deref(), Some("Practitioner/practitioner-1"));
+
+        let mut header = Header::new(Algorithm::ES384);
+        header.kid = Some("id-token-es384".to_string());
+        let key = EncodingKey::from_ec_pem(EC_KEY.as_bytes()).unwrap();
+        assert!(verify(&encode(&header, &claims(), &key).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_id_tokens() {
+        for (claim, value) in [
+            ("iss", json!("https://other.example.org")),
+            ("aud", json!("other-client")),
+            ("exp", json!(Utc::now().timestamp() - 600)),
+            ("nonce", json!("nonce-2")),
+        ] {
+            let mut invalid = claims();
+            invalid[claim] = value;
+            assert!(verify(&rsa_token(invalid)).is_err(), "accepted a wrong {}", claim);
+        }
+
+        let mut header = Header::new(Algorithm::RS384);
+        header.kid = Some("id-token-rs256".to_string());
+        let key = EncodingKey::from_rsa_pem(RSA_KEY.as_bytes()).unwrap();
+        assert!(verify(&encode(&header, &claims(), &key).unwrap()).is_err());
+    }
+
+    #[test]
+    fn caches_key_sets_by_jwks_uri() {
+        let cache = JwksCache::default();
+        let jwks: JwkSet = serde_json::from_str(JWKS).unwrap();
+        cache.insert("https://auth.example.org/jwks", &jwks, 1_000).unwrap();
+
+        assert_eq!(cache.get("https://auth.example.org/jwks", 1_000 + 60).unwrap(), Some(jwks.clone()));
+        assert_eq!(cache.get("https://auth.example.org/jwks", 1_000 + JWKS_TTL_SECS).unwrap(), None);
+        assert_eq!(cache.get("https://other.example.org/jwks", 1_000).unwrap(), None);
+
+        assert!(signing_key(&jwks, Some("id-token-rs256")).is_some());
+        assert!(signing_key(&jwks, Some("rotated-key")).is_none());
+    }
+
+    #[test]
+    fn expects_an_id_token_when_openid_is_granted() {
+        assert!(id_token_expected("openid fhirUser launch", None));
+        assert!(id_token_expected("launch", Some("openid launch")));
+        assert!(!id_token_expected("openid fhirUser launch", Some("launch patient/*.read")));
+        assert!(!id_token_expected("launch patient/*.read", None));
+    }
+
+    #[test]
+    fn authorizes_users_by_their_fhir_user() {
+        let scope = "openid fhirUser launch patient/*.read";
+        assert!(authorize_user(Some("Practitioner/practitioner-1"), scope, "p-1").is_ok());
+        assert!(authorize_user(Some("https://ehr.example.org/fhir/PractitionerRole/role-1"), scope, "p-1").is_ok());
+        assert!(authorize_user(Some("Patient/p-1"), scope, "p-1").is_ok());
+        assert!(authorize_user(None, "launch patient/*.read", "p-1").is_ok());
+
+        for (fhir_user, case) in [
+            (Some("Patient/p-2"), "another patient"),
+            (Some("Device/d-1"), "a device"),
+            (Some("Practitioner/"), "no id"),
+            (None, "no user for a granted fhirUser"),
+        ] {
+            assert!(matches!(authorize_user(fhir_user, scope, "p-1"), Err(ScrabError::Authentication(_))), "authorized {}", case);
+        }
+    }
+}
@@ -1,12 +1,20 @@
 use crate::oidc_request::get_mdata;
 use crate::http_page::get_main_page;
-use crate::libs::{MedicalRecord, MainPageParams, Patient};
+use crate::libs::{MedicalRecord, MainPageParams};
+use scrab_fhir::{FhirClient, Patient, Reference, ReferenceResolver};
 use crate::libs::{
     DefaultValueSetter, Medication, VitalSign, Treatment, TimelineEvent,
     Appointment, extract_ethnicity,
 };
 use lambda_runtime::tracing::error;
 use crate::llm_allergies::extract_allergies;
+use crate::conditions::extract_chronic_conditions;
+use crate::medications::extract_medications;
+use crate::vital_signs::extract_vital_signs;
+use crate::timeline::extract_timeline;
+use crate::treatments::extract_treatments;
+use crate::appointments::extract_appointments;
+use crate::references::reference_name;
 use chrono::{NaiveDate, Utc};
 use crate::scrab_errors::ScrabError;
 
@@ -16,6 +24,8 @@ pub struct PatientDetails {
     pub gender: String,
     pub ethnicity: String,
     pub phone: String,
+    pub email: String,
+    pub emergency_contact: String,
     pub age: u32,
 }
 
@@ -47,12 +57,41 @@ pub async fn main_console_page(
 
     let patient_details: PatientDetails = extract_patient_details(&patient);
 
+    // One resolver for the whole page, so a referenced resource is read at most once
+    let resolver = ReferenceResolver::new(FhirClient::new(&params.iss, &params.access_token)?);
+    if let Ok(patient_value) = serde_json::from_str(&patient_data) {
+        resolver.add_resource(&patient_value);
+    }
+
+    // Logged-in user from the fhirUser claim of the id_token
+    let logged_in_user = match &params.fhir_user {
+        Some(fhir_user) => reference_name(&resolver, &Reference::new(fhir_user), &[]).await
+            .unwrap_or_else(|| fhir_user.clone()),
+        None => String::new(),
+    };
+
     // Create a new record with all default 'n/a' values
     let mut record = MedicalRecord::new_default();
 
     // Extract allergies data
     let allergies: Vec<String> = extract_allergies(params).await;
 
+    // Extract chronic conditions
+    let chronic_conditions: Vec<String> = extract_chronic_conditions(params).await;
+
+    // Extract current medications
+    let medications: Vec<Medication> = extract_medications(params, &resolver).await;
+
+    // Extract vital signs
+    let vital_signs: Vec<VitalSign> = extract_vital_signs(params).await;
+
+    // Build the timeline
+    let timeline: Vec<TimelineEvent> = extract_timeline(params).await;
+
+    // Extract treatments and upcoming appointments
+    let treatments: Vec<Treatment> = extract_treatments(params, &resolver).await;
+    let appointments: Vec<Appointment> = extract_appointments(params, &resolver).await;
+
     // Selectively update specific fields
     record.set_fields(|r| {
         r.id = params.patient_id.clone();
@@ -62,176 +101,24 @@ pub async fn main_console_page(
         r.ethnicity = patient_details.ethnicity.clone();
         r.address = patient_details.address.clone();
         r.phone = patient_details.phone.clone();
-        r.email = "somemail@example.com".to_string();
-        r.emergency_contact = "John Smith (Husband) - (555) 987-6543".to_string();
+        r.email = patient_details.email.clone();
+        r.emergency_contact = patient_details.emergency_contact.clone();
+        r.logged_in_user = logged_in_user.clone();
         
         // r.allergies = vec!["Penicillin".to_string()];
         r.allergies = allergies.clone();
+
+        r.chronic_conditions = chronic_conditions.clone();
         
-        r.current_medications.push(Medication {
-            name: "Aspirin".to_string(),
-            dosage: "100mg".to_string(),
-            frequency: "Daily".to_string(),
-        });
-
-        r.current_medications.push(Medication {
-            name: "Metformin".to_string(),
-            dosage: "500mg".to_string(),
-            frequency: "Twice daily".to_string(),
-        });
-
-        r.vital_signs.push(VitalSign {
-            date: "2023-06-01".to_string(),
-            heart_rate: 74,
-            blood_pressure: "123/81".to_string(),
-            temperature: 98.8,
-            respiratory_rate: 16,
-            oxygen_saturation: 97,
-        });
-
-        r.vital_signs.push(VitalSign {
-            date: "2023-05-01".to_string(),
-            heart_rate: 71,
-            blood_pressure: "119/80".to_string(),
-            temperature: 98.6,
-            respiratory_rate: 15,
-            oxygen_saturation: 98,
-        });
-
-        r.vital_signs.push(VitalSign {
-            date: "2023-04-01".to_string(),
-            heart_rate: 73,
-            blood_pressure: "121/79".to_string(),
-            temperature: 98.5,
-            respiratory_rate: 16,
-            oxygen_saturation: 98,
-        });
-
-        r.vital_signs.push(VitalSign {
-            date: "2023-03-01".to_string(),
-            heart_rate: 70,
-            blood_pressure: "118/78".to_string(),
-            temperature: 98.7,
-            respiratory_rate: 15,
-            oxygen_saturation: 99,
-        });
-
-        r.vital_signs.push(VitalSign {
-            date: "2023-02-01".to_string(),
-            heart_rate: 75,
-            blood_pressure: "122/82".to_string(),
-            temperature: 98.4,
-            respiratory_rate: 16,
-            oxygen_saturation: 97,
-        });
-
-        r.vital_signs.push(VitalSign {
-            date: "2023-01-01".to_string(),
-            heart_rate: 72,
-            blood_pressure: "120/80".to_string(),
-            temperature: 98.6,
-            respiratory_rate: 16,
-            oxygen_saturation: 98,
-        });
-
-        r.treatments.push(Treatment {
-            date: "2023-01-15".to_string(),
-            t_type: "Medication Adjustment".to_string(),
-            provider: "Medication Adjustment".to_string(),
-            notes: "Increased Lisinopril to 10mg".to_string(),
-        });
-
-        r.treatments.push(Treatment {
-            date: "2023-02-20".to_string(),
-            t_type: "Physical Therapy".to_string(),
-            provider: "Dr. Michael Rodriguez".to_string(),
-            notes: "Started PT for lower back pain".to_string(),
-        });
-
-        r.treatments.push(Treatment {
-            date: "2023-03-10".to_string(),
-            t_type: "Lab Work".to_string(),
-            provider: "Dr. Emily Chen".to_string(),
-            notes: "Comprehensive metabolic panel and A1C".to_string(),
-        });
-
-        r.treatments.push(Treatment {
-            date: "2023-04-05".to_string(),
-            t_type: "Specialist Consultation".to_string(),
-            provider: "Dr. Sarah Johnson".to_string(),
-            notes: "Endocrinology consult for diabetes management".to_string(),
-        });
-
-        r.treatments.push(Treatment {
-            date: "2023-05-18".to_string(),
-            t_type: "Medication Adjustment".to_string(),
-            provider: "Dr. Emily Chen".to_string(),
-            notes: "Added low-dose aspirin".to_string(),
-        });
-
-        r.appointments.push(Appointment {
-            date: "2023-07-15".to_string(),
-            time: "10:00 AM".to_string(),
-            provider: "Dr. Emily Chen".to_string(),
-            a_type: "Follow-up".to_string(),
-            location: "Main Hospital, Room 302".to_string(),
-        });
-
-        r.appointments.push(Appointment {
-            date: "2023-08-05".to_string(),
-            time: "2:30 PM".to_string(),
-            provider: "Dr. Michael Rodriguez".to_string(),
-            a_type: "Physical Therapy".to_string(),
-            location: "Rehabilitation Center".to_string(),
-        });
-
-        r.appointments.push(Appointment {
-            date: "2023-09-10".to_string(),
-            time: "9:15 AM".to_string(),
-            provider: "Dr. Sarah Johnson".to_string(),
-            a_type: "Endocrinology".to_string(),
-            location: "Specialty Clinic, Suite 105".to_string(),
-        });
-
-        r.timeline.push(TimelineEvent {
-            year: "2023".to_string(),
-            title: "Cardiac Assessment".to_string(),
-            description: "Comprehensive cardiac evaluation revealed normal heart function with minor irregularities in rhythm. Prescribed beta blockers for management.".to_string(),
-            icon: "HeartPulse".to_string(),
-            highlight: true,
-        });
-
-        r.timeline.push(TimelineEvent {
-            year: "2022".to_string(),
-            title: "Type 2 Diabetes Diagnosis".to_string(),
-            description: "Initial diagnosis of Type 2 Diabetes. Started on Metformin 500mg twice daily. Implemented lifestyle modifications and dietary changes.".to_string(),
-            icon: "Activity".to_string(),
-            highlight: false,
-        });
-
-        r.timeline.push(TimelineEvent {
-            year: "2021".to_string(),
-            title: "Annual Physical".to_string(),
-            description: "Regular check-up showed elevated blood pressure (140/90). Recommended lifestyle changes and monthly monitoring.".to_string(),
-            icon: "Stethoscope".to_string(),
-            highlight: false,
-        });
-
-        r.timeline.push(TimelineEvent {
-            year: "2019".to_string(),
-            title: "Medication Review".to_string(),
-            description: "Comprehensive medication review. Adjusted dosages for better management of chronic conditions.".to_string(),
-            icon: "Pill".to_string(),
-            highlight: false,
-        });
-        
-        r.timeline.push(TimelineEvent {
-            year: "2015".to_string(),
-            title: "New Hospital Admission".to_string(),
-            description: "Brief hospital stay due to severe pneumonia.".to_string(),
-            icon: "Hospital".to_string(),
-            highlight: false,
-        });
+        r.current_medications = medications.clone();
+
+        r.vital_signs = vital_signs.clone();
+
+        r.treatments = treatments.clone();
+
+        r.appointments = appointments.clone();
+
+        r.timeline = timeline.clone();
 
     });
 
@@ -311,10 +198,24 @@ fn extract_patient_details(patient: &Patient) -> PatientDetails {
         })
         .unwrap_or_else(|| "n/a".to_string());
 
-    // Extract phone
-    let phone = patient.telecom.as_ref()
-        .and_then(|telecoms| telecoms.first())
-        .and_then(|telecom| telecom.value.clone())
+    // Extract phone and email
+    let phone = patient.phone().unwrap_or("n/a").to_string();
+    let email = patient.email().unwrap_or("n/a").to_string();
+
+    // Extract emergency contact, e.g. "John Smith (Husband) - (555) 987-6543"
+    let emergency_contact = patient.emergency_contact()
+        .map(|contact| {
+            let mut text = contact.name.as_ref()
+                .and_then(|name| name.display())
+                .unwrap_or_else(|| "n/a".to_string());
+            if let Some(relationship) = contact.relationship_display() {
+                text.push_str(&format!(" ({})", relationship));
+            }
+            if let Some(phone) = contact.phone() {
+                text.push_str(&format!(" - {}", phone));
+            }
+            text
+        })
         .unwrap_or_else(|| "n/a".to_string());
 
     // Extract ethnicity
@@ -337,6 +238,8 @@ fn extract_patient_details(patient: &Patient) -> PatientDetails {
         ethnicity,
         gender,
         phone,
-        age: age,
+        email,
+        emergency_contact,
+        age,
     }
 }
\ No newline at end of file
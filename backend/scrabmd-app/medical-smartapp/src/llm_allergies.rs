@@ -1,5 +1,6 @@
-use crate::gemini::chat::ChatGemini;
-use crate::oidc_request::get_mdata;
+use scrab_gemini::chat::ChatGemini;
+use scrab_cds::{Bundle, BundleEntry, Resource};
+use scrab_fhir::{FhirClient, SearchParams};
 use crate::libs::MainPageParams;
 use serde::{Deserialize, Serialize};
 use lambda_runtime::tracing::error;
@@ -21,35 +22,47 @@ pub struct AllergyItem {
 pub async fn extract_allergies(
     params: &MainPageParams,
 ) -> Vec<String> {
-    let allergies = match extract_allergies_handle(params).await {
+    match extract_allergies_handle(params).await {
         Ok(allergies) => allergies,
         Err(e) => {
             error!("Error extracting allergies: {:?}", e);
             vec![]
         }
-    };
+    }
+}
 
-    allergies
+/// Searchset Bundle of the matches of a search, skipping entries that are not resources
+fn searchset(matches: Vec<Value>) -> Bundle {
+    let entry: Vec<BundleEntry> = matches.into_iter()
+        .filter_map(|resource| serde_json::from_value::<Resource>(resource).ok())
+        .map(|resource| BundleEntry { resource: Some(resource), ..Default::default() })
+        .collect();
+    Bundle {
+        bundle_type: "searchset".to_string(),
+        total: Some(entry.len() as u32),
+        entry,
+        ..Default::default()
+    }
 }
 
 pub async fn extract_allergies_handle(
     params: &MainPageParams,
 ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
 
-    let query = format!("AllergyIntolerance?patient={}", params.patient_id);
+    let client = FhirClient::new(&params.iss, &params.access_token)?;
+    let search = SearchParams::new("AllergyIntolerance").with_patient(&params.patient_id);
 
-    let allergies = match get_mdata(
-        &params.iss,
-        &query,
-        &params.access_token,
-    ).await {
-        Ok(data) => data,
+    let result = match client.search(&search).await {
+        Ok(result) => result,
         Err(e) => {
             error!("Error getting AllergyIntolerance data: {:?}", e);
             return Err(Box::new(e));
         }
     };
 
+    // All the pages of the search, as a single Bundle
+    let allergies = searchset(result.matches);
+
     let llm = ChatGemini::new("gemini-2.0-flash");
 
     let get_allergy_intolerance = json!({
@@ -105,7 +118,7 @@ pub async fn extract_allergies_handle(
         \n \
         Here is the FHIR Bundle: \
         \n \
-        {}", allergies);
+        {}", serde_json::to_string(&allergies)?);
 
     let response = llm
         .with_tools(function_dec)
@@ -133,4 +146,24 @@ pub async fn extract_allergies_handle(
         allergies_array.push(alle.code_display);
     }
     Ok(allergies_array)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_a_searchset_of_the_matches() {
+        let bundle = searchset(vec![
+            json!({ "resourceType": "AllergyIntolerance", "id": "a1", "code": { "text": "Penicillin" } }),
+            json!({ "id": "no-type" }),
+        ]);
+        assert_eq!(bundle.bundle_type, "searchset");
+        assert_eq!(bundle.total, Some(1));
+        assert_eq!(bundle.resources_of("AllergyIntolerance").next().unwrap().reference().as_deref(), Some("AllergyIntolerance/a1"));
+
+        let json = serde_json::to_value(&bundle).unwrap();
+        assert_eq!(json["resourceType"], "Bundle");
+        assert_eq!(json["entry"][0]["resource"]["code"]["text"], "Penicillin");
+    }
+}
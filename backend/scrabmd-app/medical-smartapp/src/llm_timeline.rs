@@ -0,0 +1,125 @@
+use std::env;
+use scrab_gemini::chat::ChatGemini;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use crate::libs::TimelineEvent;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventDescriptions {
+    pub descriptions: Vec<EventDescription>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EventDescription {
+    pub index: usize,
+    pub description: String,
+}
+
+/// When `TIMELINE_AI_DESCRIPTIONS` is enabled each timeline event gets a
+/// one-line summary from the LLM, labeled as AI-generated on the page
+pub fn ai_descriptions_enabled() -> bool {
+    env::var("TIMELINE_AI_DESCRIPTIONS")
+        .is_ok_and(|value| matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+}
+
+pub async fn describe_events(
+    events: &mut [TimelineEvent],
+) -> Result<(), Box<dyn std::error::Error>> {
+    if events.is_empty() {
+        return Ok(());
+    }
+
+    let llm = ChatGemini::new("gemini-2.0-flash");
+
+    let describe_timeline_events = json!({
+    "name":"describe_timeline_events",
+    "description":"Function to return a one-line description for each timeline event.",
+    "parameters":{
+        "type":"OBJECT",
+        "properties":{
+            "descriptions":{
+                "type":"ARRAY",
+                "description":"One description per event",
+                "items":{
+                    "type":"OBJECT",
+                    "properties":{
+                        "index":{
+                            "type":"INTEGER",
+                            "description":"Index of the event in the list"
+                        },
+                        "description":{
+                            "type":"STRING",
+                            "description":"One-line plain-language description of the event"
+                        }
+                    },
+                    "required":[
+                        "index",
+                        "description"
+                    ]
+                }
+            }
+        }
+    }
+    });
+
+    let function_dec = vec![json!({
+        "functionDeclarations":[
+            describe_timeline_events
+        ]
+    })];
+
+    let tool_config = json!({
+        "function_calling_config":{
+            "mode":"ANY",
+            "allowed_function_names":[
+                "describe_timeline_events"
+            ]
+        }
+    });
+
+    let listed: Vec<Value> = events.iter().enumerate()
+        .map(|(index, event)| json!({
+            "index": index,
+            "year": event.year,
+            "title": event.title,
+            "details": event.description,
+        }))
+        .collect();
+
+    let prompt = format!(
+        "You will be given the events of a patient timeline built from FHIR Encounter, \
+        Condition and Procedure resources. For each event write one short sentence, \
+        in plain language for a clinician, using only the facts given. \
+        \n \
+        Here are the events: \
+        \n \
+        {}", Value::Array(listed));
+
+    let response = llm
+        .with_tools(function_dec)
+        .with_tool_config(tool_config)
+        .invoke(&prompt)
+        .await?;
+
+    let mut function_args = Value::Null;
+
+    if let Some(candidates) = &response.candidates {
+        for candidate in candidates {
+            if let Some(content) = &candidate.content {
+                for part in &content.parts {
+                    if let Some(function_call) = &part.function_call {
+                        function_args = function_call.args.clone();
+                    }
+                }
+            }
+        }
+    };
+
+    let described: EventDescriptions = serde_json::from_value(function_args)?;
+    for item in described.descriptions {
+        if let Some(event) = events.get_mut(item.index) {
+            event.ai_description = Some(item.description);
+        }
+    }
+    Ok(())
+}
@@ -1,13 +1,24 @@
 use lambda_runtime::{run, service_fn, tracing, Error};
 mod http_handler;
 mod libs;
-mod gemini;
 mod llm_allergies;
+mod llm_timeline;
+mod medications;
+mod conditions;
+mod vital_signs;
+mod timeline;
+mod appointments;
+mod treatments;
+mod references;
 mod intro_console;
 mod http_page;
 mod scrab_errors;
 mod oidc_request;
 mod oidc_database;
+mod id_token;
+mod standalone;
+mod smart_config;
+mod token_manager;
 use http_handler::function_handler;
 
 #[tokio::main]
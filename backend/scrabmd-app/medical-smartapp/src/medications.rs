@@ -0,0 +1,232 @@
+use lambda_runtime::tracing::error;
+use scrab_fhir::{
+    Dosage, MedicationChoice, MedicationRequest, MedicationStatement, ReferenceResolver, SearchParams,
+    SearchResult,
+};
+use serde_json::Value;
+use crate::libs::{MainPageParams, Medication};
+use crate::references::reference_name;
+use crate::scrab_errors::ScrabError;
+
+/// Name shown for a medication whose concept or reference has no name
+pub const UNKNOWN_MEDICATION: &str = "Unknown medication";
+
+pub async fn extract_medications(
+    params: &MainPageParams,
+    resolver: &ReferenceResolver,
+) -> Vec<Medication> {
+    match extract_medications_handle(params, resolver).await {
+        Ok(medications) => medications,
+        Err(e) => {
+            error!("Error extracting medications: {:?}", e);
+            vec![]
+        }
+    }
+}
+
+/// Current medications from the active MedicationRequests and MedicationStatements of the patient
+pub async fn extract_medications_handle(
+    params: &MainPageParams,
+    resolver: &ReferenceResolver,
+) -> Result<Vec<Medication>, ScrabError> {
+    let client = resolver.client();
+
+    let requests = client.search(&SearchParams::of::<MedicationRequest>()
+        .with_patient(&params.patient_id)
+        .with("status", "active")
+        .with_include("MedicationRequest:medication")
+    ).await?;
+    let statements = client.search(&SearchParams::of::<MedicationStatement>()
+        .with_patient(&params.patient_id)
+        .with("status", "active")
+        .with_include("MedicationStatement:medication")
+    ).await?;
+
+    resolver.add_search(&requests);
+    resolver.add_search(&statements);
+
+    Ok(current_medications(&requests, &statements, resolver).await)
+}
+
+/// Active medications of the searched requests and statements, requests first
+pub async fn current_medications(
+    requests: &SearchResult,
+    statements: &SearchResult,
+    resolver: &ReferenceResolver,
+) -> Vec<Medication> {
+    let mut medications: Vec<Medication> = vec![];
+
+    for request in requests.resources::<MedicationRequest>() {
+        if request.status != "active" {
+            continue;
+        }
+        let name = medication_name(resolver, request.medication.as_ref(), &request.contained).await
+            .unwrap_or_else(|| UNKNOWN_MEDICATION.to_string());
+        push_unique(&mut medications, to_medication(name, request.dosage_instruction.first()));
+    }
+
+    for statement in statements.resources::<MedicationStatement>() {
+        if statement.status != "active" {
+            continue;
+        }
+        let name = medication_name(resolver, statement.medication.as_ref(), &statement.contained).await
+            .unwrap_or_else(|| UNKNOWN_MEDICATION.to_string());
+        push_unique(&mut medications, to_medication(name, statement.dosage.first()));
+    }
+
+    medications
+}
+
+/// Name of the medication from its concept, or the Medication it references
+pub async fn medication_name(
+    resolver: &ReferenceResolver,
+    medication: Option<&MedicationChoice>,
+    contained: &[Value],
+) -> Option<String> {
+    match medication? {
+        MedicationChoice::CodeableConcept(concept) => concept.display().map(str::to_string),
+        MedicationChoice::Reference(reference) => reference_name(resolver, reference, contained).await,
+    }
+}
+
+/// Dosage and frequency of a medication, rendered from the structured dosage or its text
+pub fn to_medication(name: String, dosage: Option<&Dosage>) -> Medication {
+    let Some(dosage) = dosage else {
+        return Medication { name, dosage: "n/a".to_string(), frequency: "n/a".to_string() };
+    };
+
+    let route = dosage.route.as_ref().and_then(|route| route.display());
+    let dose = match (dosage.dose(), route) {
+        (Some(dose), Some(route)) => Some(format!("{} ({})", dose, route.to_lowercase())),
+        (dose, _) => dose,
+    };
+
+    Medication {
+        name,
+        dosage: dose.or_else(|| dosage.text.clone()).unwrap_or_else(|| "n/a".to_string()),
+        frequency: dosage.frequency().unwrap_or_else(|| "n/a".to_string()),
+    }
+}
+
+/// Keeps the first entry for a medication listed by both a request and a
+/// statement. Unknown medications are all kept, they may be different drugs.
+fn push_unique(medications: &mut Vec<Medication>, medication: Medication) {
+    let known = medication.name != UNKNOWN_MEDICATION;
+    if !known || !medications.iter().any(|m| m.name.eq_ignore_ascii_case(&medication.name)) {
+        medications.push(medication);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scrab_fhir::FhirClient;
+    use serde_json::json;
+
+    // Nothing listens on the discard port, so unincluded references stay unresolved
+    fn resolver() -> ReferenceResolver {
+        ReferenceResolver::new(FhirClient::new("http://127.0.0.1:9/r4", "token").unwrap())
+    }
+
+    fn search(resolver: &ReferenceResolver, resources: Vec<Value>) -> SearchResult {
+        let entries: Vec<Value> = resources.into_iter()
+            .map(|resource| {
+                let mode = if resource["resourceType"] == "Medication" { "include" } else { "match" };
+                json!({ "search": { "mode": mode }, "resource": resource })
+            })
+            .collect();
+        let mut result = SearchResult::default();
+        result.add_page(&json!({ "resourceType": "Bundle", "type": "searchset", "entry": entries }));
+        resolver.add_search(&result);
+        result
+    }
+
+    fn dosage(text: &str) -> Value {
+        json!([{ "text": text, "doseAndRate": [{ "doseQuantity": { "value": 10, "unit": "mg" } }] }])
+    }
+
+    fn medication(name: &str) -> Medication {
+        Medication { name: name.to_string(), dosage: "n/a".to_string(), frequency: "n/a".to_string() }
+    }
+
+    #[tokio::test]
+    async fn lists_active_requests_and_statements() {
+        let resolver = resolver();
+        let requests = search(&resolver, vec![
+            json!({ "resourceType": "Medication", "id": "med-1", "code": { "text": "Metformin 500 MG" } }),
+            json!({
+                "resourceType": "MedicationRequest",
+                "status": "active",
+                "intent": "order",
+                "medicationReference": { "reference": "Medication/med-1" },
+                "dosageInstruction": dosage("Twice a day")
+            }),
+            json!({
+                "resourceType": "MedicationRequest",
+                "status": "stopped",
+                "intent": "order",
+                "medicationCodeableConcept": { "text": "Warfarin 5 MG" }
+            }),
+        ]);
+        let statements = search(&resolver, vec![
+            json!({
+                "resourceType": "MedicationStatement",
+                "status": "active",
+                "subject": { "reference": "Patient/1288992" },
+                "medicationCodeableConcept": { "text": "Lisinopril 10 MG" },
+                "dosage": dosage("Once a day")
+            }),
+            json!({
+                "resourceType": "MedicationStatement",
+                "status": "completed",
+                "subject": { "reference": "Patient/1288992" },
+                "medicationCodeableConcept": { "text": "Amoxicillin 500 MG" }
+            }),
+        ]);
+
+        let medications = current_medications(&requests, &statements, &resolver).await;
+        let names: Vec<&str> = medications.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["Metformin 500 MG", "Lisinopril 10 MG"]);
+        assert_eq!(medications[0].dosage, "10 mg");
+        assert_eq!(resolver.reads(), 0);
+    }
+
+    #[tokio::test]
+    async fn shows_unnamed_medications_with_their_dosage() {
+        let resolver = resolver();
+        let requests = search(&resolver, vec![
+            json!({
+                "resourceType": "MedicationRequest",
+                "status": "active",
+                "intent": "order",
+                "medicationReference": { "reference": "Medication/missing" },
+                "dosageInstruction": dosage("Twice a day")
+            }),
+            json!({
+                "resourceType": "MedicationRequest",
+                "status": "active",
+                "intent": "order",
+                "medicationCodeableConcept": { "coding": [{ "system": "http://www.nlm.nih.gov/research/umls/rxnorm", "code": "197361" }] }
+            }),
+        ]);
+
+        let medications = current_medications(&requests, &SearchResult::default(), &resolver).await;
+        assert_eq!(medications.len(), 2);
+        assert!(medications.iter().all(|m| m.name == UNKNOWN_MEDICATION));
+        assert_eq!(medications[0].dosage, "10 mg");
+    }
+
+    #[test]
+    fn keeps_the_first_entry_of_a_medication() {
+        let mut medications = vec![];
+        push_unique(&mut medications, Medication { dosage: "10 mg".to_string(), ..medication("Lisinopril 10 MG") });
+        push_unique(&mut medications, medication("lisinopril 10 mg"));
+        push_unique(&mut medications, medication("Metformin 500 MG"));
+        push_unique(&mut medications, medication(UNKNOWN_MEDICATION));
+        push_unique(&mut medications, medication(UNKNOWN_MEDICATION));
+
+        let names: Vec<&str> = medications.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["Lisinopril 10 MG", "Metformin 500 MG", UNKNOWN_MEDICATION, UNKNOWN_MEDICATION]);
+        assert_eq!(medications[0].dosage, "10 mg");
+    }
+}
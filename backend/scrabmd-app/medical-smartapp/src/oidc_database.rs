@@ -9,6 +9,9 @@ pub struct SessionData {
     pub pk: String,
     pub access_token: Option<String>,
     pub expires_in: Option<i32>,
+    pub refresh_token: Option<String>,
+    /// Epoch milliseconds at which the access token expires
+    pub token_expires_at: Option<i64>,
     pub scope: Option<String>,
     pub token_type: Option<String>,
     pub id_token: Option<String>,
@@ -22,6 +25,11 @@ pub struct SessionData {
     pub iss: Option<String>,
     pub session_timeout: Option<i64>,
     pub patient: Option<String>,
+    pub nonce: Option<String>,
+    pub jwks_uri: Option<String>,
+    pub oidc_issuer: Option<String>,
+    /// `fhirUser` claim of the validated id_token, e.g. `Practitioner/123`
+    pub fhir_user: Option<String>,
 }
 
 impl SessionData {
@@ -39,6 +47,12 @@ impl SessionData {
         if let Some(exp) = self.expires_in {
             item.insert("expires_in".to_string(), AttributeValue::N(exp.to_string()));
         }
+        if let Some(refresh_token) = &self.refresh_token {
+            item.insert("refresh_token".to_string(), AttributeValue::S(refresh_token.clone()));
+        }
+        if let Some(token_expires_at) = self.token_expires_at {
+            item.insert("token_expires_at".to_string(), AttributeValue::N(token_expires_at.to_string()));
+        }
         if let Some(scope) = &self.scope {
             item.insert("scope".to_string(), AttributeValue::S(scope.clone()));
         }
@@ -78,8 +92,56 @@ impl SessionData {
         if let Some(patient) = &self.patient {
             item.insert("patient".to_string(), AttributeValue::S(patient.clone()));
         }
+        if let Some(nonce) = &self.nonce {
+            item.insert("nonce".to_string(), AttributeValue::S(nonce.clone()));
+        }
+        if let Some(jwks_uri) = &self.jwks_uri {
+            item.insert("jwks_uri".to_string(), AttributeValue::S(jwks_uri.clone()));
+        }
+        if let Some(oidc_issuer) = &self.oidc_issuer {
+            item.insert("oidc_issuer".to_string(), AttributeValue::S(oidc_issuer.clone()));
+        }
+        if let Some(fhir_user) = &self.fhir_user {
+            item.insert("fhir_user".to_string(), AttributeValue::S(fhir_user.clone()));
+        }
         item
     }
+
+    /// Update expression and values writing the token attributes of a renewal,
+    /// removing the ones the token response left out
+    pub(crate) fn token_update(&self) -> (String, HashMap<String, AttributeValue>) {
+        let attributes = [
+            ("access_token", self.access_token.clone().map(AttributeValue::S)),
+            ("refresh_token", self.refresh_token.clone().map(AttributeValue::S)),
+            ("expires_in", self.expires_in.map(|n| AttributeValue::N(n.to_string()))),
+            ("token_expires_at", self.token_expires_at.map(|n| AttributeValue::N(n.to_string()))),
+            ("token_type", self.token_type.clone().map(AttributeValue::S)),
+            ("id_token", self.id_token.clone().map(AttributeValue::S)),
+            ("session_timeout", self.session_timeout.map(|n| AttributeValue::N(n.to_string()))),
+        ];
+
+        let mut set = vec![];
+        let mut remove = vec![];
+        let mut values = HashMap::new();
+        for (name, value) in attributes {
+            match value {
+                Some(value) => {
+                    set.push(format!("{} = :{}", name, name));
+                    values.insert(format!(":{}", name), value);
+                }
+                None => remove.push(name),
+            }
+        }
+
+        let mut clauses = vec![];
+        if !set.is_empty() {
+            clauses.push(format!("SET {}", set.join(", ")));
+        }
+        if !remove.is_empty() {
+            clauses.push(format!("REMOVE {}", remove.join(", ")));
+        }
+        (clauses.join(" "), values)
+    }
 }
 
 #[allow(dead_code)]
@@ -110,6 +172,8 @@ pub async fn get_session_data(
             pk: pk.to_string(),
             access_token: item.get("access_token").and_then(|av| av.as_s().ok().map(|s| s.to_string())),
             expires_in: item.get("expires_in").and_then(|av| av.as_n().ok().and_then(|n| n.parse::<i32>().ok())),
+            refresh_token: item.get("refresh_token").and_then(|av| av.as_s().ok().map(|s| s.to_string())),
+            token_expires_at: item.get("token_expires_at").and_then(|av| av.as_n().ok().and_then(|n| n.parse::<i64>().ok())),
             scope: item.get("scope").and_then(|av| av.as_s().ok().map(|s| s.to_string())),
             token_type: item.get("token_type").and_then(|av| av.as_s().ok().map(|s| s.to_string())),
             id_token: item.get("id_token").and_then(|av| av.as_s().ok().map(|s| s.to_string())),
@@ -123,6 +187,10 @@ pub async fn get_session_data(
             iss: item.get("iss").and_then(|av| av.as_s().ok().map(|s| s.to_string())),
             session_timeout: item.get("session_timeout").and_then(|av| av.as_n().ok().and_then(|n| n.parse::<i64>().ok())),
             patient: item.get("patient").and_then(|av| av.as_s().ok().map(|s| s.to_string())),
+            nonce: item.get("nonce").and_then(|av| av.as_s().ok().map(|s| s.to_string())),
+            jwks_uri: item.get("jwks_uri").and_then(|av| av.as_s().ok().map(|s| s.to_string())),
+            oidc_issuer: item.get("oidc_issuer").and_then(|av| av.as_s().ok().map(|s| s.to_string())),
+            fhir_user: item.get("fhir_user").and_then(|av| av.as_s().ok().map(|s| s.to_string())),
         };
         Ok(Some(session_data))
     } else {
@@ -149,4 +217,41 @@ pub async fn save_to_dynamo(
         .await?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Store a renewed token only if the session still holds `previous_refresh_token`,
+/// so two requests refreshing at once cannot overwrite each other's rotation.
+/// Returns `false` when another request already stored a newer token.
+pub async fn update_session_token(
+    session_data: &SessionData,
+    previous_refresh_token: &str,
+    table_name: &str,
+) -> Result<bool, dynamodb::Error> {
+    let config = aws_config::load_from_env().await;
+    let client = dynamodb::Client::new(&config);
+
+    let (update_expression, values) = session_data.token_update();
+
+    let result = client
+        .update_item()
+        .table_name(table_name)
+        .key("pk", AttributeValue::S(session_data.pk.clone()))
+        .update_expression(update_expression)
+        .condition_expression("refresh_token = :previous_refresh_token")
+        .set_expression_attribute_values(Some(values))
+        .expression_attribute_values(":previous_refresh_token", AttributeValue::S(previous_refresh_token.to_string()))
+        .send()
+        .await;
+
+    match result {
+        Ok(_) => Ok(true),
+        Err(e) => {
+            let e = e.into_service_error();
+            if e.is_conditional_check_failed_exception() {
+                Ok(false)
+            } else {
+                Err(e.into())
+            }
+        }
+    }
+}
@@ -1,10 +1,9 @@
-use reqwest::blocking::Client;
 use reqwest::{self, header::{HeaderMap, HeaderValue}};
 use reqwest::Method;
-use serde_json::Value;
 use lambda_runtime::tracing::error;
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
+use scrab_fhir::FhirClient;
 use crate::scrab_errors::ScrabError;
 
 #[allow(dead_code)]
@@ -12,48 +11,13 @@ use crate::scrab_errors::ScrabError;
 pub struct TokenResponse {
     pub access_token: String,
     pub expires_in: Option<i32>,
+    pub refresh_token: Option<String>,
     pub token_type: Option<String>,
     pub scope: Option<String>,
     pub id_token: Option<String>,
     pub patient: Option<String>,
 }
 
-pub async fn discover_endpoints(
-    iss: &str
-) -> Result<(String, String), ScrabError> {
-    let client = Client::new();
-    
-    // First try SMART configuration
-    let smart_config_url = format!("{}/.well-known/smart-configuration", iss.trim_end_matches('/'));
-    let response = client.get(&smart_config_url).send()?;
-    
-    if response.status().is_success() {
-        let config: Value = response.json()?;
-        let auth_endpoint = config["authorization_endpoint"].as_str()
-            .ok_or(ScrabError::MissingAuthEndpoint)?;
-        let token_endpoint = config["token_endpoint"].as_str()
-            .ok_or(ScrabError::MissingTokenEndpoint)?;
-        
-        return Ok((auth_endpoint.to_string(), token_endpoint.to_string()));
-    }
-    
-    // Fallback to OAuth configuration
-    let oauth_config_url = format!("{}/.well-known/oauth-authorization-server", iss.trim_end_matches('/'));
-    let response = client.get(&oauth_config_url).send()?;
-    
-    if response.status().is_success() {
-        let config: Value = response.json()?;
-        let auth_endpoint = config["authorization_endpoint"].as_str()
-            .ok_or(ScrabError::MissingAuthEndpoint)?;
-        let token_endpoint = config["token_endpoint"].as_str()
-            .ok_or(ScrabError::MissingTokenEndpoint)?;
-        
-        return Ok((auth_endpoint.to_string(), token_endpoint.to_string()));
-    }
-    
-    Err(ScrabError::GenericError("Could not discover authorization endpoints".into()))
-}
-
 pub async fn get_token_accesss(
     client_id: &str,
     token_endpoint: &str,
@@ -110,47 +74,57 @@ pub async fn get_token_accesss(
     Ok(token_response)
 }
 
-pub async fn get_mdata(
-    iss: &str,
-    query: &str,
-    access_token: &str,
-) -> Result<String, ScrabError> {
-    // Creates an HTTPS-capable client using rustls TLS implementation.
+/// Exchange a refresh token for a new access token with the `refresh_token` grant
+pub async fn refresh_access_token(
+    client_id: &str,
+    token_endpoint: &str,
+    refresh_token: &str,
+) -> Result<TokenResponse, ScrabError> {
     let client = reqwest::Client::builder()
         .use_rustls_tls()
         .build()?;
 
-    let url = format!(
-        "{}/{}", 
-        iss.trim_end_matches('/'),
-        query,
-    );
     let mut headers = HeaderMap::new();
-    let auth_str = format!("Bearer {}", access_token);
-
-    headers.insert("accept", HeaderValue::from_static("application/json"));
-    headers.insert("authorization", HeaderValue::from_str(&auth_str).unwrap());
+    headers.insert("Content-Type", HeaderValue::from_static("application/x-www-form-urlencoded"));
+    headers.insert("Accept", HeaderValue::from_static("application/json"));
 
-    let request = client.request(Method::GET, url)
-        .headers(headers);
+    let mut params = HashMap::new();
+    params.insert("client_id", client_id);
+    params.insert("grant_type", "refresh_token");
+    params.insert("refresh_token", refresh_token);
 
-    let response = match request.send().await {
-        Ok(response) => response,
-        Err(e) => {
-            error!("Error sending token request: {}", e);
-            return Err(ScrabError::RequestError(e.to_string()));
-        }
-    };
+    let response = client.request(Method::POST, token_endpoint)
+        .headers(headers)
+        .form(&params)
+        .send()
+        .await
+        .map_err(|e| {
+            error!("Error sending refresh request: {}", e);
+            ScrabError::RequestError(e.to_string())
+        })?;
 
-    // Check if status code is in the 200-299 range
     let status = response.status();
+    let body = response.text().await
+        .map_err(|e| ScrabError::RequestError(e.to_string()))?;
+
     if !status.is_success() {
-        return Err(ScrabError::RequestError(format!(
-            "Request failed with status: {}", status
-        )));
+        return Err(ScrabError::Authentication(format!("Refresh rejected with {}: {}", status, body)));
     }
-    
-    // Parse the response body as a string
-    let body = response.text().await?;
-    Ok(body)
+
+    Ok(serde_json::from_str(&body)?)
+}
+
+/// GET `{iss}/{query}` from the FHIR server of the launch, returning the raw JSON body
+pub async fn get_mdata(
+    iss: &str,
+    query: &str,
+    access_token: &str,
+) -> Result<String, ScrabError> {
+    let client = FhirClient::new(iss, access_token)?;
+
+    let body = client.get(query).await.map_err(|e| {
+        error!("Error sending FHIR request: {}", e);
+        e
+    })?;
+    Ok(body.to_string())
 }
@@ -0,0 +1,24 @@
+use scrab_fhir::{Location, Medication, Patient, Practitioner, Reference, ReferenceResolver, from_value};
+use serde_json::Value;
+
+/// Display name of a resource that can be the target of a reference
+pub fn resource_name(resource: &Value) -> Option<String> {
+    match resource["resourceType"].as_str()? {
+        "Practitioner" => from_value::<Practitioner>(resource.clone()).ok()?.display(),
+        "Location" => from_value::<Location>(resource.clone()).ok()?.display(),
+        "Medication" => from_value::<Medication>(resource.clone()).ok()?.display(),
+        "Patient" => from_value::<Patient>(resource.clone()).ok()?.name?.first()?.display(),
+        _ => resource["name"].as_str().map(str::to_string),
+    }
+}
+
+/// Name of the target of a reference, falling back to the reference display
+pub async fn reference_name(
+    resolver: &ReferenceResolver,
+    reference: &Reference,
+    contained: &[Value],
+) -> Option<String> {
+    resolver.resolve(reference, contained).await
+        .and_then(|resource| resource_name(&resource))
+        .or_else(|| reference.display.clone())
+}
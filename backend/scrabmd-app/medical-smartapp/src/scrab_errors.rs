@@ -24,6 +24,9 @@ pub enum ScrabError {
 
     #[error("Request error: {0}")]
     RequestError(String),
+
+    #[error("FHIR error: {0}")]
+    Fhir(#[from] scrab_fhir::FhirError),
     
     #[error("IO error: {0}")]
     Io(#[from] io::Error),
@@ -0,0 +1,336 @@
+use lambda_runtime::tracing::{info, warn};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use crate::scrab_errors::ScrabError;
+
+// How long a discovered configuration is reused for an issuer
+const CONFIG_TTL_SECS: i64 = 60 * 60; // 1 hour
+
+// Narrower scopes to fall back on when the server does not offer the requested one
+const SCOPE_FALLBACKS: &[(&str, &[&str])] = &[
+    ("patient/*.*", &["patient/*.read", "patient/*.rs"]),
+];
+
+// Scopes asking for a refresh token, only requested when the server grants refresh tokens
+const REFRESH_SCOPES: &[&str] = &["online_access", "offline_access"];
+
+// Issuers whose discovered configuration is kept, the `iss` of a launch is not trusted
+const MAX_CACHED_CONFIGS: usize = 32;
+
+/// Discovery document a configuration was read from
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum MetadataSource {
+    #[default]
+    SmartConfiguration,
+    /// Legacy fallback: OAuth authorization server metadata, which has no SMART capabilities
+    OAuthAuthorizationServer,
+}
+
+/// `.well-known/smart-configuration` of a FHIR server, or its OAuth
+/// authorization server metadata when it publishes no SMART configuration
+#[derive(Debug, Default, Deserialize, Clone, PartialEq)]
+pub struct SmartConfiguration {
+    #[serde(skip)]
+    pub source: MetadataSource,
+    pub issuer: Option<String>,
+    pub jwks_uri: Option<String>,
+    #[serde(default)]
+    pub authorization_endpoint: String,
+    #[serde(default)]
+    pub token_endpoint: String,
+    #[serde(default)]
+    pub grant_types_supported: Vec<String>,
+    #[serde(default)]
+    pub scopes_supported: Vec<String>,
+    #[serde(default)]
+    pub response_types_supported: Vec<String>,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    #[serde(default)]
+    pub code_challenge_methods_supported: Vec<String>,
+}
+
+impl SmartConfiguration {
+    /// Parse a discovery document, which must name both OAuth endpoints
+    pub fn parse(body: &str) -> Result<Self, ScrabError> {
+        let config: SmartConfiguration = serde_json::from_str(body)?;
+        if config.authorization_endpoint.is_empty() {
+            return Err(ScrabError::MissingAuthEndpoint);
+        }
+        if config.token_endpoint.is_empty() {
+            return Err(ScrabError::MissingTokenEndpoint);
+        }
+        Ok(config)
+    }
+
+    pub fn with_source(mut self, source: MetadataSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub fn has_capability(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+
+    /// Whether `code_challenge_method=S256` is listed; a server that lists no
+    /// methods does not support PKCE
+    pub fn supports_pkce_s256(&self) -> bool {
+        self.code_challenge_methods_supported.iter().any(|m| m == "S256")
+    }
+
+    /// Whether the `refresh_token` grant is listed; a server that lists no
+    /// grant types (SMART App Launch 1.0) is assumed to grant it
+    pub fn supports_refresh_token(&self) -> bool {
+        self.grant_types_supported.is_empty() || self.grant_types_supported.iter().any(|g| g == "refresh_token")
+    }
+
+    fn supports_scope(&self, scope: &str) -> bool {
+        if REFRESH_SCOPES.contains(&scope) && !self.supports_refresh_token() {
+            return false;
+        }
+        self.scopes_supported.is_empty() || self.scopes_supported.iter().any(|s| s == scope)
+    }
+
+    /// Scopes to request from the server: unsupported optional scopes are dropped,
+    /// refresh token scopes too when the server does not grant refresh tokens, and
+    /// `patient/*.*` is narrowed to a read scope. Fails when a required scope is unavailable.
+    pub fn negotiate_scopes(&self, requested: &str, required: &[&str]) -> Result<String, ScrabError> {
+        let mut scopes: Vec<&str> = vec![];
+
+        for scope in requested.split_whitespace() {
+            let fallbacks = SCOPE_FALLBACKS.iter()
+                .find(|(wanted, _)| *wanted == scope)
+                .map(|(_, fallbacks)| *fallbacks)
+                .unwrap_or_default();
+
+            match std::iter::once(scope).chain(fallbacks.iter().copied()).find(|s| self.supports_scope(s)) {
+                Some(supported) => scopes.push(supported),
+                None if required.contains(&scope) => {
+                    return Err(ScrabError::Validation(format!("Server does not support the {} scope", scope)));
+                }
+                None => warn!("Server does not support the {} scope, not requesting it", scope),
+            }
+        }
+        Ok(scopes.join(" "))
+    }
+
+    /// Check that a launch needing `capabilities` can run against the server,
+    /// returning the scopes to request
+    pub fn check_launch(
+        &self,
+        capabilities: &[&str],
+        scope: &str,
+        required_scopes: &[&str],
+    ) -> Result<String, ScrabError> {
+        // Only the legacy OAuth metadata fallback goes without SMART capabilities
+        if self.source == MetadataSource::OAuthAuthorizationServer && self.capabilities.is_empty() {
+            warn!("OAuth metadata of {:?} has no SMART capabilities, assuming {:?}", self.issuer, capabilities);
+        } else if let Some(missing) = capabilities.iter().find(|c| !self.has_capability(c)) {
+            return Err(ScrabError::Validation(format!("Server lacks the {} capability", missing)));
+        }
+
+        if !self.supports_pkce_s256() {
+            return Err(ScrabError::Validation("Server does not support PKCE with S256".to_string()));
+        }
+
+        self.negotiate_scopes(scope, required_scopes)
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ DISCOVERY ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Discovered configurations by issuer, with the time they were fetched
+#[derive(Default)]
+pub struct SmartConfigCache {
+    configs: Mutex<HashMap<String, (SmartConfiguration, i64)>>,
+}
+
+impl SmartConfigCache {
+    pub fn shared() -> &'static SmartConfigCache {
+        static CACHE: OnceLock<SmartConfigCache> = OnceLock::new();
+        CACHE.get_or_init(SmartConfigCache::default)
+    }
+
+    pub fn get(&self, iss: &str, now: i64) -> Result<Option<SmartConfiguration>, ScrabError> {
+        let configs = self.configs.lock()
+            .map_err(|e| ScrabError::GenericError(e.to_string()))?;
+        Ok(configs.get(iss)
+            .filter(|(_, fetched_at)| fetched_at + CONFIG_TTL_SECS > now)
+            .map(|(config, _)| config.clone()))
+    }
+
+    /// Keep a configuration, making room by dropping the expired ones and
+    /// then the oldest once [`MAX_CACHED_CONFIGS`] issuers are cached
+    pub fn insert(&self, iss: &str, config: &SmartConfiguration, now: i64) -> Result<(), ScrabError> {
+        let mut configs = self.configs.lock()
+            .map_err(|e| ScrabError::GenericError(e.to_string()))?;
+
+        if !configs.contains_key(iss) && configs.len() >= MAX_CACHED_CONFIGS {
+            configs.retain(|_, (_, fetched_at)| *fetched_at + CONFIG_TTL_SECS > now);
+            if configs.len() >= MAX_CACHED_CONFIGS
+                && let Some(oldest) = configs.iter().min_by_key(|(_, (_, fetched_at))| *fetched_at).map(|(key, _)| key.clone())
+            {
+                configs.remove(&oldest);
+            }
+        }
+        configs.insert(iss.to_string(), (config.clone(), now));
+        Ok(())
+    }
+}
+
+/// Fetch the SMART configuration of an issuer, falling back to its OAuth metadata
+pub async fn fetch_smart_configuration(iss: &str) -> Result<SmartConfiguration, ScrabError> {
+    let client = reqwest::Client::builder()
+        .use_rustls_tls()
+        .build()?;
+
+    for (well_known, source) in [
+        ("smart-configuration", MetadataSource::SmartConfiguration),
+        ("oauth-authorization-server", MetadataSource::OAuthAuthorizationServer),
+    ] {
+        let url = format!("{}/.well-known/{}", iss.trim_end_matches('/'), well_known);
+        let response = client.get(&url)
+            .header("Accept", "application/json")
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            return Ok(SmartConfiguration::parse(&response.text().await?)?.with_source(source));
+        }
+    }
+
+    Err(ScrabError::GenericError("Could not discover authorization endpoints".into()))
+}
+
+/// SMART configuration of an issuer, fetched once per [`CONFIG_TTL_SECS`]
+pub async fn discover_configuration(iss: &str, now: i64) -> Result<SmartConfiguration, ScrabError> {
+    let iss = iss.trim_end_matches('/');
+    let cache = SmartConfigCache::shared();
+    if let Some(config) = cache.get(iss, now)? {
+        return Ok(config);
+    }
+
+    let config = fetch_smart_configuration(iss).await?;
+    info!("Discovered {:?} of {}", config.source, iss);
+    cache.insert(iss, &config, now)?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SMART_CONFIGURATION: &str = r#"{
+        "issuer": "https://auth.example.org",
+        "jwks_uri": "https://auth.example.org/.well-known/jwks.json",
+        "authorization_endpoint": "https://auth.example.org/authorize",
+        "token_endpoint": "https://auth.example.org/token",
+        "revocation_endpoint": "https://auth.example.org/revoke",
+        "introspection_endpoint": "https://auth.example.org/introspect",
+        "grant_types_supported": ["authorization_code", "refresh_token"],
+        "token_endpoint_auth_methods_supported": ["private_key_jwt"],
+        "scopes_supported": ["openid", "fhirUser", "launch", "launch/patient", "patient/*.read", "offline_access"],
+        "response_types_supported": ["code"],
+        "capabilities": ["launch-ehr", "launch-standalone", "client-public", "context-standalone-patient"],
+        "code_challenge_methods_supported": ["S256"]
+    }"#;
+
+    #[test]
+    fn parses_smart_configuration_and_requires_endpoints() {
+        let config = SmartConfiguration::parse(SMART_CONFIGURATION).unwrap();
+        assert_eq!(config.jwks_uri.as_deref(), Some("https://auth.example.org/.well-known/jwks.json"));
+        assert!(config.has_capability("launch-ehr"));
+        assert!(config.supports_refresh_token());
+        assert!(config.supports_pkce_s256());
+
+        assert!(matches!(
+            SmartConfiguration::parse(r#"{"token_endpoint": "https://auth.example.org/token"}"#),
+            Err(ScrabError::MissingAuthEndpoint)
+        ));
+    }
+
+    #[test]
+    fn checks_capabilities_pkce_and_scopes_of_a_launch() {
+        let config = SmartConfiguration::parse(SMART_CONFIGURATION).unwrap();
+
+        let scope = config.check_launch(&["launch-ehr"], "openid fhirUser cds launch patient/*.*", &["launch", "patient/*.*"]);
+        assert_eq!(scope.unwrap(), "openid fhirUser launch patient/*.read");
+
+        assert!(config.check_launch(&["launch-ehr"], "openid patient/*.*", &["launch", "patient/*.*"]).is_ok());
+        assert!(config.check_launch(&["launch-ehr"], "launch user/*.*", &["user/*.*"]).is_err());
+        assert!(config.check_launch(&["context-ehr-encounter"], "launch", &[]).is_err());
+
+        let plain_pkce = SmartConfiguration { code_challenge_methods_supported: vec!["plain".into()], ..config.clone() };
+        assert!(plain_pkce.check_launch(&["launch-ehr"], "launch", &[]).is_err());
+    }
+
+    #[test]
+    fn asks_for_refresh_tokens_only_when_granted() {
+        let config = SmartConfiguration::parse(SMART_CONFIGURATION).unwrap();
+        assert_eq!(config.negotiate_scopes("launch offline_access", &["launch"]).unwrap(), "launch offline_access");
+
+        let no_refresh = SmartConfiguration { grant_types_supported: vec!["authorization_code".into()], ..config };
+        assert!(!no_refresh.supports_refresh_token());
+        assert_eq!(no_refresh.negotiate_scopes("launch offline_access", &["launch"]).unwrap(), "launch");
+        assert!(no_refresh.negotiate_scopes("launch offline_access", &["offline_access"]).is_err());
+    }
+
+    #[test]
+    fn refuses_smart_configurations_without_capabilities_or_pkce() {
+        let config = SmartConfiguration::parse(SMART_CONFIGURATION).unwrap();
+
+        let no_capabilities = SmartConfiguration { capabilities: vec![], ..config.clone() };
+        assert!(matches!(no_capabilities.check_launch(&["launch-ehr"], "launch", &[]), Err(ScrabError::Validation(_))));
+
+        let no_pkce = SmartConfiguration { code_challenge_methods_supported: vec![], ..config };
+        assert!(!no_pkce.supports_pkce_s256());
+        assert!(matches!(no_pkce.check_launch(&["launch-ehr"], "launch", &[]), Err(ScrabError::Validation(_))));
+    }
+
+    #[test]
+    fn falls_back_on_legacy_oauth_metadata() {
+        // OAuth metadata without capabilities or scopes is taken as is, but must still offer S256
+        let oauth = SmartConfiguration::parse(r#"{
+            "issuer": "https://auth.example.org",
+            "authorization_endpoint": "https://auth.example.org/authorize",
+            "token_endpoint": "https://auth.example.org/token",
+            "code_challenge_methods_supported": ["S256"]
+        }"#).unwrap().with_source(MetadataSource::OAuthAuthorizationServer);
+        assert_eq!(oauth.check_launch(&["launch-ehr"], "launch patient/*.*", &["launch"]).unwrap(), "launch patient/*.*");
+
+        let no_pkce = SmartConfiguration { code_challenge_methods_supported: vec![], ..oauth };
+        assert!(no_pkce.check_launch(&["launch-ehr"], "launch", &[]).is_err());
+    }
+
+    #[test]
+    fn caches_configurations_by_issuer() {
+        let cache = SmartConfigCache::default();
+        let config = SmartConfiguration::parse(SMART_CONFIGURATION).unwrap();
+        cache.insert("https://ehr.example.org/fhir", &config, 1_000).unwrap();
+
+        assert_eq!(cache.get("https://ehr.example.org/fhir", 1_000 + 60).unwrap(), Some(config));
+        assert_eq!(cache.get("https://ehr.example.org/fhir", 1_000 + CONFIG_TTL_SECS).unwrap(), None);
+        assert_eq!(cache.get("https://other.example.org/fhir", 1_000).unwrap(), None);
+    }
+
+    #[test]
+    fn caps_the_cached_issuers() {
+        let cache = SmartConfigCache::default();
+        let config = SmartConfiguration::parse(SMART_CONFIGURATION).unwrap();
+        for i in 0..MAX_CACHED_CONFIGS as i64 {
+            cache.insert(&format!("https://ehr{}.example.org/fhir", i), &config, 1_000 + i).unwrap();
+        }
+
+        // The oldest issuer makes room for a new one
+        cache.insert("https://new.example.org/fhir", &config, 2_000).unwrap();
+        assert_eq!(cache.configs.lock().unwrap().len(), MAX_CACHED_CONFIGS);
+        assert_eq!(cache.get("https://ehr0.example.org/fhir", 2_000).unwrap(), None);
+        assert!(cache.get("https://ehr1.example.org/fhir", 2_000).unwrap().is_some());
+        assert!(cache.get("https://new.example.org/fhir", 2_000).unwrap().is_some());
+
+        // Expired issuers all go at once
+        cache.insert("https://late.example.org/fhir", &config, 1_000 + CONFIG_TTL_SECS + 40).unwrap();
+        assert_eq!(cache.configs.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,106 @@
+use std::env;
+use url::form_urlencoded;
+
+// Scopes of an EHR launch, the EHR provides the patient in context
+pub const EHR_LAUNCH_SCOPE: &str = "meldrx-api cds profile openid fhirUser online_access launch patient/*.*";
+
+// Scopes of a standalone launch, the user picks the patient on the authorization server
+pub const STANDALONE_LAUNCH_SCOPE: &str = "meldrx-api cds profile openid fhirUser online_access launch/patient patient/*.*";
+
+// Scopes the app cannot work without, the others are dropped if the server lacks them.
+// `online_access` is optional: without a refresh token the session ends with its access token.
+pub const EHR_REQUIRED_SCOPES: &[&str] = &["launch", "patient/*.*"];
+pub const STANDALONE_REQUIRED_SCOPES: &[&str] = &["launch/patient", "patient/*.*"];
+
+// SMART capabilities the server must advertise for each launch
+pub const EHR_CAPABILITIES: &[&str] = &["launch-ehr"];
+pub const STANDALONE_CAPABILITIES: &[&str] = &["launch-standalone", "context-standalone-patient"];
+
+/// FHIR server the app can be launched against outside of an EHR
+#[derive(Debug, Clone, PartialEq)]
+pub struct StandaloneServer {
+    pub name: String,
+    pub iss: String,
+    pub client_id: String,
+}
+
+impl StandaloneServer {
+    /// Link that starts a standalone launch against the server
+    pub fn launch_link(&self) -> String {
+        let query: String = form_urlencoded::Serializer::new(String::new())
+            .append_pair("iss", &self.iss)
+            .finish();
+        format!("/launch?{}", query)
+    }
+}
+
+/// Servers of `STANDALONE_SERVERS`, see [`parse_servers`]
+pub fn standalone_servers() -> Vec<StandaloneServer> {
+    env::var("STANDALONE_SERVERS")
+        .map(|value| parse_servers(&value))
+        .unwrap_or_default()
+}
+
+/// Parse `name|iss|client_id` entries separated by `;`, skipping incomplete ones
+pub fn parse_servers(value: &str) -> Vec<StandaloneServer> {
+    value.split(';')
+        .filter_map(|entry| {
+            let mut parts = entry.split('|').map(str::trim);
+            let (name, iss, client_id) = (parts.next()?, parts.next()?, parts.next()?);
+            if name.is_empty() || iss.is_empty() || client_id.is_empty() {
+                return None;
+            }
+            Some(StandaloneServer {
+                name: name.to_string(),
+                iss: iss.trim_end_matches('/').to_string(),
+                client_id: client_id.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Configured server of an issuer, only these can be launched standalone
+pub fn find_server<'a>(servers: &'a [StandaloneServer], iss: &str) -> Option<&'a StandaloneServer> {
+    let iss = iss.trim_end_matches('/');
+    servers.iter().find(|server| server.iss == iss)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smart_config::SmartConfiguration;
+
+    #[test]
+    fn parses_and_finds_configured_servers() {
+        let servers = parse_servers(
+            "MeldRx Sandbox | https://app.meldrx.com/api/fhir/ws-1/ | client-1; broken|https://x.org; \
+             SMART Health IT|https://launch.smarthealthit.org/v/r4/fhir|client-2",
+        );
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers[0].iss, "https://app.meldrx.com/api/fhir/ws-1");
+        assert_eq!(servers[1].client_id, "client-2");
+
+        let server = find_server(&servers, "https://app.meldrx.com/api/fhir/ws-1/").unwrap();
+        assert_eq!(server.name, "MeldRx Sandbox");
+        assert_eq!(server.launch_link(), "/launch?iss=https%3A%2F%2Fapp.meldrx.com%2Fapi%2Ffhir%2Fws-1");
+        assert!(find_server(&servers, "https://unknown.org/fhir").is_none());
+    }
+
+    #[test]
+    fn requests_online_access_only_when_offered() {
+        for (scope, required) in [(EHR_LAUNCH_SCOPE, EHR_REQUIRED_SCOPES), (STANDALONE_LAUNCH_SCOPE, STANDALONE_REQUIRED_SCOPES)] {
+            assert!(scope.split_whitespace().any(|s| s == "online_access"));
+            assert!(!required.contains(&"online_access"));
+
+            let mut config = SmartConfiguration {
+                scopes_supported: scope.split_whitespace().map(str::to_string).collect(),
+                ..Default::default()
+            };
+            assert_eq!(config.negotiate_scopes(scope, required).unwrap(), scope);
+
+            config.scopes_supported.retain(|s| s != "online_access");
+            let negotiated = config.negotiate_scopes(scope, required).unwrap();
+            assert_eq!(negotiated, scope.replace(" online_access", ""));
+        }
+    }
+}
@@ -0,0 +1,373 @@
+use chrono::{Duration, NaiveDate, Utc};
+use lambda_runtime::tracing::error;
+use scrab_fhir::{
+    CodeableConcept, Condition, Encounter, FhirClient, FhirResource, Procedure, SNOMED_SYSTEM,
+    SearchParams,
+};
+use crate::libs::{MainPageParams, TimelineEvent};
+use crate::llm_timeline::{ai_descriptions_enabled, describe_events};
+use crate::scrab_errors::ScrabError;
+
+/// Most recent events kept on the timeline
+const MAX_EVENTS: usize = 30;
+
+const SURGICAL_PROCEDURE: &str = "387713003";
+const DIAGNOSTIC_PROCEDURE: &str = "103693007";
+
+pub async fn extract_timeline(
+    params: &MainPageParams,
+) -> Vec<TimelineEvent> {
+    match extract_timeline_handle(params).await {
+        Ok(timeline) => timeline,
+        Err(e) => {
+            error!("Error extracting timeline: {:?}", e);
+            vec![]
+        }
+    }
+}
+
+/// Timeline of the patient from Encounters, Conditions and Procedures, newest first
+pub async fn extract_timeline_handle(
+    params: &MainPageParams,
+) -> Result<Vec<TimelineEvent>, ScrabError> {
+    let client = FhirClient::new(&params.iss, &params.access_token)?;
+
+    let encounters: Vec<Encounter> = search(&client, &params.patient_id).await;
+    let conditions: Vec<Condition> = search(&client, &params.patient_id).await;
+    let procedures: Vec<Procedure> = search(&client, &params.patient_id).await;
+
+    let mut timeline = build_timeline(&encounters, &conditions, &procedures, Utc::now().date_naive());
+
+    if ai_descriptions_enabled()
+        && let Err(e) = describe_events(&mut timeline).await
+    {
+        error!("Error generating timeline descriptions: {:?}", e);
+    }
+
+    Ok(timeline)
+}
+
+/// Resources of one type for the patient; a failed search leaves the others on the timeline
+async fn search<T: FhirResource>(client: &FhirClient, patient_id: &str) -> Vec<T> {
+    let params = SearchParams::of::<T>()
+        .with_patient(patient_id)
+        .with_count(100);
+    match client.search(&params).await {
+        Ok(result) => result.resources(),
+        Err(e) => {
+            error!("Error searching {}: {:?}", T::RESOURCE_TYPE, e);
+            vec![]
+        }
+    }
+}
+
+pub fn build_timeline(
+    encounters: &[Encounter],
+    conditions: &[Condition],
+    procedures: &[Procedure],
+    today: NaiveDate,
+) -> Vec<TimelineEvent> {
+    let mut events: Vec<(String, TimelineEvent)> = vec![];
+
+    for encounter in encounters {
+        if matches!(encounter.status.as_str(), "cancelled" | "entered-in-error") {
+            continue;
+        }
+        let Some(start) = encounter.period.as_ref().and_then(|p| p.start.clone()) else {
+            continue;
+        };
+        events.push((start.clone(), encounter_event(encounter, &start)));
+    }
+
+    for condition in conditions {
+        if condition.has_verification_status("entered-in-error") || condition.has_verification_status("refuted") {
+            continue;
+        }
+        events.extend(condition_events(condition, today));
+    }
+
+    for procedure in procedures {
+        if matches!(procedure.status.as_str(), "not-done" | "entered-in-error") {
+            continue;
+        }
+        let Some(start) = procedure.performed.as_ref().and_then(|p| p.start()) else {
+            continue;
+        };
+        events.push((start.to_string(), procedure_event(procedure, start)));
+    }
+
+    events.sort_by(|a, b| b.0.cmp(&a.0));
+    events.into_iter()
+        .take(MAX_EVENTS)
+        .map(|(_, event)| event)
+        .collect()
+}
+
+fn event(date: &str, title: String, description: String, icon: &str, highlight: bool) -> TimelineEvent {
+    TimelineEvent {
+        year: date.chars().take(4).collect(),
+        title,
+        description,
+        icon: icon.to_string(),
+        highlight,
+        ai_description: None,
+    }
+}
+
+fn display(concept: Option<&CodeableConcept>) -> Option<String> {
+    concept.and_then(|c| c.display()).map(str::to_string)
+}
+
+fn encounter_event(encounter: &Encounter, start: &str) -> TimelineEvent {
+    let inpatient = encounter.is_inpatient();
+    let emergency = encounter.class.code.as_deref() == Some("EMER");
+
+    let title = display(encounter.encounter_type.first())
+        .or_else(|| encounter.class.display.clone())
+        .unwrap_or_else(|| match (inpatient, emergency) {
+            (true, _) => "Hospital Admission".to_string(),
+            (false, true) => "Emergency Visit".to_string(),
+            _ => "Visit".to_string(),
+        });
+
+    let mut details: Vec<String> = vec![];
+    let reasons: Vec<String> = encounter.reason_code.iter().filter_map(|r| display(Some(r))).collect();
+    if !reasons.is_empty() {
+        details.push(format!("Reason: {}", reasons.join(", ")));
+    }
+    if let Some(hospitalization) = &encounter.hospitalization {
+        if let Some(source) = display(hospitalization.admit_source.as_ref()) {
+            details.push(format!("Admitted from {}", source.to_lowercase()));
+        }
+        if let Some(disposition) = display(hospitalization.discharge_disposition.as_ref()) {
+            details.push(format!("Discharged to {}", disposition.to_lowercase()));
+        }
+    }
+    if let Some(end) = encounter.period.as_ref().and_then(|p| p.end.as_deref())
+        && inpatient
+    {
+        details.push(format!("From {} to {}", day(start), day(end)));
+    }
+
+    let icon = if inpatient || emergency { "Hospital" } else { "Stethoscope" };
+    event(start, title, join(details, "Encounter on", start), icon, inpatient)
+}
+
+fn condition_events(condition: &Condition, today: NaiveDate) -> Vec<(String, TimelineEvent)> {
+    let name = display(condition.code.as_ref()).unwrap_or_else(|| "Condition".to_string());
+    let mut events = vec![];
+
+    let onset = condition.onset.as_ref().and_then(|o| o.start())
+        .or(condition.recorded_date.as_deref());
+    let abatement = condition.abatement.as_ref().and_then(|a| a.start());
+
+    if let Some(onset) = onset {
+        let chronic = condition.has_category("problem-list-item")
+            && condition.has_clinical_status("active")
+            && abatement.is_none();
+        let recent = parse_day(onset).is_some_and(|date| today - date <= Duration::days(365));
+
+        let mut details = vec![];
+        if let Some(severity) = display(condition.severity.as_ref()) {
+            details.push(format!("Severity: {}", severity.to_lowercase()));
+        }
+        if chronic {
+            details.push("On the problem list".to_string());
+        }
+
+        events.push((onset.to_string(), event(
+            onset,
+            format!("{} Diagnosis", name),
+            join(details, "Diagnosed on", onset),
+            "Activity",
+            chronic && recent,
+        )));
+    }
+
+    if let Some(abatement) = abatement {
+        events.push((abatement.to_string(), event(
+            abatement,
+            format!("{} Resolved", name),
+            format!("Resolved on {}", day(abatement)),
+            "HeartPulse",
+            false,
+        )));
+    }
+
+    events
+}
+
+fn procedure_event(procedure: &Procedure, start: &str) -> TimelineEvent {
+    let title = display(procedure.code.as_ref()).unwrap_or_else(|| "Procedure".to_string());
+    let category = procedure.category.as_ref();
+
+    let icon = match category {
+        Some(c) if c.has_code(SNOMED_SYSTEM, SURGICAL_PROCEDURE) => "Hospital",
+        Some(c) if c.has_code(SNOMED_SYSTEM, DIAGNOSTIC_PROCEDURE) => "Stethoscope",
+        _ => "Syringe",
+    };
+
+    let mut details = vec![];
+    let reasons: Vec<String> = procedure.reason_code.iter().filter_map(|r| display(Some(r))).collect();
+    if !reasons.is_empty() {
+        details.push(format!("Reason: {}", reasons.join(", ")));
+    }
+    if let Some(outcome) = display(procedure.outcome.as_ref()) {
+        details.push(format!("Outcome: {}", outcome.to_lowercase()));
+    }
+
+    event(start, title, join(details, "Performed on", start), icon, false)
+}
+
+/// Details joined in one sentence, or the date when there are none
+fn join(details: Vec<String>, label: &str, date: &str) -> String {
+    if details.is_empty() {
+        format!("{} {}.", label, day(date))
+    } else {
+        format!("{}.", details.join(". "))
+    }
+}
+
+fn day(date: &str) -> &str {
+    date.get(..10).unwrap_or(date)
+}
+
+fn parse_day(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(day(date), "%Y-%m-%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scrab_fhir::from_value;
+    use serde_json::{Value, json};
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()
+    }
+
+    fn encounter(class: &str, start: &str, status: &str) -> Encounter {
+        from_value(json!({
+            "resourceType": "Encounter",
+            "status": status,
+            "class": { "system": "http://terminology.hl7.org/CodeSystem/v3-ActCode", "code": class },
+            "period": { "start": start, "end": "2024-03-05" },
+            "reasonCode": [{ "text": "Pneumonia" }]
+        })).unwrap()
+    }
+
+    fn condition(name: &str, onset: &str, extra: Value) -> Condition {
+        let mut condition = json!({
+            "resourceType": "Condition",
+            "clinicalStatus": { "coding": [{ "system": "http://terminology.hl7.org/CodeSystem/condition-clinical", "code": "active" }] },
+            "category": [{ "coding": [{ "system": "http://terminology.hl7.org/CodeSystem/condition-category", "code": "problem-list-item" }] }],
+            "code": { "text": name },
+            "onsetDateTime": onset
+        });
+        condition.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        from_value(condition).unwrap()
+    }
+
+    fn procedure(category: Option<&str>, performed: &str, status: &str) -> Procedure {
+        let mut procedure = json!({
+            "resourceType": "Procedure",
+            "status": status,
+            "code": { "text": "Procedure" },
+            "performedDateTime": performed
+        });
+        if let Some(code) = category {
+            procedure["category"] = json!({ "coding": [{ "system": SNOMED_SYSTEM, "code": code }] });
+        }
+        from_value(procedure).unwrap()
+    }
+
+    fn verification(code: &str) -> Value {
+        json!({ "verificationStatus": { "coding": [{ "system": "http://terminology.hl7.org/CodeSystem/condition-ver-status", "code": code }] } })
+    }
+
+    #[test]
+    fn highlights_inpatient_stays() {
+        let timeline = build_timeline(
+            &[encounter("IMP", "2024-03-01", "finished"), encounter("AMB", "2024-02-01", "finished")],
+            &[],
+            &[],
+            today(),
+        );
+
+        assert_eq!(timeline[0].title, "Hospital Admission");
+        assert_eq!(timeline[0].icon, "Hospital");
+        assert!(timeline[0].highlight);
+        assert_eq!(timeline[0].description, "Reason: Pneumonia. From 2024-03-01 to 2024-03-05.");
+        assert_eq!((timeline[1].title.as_str(), timeline[1].icon.as_str()), ("Visit", "Stethoscope"));
+        assert!(!timeline[1].highlight);
+    }
+
+    #[test]
+    fn highlights_chronic_diagnoses_of_the_last_year() {
+        let timeline = build_timeline(&[], &[
+            condition("Diabetes", "2023-06-02", json!({})),
+            condition("Asthma", "2023-05-31", json!({})),
+            condition("Cough", "2024-05-01", json!({ "category": [] })),
+        ], &[], today());
+
+        let highlighted = |name: &str| timeline.iter()
+            .find(|e| e.title == format!("{} Diagnosis", name))
+            .unwrap()
+            .highlight;
+        assert!(highlighted("Diabetes"));
+        assert!(!highlighted("Asthma"), "diagnosed more than 365 days ago");
+        assert!(!highlighted("Cough"), "not on the problem list");
+    }
+
+    #[test]
+    fn adds_an_event_when_a_condition_resolves() {
+        let timeline = build_timeline(&[], &[
+            condition("Fracture", "2023-01-10", json!({ "abatementDateTime": "2023-03-15T10:00:00Z" })),
+        ], &[], today());
+
+        assert_eq!(timeline.len(), 2);
+        assert_eq!(timeline[0].title, "Fracture Resolved");
+        assert_eq!(timeline[0].description, "Resolved on 2023-03-15");
+        assert_eq!(timeline[0].icon, "HeartPulse");
+        assert!(!timeline[1].highlight, "a resolved condition is not chronic");
+    }
+
+    #[test]
+    fn picks_the_procedure_icon_by_category() {
+        let timeline = build_timeline(&[], &[], &[
+            procedure(Some(SURGICAL_PROCEDURE), "2024-03-03", "completed"),
+            procedure(Some(DIAGNOSTIC_PROCEDURE), "2024-03-02", "completed"),
+            procedure(None, "2024-03-01", "completed"),
+        ], today());
+
+        let icons: Vec<&str> = timeline.iter().map(|e| e.icon.as_str()).collect();
+        assert_eq!(icons, ["Hospital", "Stethoscope", "Syringe"]);
+    }
+
+    #[test]
+    fn skips_cancelled_refuted_and_erroneous_entries() {
+        let timeline = build_timeline(
+            &[encounter("AMB", "2024-03-01", "cancelled"), encounter("AMB", "2024-03-02", "entered-in-error")],
+            &[
+                condition("Refuted", "2024-03-01", verification("refuted")),
+                condition("Mistake", "2024-03-01", verification("entered-in-error")),
+            ],
+            &[procedure(None, "2024-03-01", "not-done"), procedure(None, "2024-03-02", "entered-in-error")],
+            today(),
+        );
+        assert!(timeline.is_empty(), "{:?}", timeline);
+    }
+
+    #[test]
+    fn keeps_the_most_recent_events() {
+        let procedures: Vec<Procedure> = (0..MAX_EVENTS as i64)
+            .map(|days| procedure(None, &(today() - Duration::days(days)).to_string(), "completed"))
+            .chain([procedure(None, "2019-12-31", "completed")])
+            .collect();
+
+        let timeline = build_timeline(&[], &[], &procedures, today());
+        assert_eq!(timeline.len(), MAX_EVENTS);
+        assert_eq!(timeline[0].description, "Performed on 2024-06-01.");
+        assert!(timeline.iter().all(|e| e.year == "2024"), "the oldest event is dropped");
+    }
+}
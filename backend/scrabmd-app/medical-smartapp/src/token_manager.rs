@@ -0,0 +1,297 @@
+use lambda_runtime::tracing::{info, warn};
+use crate::oidc_database::{SessionData, get_session_data, update_session_token};
+use crate::oidc_request::{TokenResponse, refresh_access_token};
+use crate::scrab_errors::ScrabError;
+
+// Length of a session from its launch, token renewals do not extend it
+pub const SESSION_LENGTH: i64 = 60 * 60 * 1000; // 1 hour
+
+// Renew the access token this long before it expires, so it does not lapse mid-page
+const EXPIRY_MARGIN: i64 = 60 * 1000; // 1 minute
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ SESSION TOKEN ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+impl SessionData {
+    /// Whether the access token has expired, or is about to
+    pub fn token_expired(&self, now: i64) -> bool {
+        self.access_token.is_none()
+            || self.token_expires_at.is_some_and(|expires_at| expires_at - EXPIRY_MARGIN <= now)
+    }
+
+    /// Whether the session has outlived its `SESSION_LENGTH`, the user has to launch the app again
+    pub fn session_expired(&self, now: i64) -> bool {
+        self.session_timeout.is_some_and(|timeout| timeout != 0 && timeout <= now)
+    }
+
+    /// Take over a token response, keeping the refresh and id tokens the server did not rotate
+    pub fn apply_token(&mut self, token: TokenResponse, now: i64) {
+        self.access_token = Some(token.access_token);
+        self.expires_in = token.expires_in;
+        self.token_expires_at = token.expires_in.map(|seconds| now + i64::from(seconds) * 1000);
+        if token.refresh_token.is_some() {
+            self.refresh_token = token.refresh_token;
+        }
+        if token.id_token.is_some() {
+            self.id_token = token.id_token;
+        }
+        if token.token_type.is_some() {
+            self.token_type = token.token_type;
+        }
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ TOKEN STORE ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Storage of the session a renewed token is written back to
+pub trait TokenStore {
+    async fn get(&self, pk: &str) -> Result<Option<SessionData>, ScrabError>;
+
+    /// Store the token of `session` if the stored refresh token is still `previous_refresh_token`
+    async fn replace_token(&self, session: &SessionData, previous_refresh_token: &str) -> Result<bool, ScrabError>;
+}
+
+/// Sessions in the `TABLE_NAME` DynamoDB table
+pub struct DynamoTokenStore {
+    table_name: String,
+}
+
+impl DynamoTokenStore {
+    pub fn new(table_name: &str) -> Self {
+        Self { table_name: table_name.to_string() }
+    }
+}
+
+impl TokenStore for DynamoTokenStore {
+    async fn get(&self, pk: &str) -> Result<Option<SessionData>, ScrabError> {
+        get_session_data(pk, &self.table_name).await
+            .map_err(|e| ScrabError::RequestError(e.to_string()))
+    }
+
+    async fn replace_token(&self, session: &SessionData, previous_refresh_token: &str) -> Result<bool, ScrabError> {
+        update_session_token(session, previous_refresh_token, &self.table_name).await
+            .map_err(|e| ScrabError::RequestError(e.to_string()))
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ TOKEN MANAGER ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Access token of the session to call the FHIR server with. An expired token is
+/// renewed with the `refresh_token` grant and written back to the store; an error
+/// means the session has timed out or cannot be renewed, and the user has to
+/// launch the app again.
+pub async fn session_access_token(
+    store: &impl TokenStore,
+    session: &mut SessionData,
+    now: i64,
+) -> Result<String, ScrabError> {
+    if session.session_expired(now) {
+        return Err(ScrabError::Authentication("Session has timed out".into()));
+    }
+    if !session.token_expired(now) {
+        return Ok(session.access_token.clone().unwrap_or_default());
+    }
+
+    let refresh_token = session.refresh_token.clone()
+        .ok_or_else(|| ScrabError::Authentication("Session has no refresh token".into()))?;
+    let client_id = session.client_id.clone().unwrap_or_default();
+    let token_endpoint = session.token_endpoint.clone()
+        .ok_or(ScrabError::MissingTokenEndpoint)?;
+
+    let token = refresh_access_token(&client_id, &token_endpoint, &refresh_token).await?;
+    let mut renewed = session.clone();
+    renewed.apply_token(token, now);
+
+    if store.replace_token(&renewed, &refresh_token).await? {
+        info!("Access token renewed for session {}", session.pk);
+        *session = renewed;
+    } else {
+        // Another request rotated the refresh token first, its token is the one to use
+        warn!("Access token of session {} renewed concurrently", session.pk);
+        let stored = store.get(&session.pk).await?
+            .filter(|stored| !stored.token_expired(now))
+            .ok_or_else(|| ScrabError::Authentication("Concurrently renewed token is not available".into()))?;
+        *session = stored;
+    }
+
+    Ok(session.access_token.clone().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_dynamodb::types::AttributeValue;
+    use scrab_testing::MockServer;
+    use std::sync::Mutex;
+
+    const NOW: i64 = 1_700_000_000_000;
+
+    /// Store of one session; with `concurrent` set, another request has already rotated its token
+    #[derive(Default)]
+    struct TestStore {
+        replaced: Mutex<Vec<SessionData>>,
+        concurrent: Option<SessionData>,
+    }
+
+    impl TokenStore for TestStore {
+        async fn get(&self, _pk: &str) -> Result<Option<SessionData>, ScrabError> {
+            Ok(self.concurrent.clone().or_else(|| self.replaced.lock().unwrap().last().cloned()))
+        }
+
+        async fn replace_token(&self, session: &SessionData, _previous: &str) -> Result<bool, ScrabError> {
+            if self.concurrent.is_some() {
+                return Ok(false);
+            }
+            self.replaced.lock().unwrap().push(session.clone());
+            Ok(true)
+        }
+    }
+
+    fn session(token_expires_at: i64) -> SessionData {
+        SessionData {
+            pk: "state-1".to_string(),
+            access_token: Some("access-1".to_string()),
+            refresh_token: Some("refresh-1".to_string()),
+            token_expires_at: Some(token_expires_at),
+            session_timeout: Some(NOW + SESSION_LENGTH),
+            client_id: Some("client".to_string()),
+            token_endpoint: Some("http://127.0.0.1:9/token".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn expires_tokens_early_and_keeps_unrotated_tokens() {
+        assert!(!session(NOW + 5 * 60 * 1000).token_expired(NOW));
+        assert!(session(NOW + 30 * 1000).token_expired(NOW));
+        assert!(!SessionData { session_timeout: Some(NOW - 1), ..session(NOW + SESSION_LENGTH) }.token_expired(NOW));
+        assert!(SessionData { session_timeout: Some(NOW - 1), ..session(NOW + SESSION_LENGTH) }.session_expired(NOW));
+        assert!(!SessionData { session_timeout: Some(0), ..session(NOW) }.session_expired(NOW));
+
+        let mut renewed = session(NOW);
+        renewed.apply_token(TokenResponse {
+            access_token: "access-2".to_string(),
+            expires_in: Some(3600),
+            refresh_token: None,
+            token_type: Some("Bearer".to_string()),
+            scope: None,
+            id_token: None,
+            patient: None,
+        }, NOW);
+        assert_eq!(renewed.access_token.as_deref(), Some("access-2"));
+        assert_eq!(renewed.refresh_token.as_deref(), Some("refresh-1"));
+        assert_eq!(renewed.token_expires_at, Some(NOW + 3600 * 1000));
+        assert!(!renewed.token_expired(NOW));
+    }
+
+    #[tokio::test]
+    async fn keeps_valid_tokens_and_fails_without_renewal() {
+        let store = TestStore::default();
+
+        let mut valid = session(NOW + SESSION_LENGTH);
+        assert_eq!(session_access_token(&store, &mut valid, NOW).await.unwrap(), "access-1");
+
+        let mut expired = session(NOW);
+        assert!(session_access_token(&store, &mut expired, NOW).await.is_err());
+        assert_eq!(expired.access_token.as_deref(), Some("access-1"));
+
+        let mut no_refresh = SessionData { refresh_token: None, ..session(NOW) };
+        assert!(matches!(
+            session_access_token(&store, &mut no_refresh, NOW).await,
+            Err(ScrabError::Authentication(_))
+        ));
+        assert!(store.replaced.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn ends_the_session_after_its_length_even_with_a_valid_token() {
+        let server = MockServer::json(
+            r#"{"access_token":"access-2","token_type":"Bearer","expires_in":3600}"#,
+        ).await;
+        let store = TestStore::default();
+
+        for token_expires_at in [NOW + SESSION_LENGTH, NOW] {
+            let mut timed_out = SessionData {
+                token_endpoint: Some(server.url("/token")),
+                session_timeout: Some(NOW),
+                ..session(token_expires_at)
+            };
+            assert!(matches!(
+                session_access_token(&store, &mut timed_out, NOW).await,
+                Err(ScrabError::Authentication(_))
+            ));
+        }
+        assert!(server.requests().is_empty());
+        assert!(store.replaced.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn renews_an_expired_token_and_stores_it() {
+        let server = MockServer::json(
+            r#"{"access_token":"access-2","token_type":"Bearer","expires_in":3600,"refresh_token":"refresh-2"}"#,
+        ).await;
+        let store = TestStore::default();
+        let mut expired = SessionData {
+            token_endpoint: Some(server.url("/token")),
+            session_timeout: Some(NOW + 60 * 1000),
+            ..session(NOW)
+        };
+
+        assert_eq!(session_access_token(&store, &mut expired, NOW).await.unwrap(), "access-2");
+        assert_eq!(expired.refresh_token.as_deref(), Some("refresh-2"));
+        assert_eq!(expired.token_expires_at, Some(NOW + 3600 * 1000));
+        // The renewal does not extend the session
+        assert_eq!(expired.session_timeout, Some(NOW + 60 * 1000));
+
+        let request = server.requests()[0].clone();
+        assert!(request.contains("grant_type=refresh_token"), "{}", request);
+        assert!(request.contains("refresh_token=refresh-1"), "{}", request);
+        assert_eq!(store.replaced.lock().unwrap()[0].access_token.as_deref(), Some("access-2"));
+    }
+
+    #[tokio::test]
+    async fn removes_the_expiry_of_a_renewal_without_expires_in() {
+        let server = MockServer::json(r#"{"access_token":"access-2","token_type":"Bearer"}"#).await;
+        let store = TestStore::default();
+        let mut expired = SessionData {
+            token_endpoint: Some(server.url("/token")),
+            expires_in: Some(3600),
+            id_token: None,
+            ..session(NOW)
+        };
+
+        assert_eq!(session_access_token(&store, &mut expired, NOW).await.unwrap(), "access-2");
+        assert_eq!(expired.token_expires_at, None);
+
+        let (expression, values) = store.replaced.lock().unwrap()[0].token_update();
+        assert!(expression.ends_with(" REMOVE expires_in, token_expires_at, id_token"), "{}", expression);
+        assert!(!values.contains_key(":token_expires_at"));
+        assert_eq!(values[":refresh_token"], AttributeValue::S("refresh-1".to_string()));
+    }
+
+    #[tokio::test]
+    async fn uses_the_token_of_a_concurrent_renewal() {
+        let server = MockServer::json(
+            r#"{"access_token":"access-2","token_type":"Bearer","expires_in":3600,"refresh_token":"refresh-2"}"#,
+        ).await;
+        let concurrent = SessionData {
+            access_token: Some("access-3".to_string()),
+            refresh_token: Some("refresh-3".to_string()),
+            ..session(NOW + SESSION_LENGTH)
+        };
+        let store = TestStore { concurrent: Some(concurrent), ..Default::default() };
+        let mut expired = SessionData { token_endpoint: Some(server.url("/token")), ..session(NOW) };
+
+        assert_eq!(session_access_token(&store, &mut expired, NOW).await.unwrap(), "access-3");
+        assert_eq!(expired.refresh_token.as_deref(), Some("refresh-3"));
+        assert!(store.replaced.lock().unwrap().is_empty());
+
+        // The token the other request stored has expired as well
+        let stale = TestStore { concurrent: Some(session(NOW)), ..Default::default() };
+        let mut expired = SessionData { token_endpoint: Some(server.url("/token")), ..session(NOW) };
+        assert!(matches!(
+            session_access_token(&stale, &mut expired, NOW).await,
+            Err(ScrabError::Authentication(_))
+        ));
+        assert_eq!(expired.access_token.as_deref(), Some("access-1"));
+    }
+}
@@ -0,0 +1,290 @@
+use lambda_runtime::tracing::error;
+use scrab_fhir::{
+    CarePlan, MedicationRequest, Procedure, Reference, ReferenceResolver, SearchParams, SearchResult,
+};
+use crate::libs::{MainPageParams, Treatment};
+use crate::medications::{medication_name, to_medication};
+use crate::references::reference_name;
+use crate::scrab_errors::ScrabError;
+
+pub async fn extract_treatments(
+    params: &MainPageParams,
+    resolver: &ReferenceResolver,
+) -> Vec<Treatment> {
+    match extract_treatments_handle(params, resolver).await {
+        Ok(treatments) => treatments,
+        Err(e) => {
+            error!("Error extracting treatments: {:?}", e);
+            vec![]
+        }
+    }
+}
+
+/// Treatments of the patient from Procedures, CarePlan activities and
+/// MedicationRequest changes, oldest first
+pub async fn extract_treatments_handle(
+    params: &MainPageParams,
+    resolver: &ReferenceResolver,
+) -> Result<Vec<Treatment>, ScrabError> {
+
+    let procedures = search(resolver, SearchParams::of::<Procedure>()
+        .with_patient(&params.patient_id)
+        .with_include("Procedure:performer")
+    ).await;
+    let care_plans = search(resolver, SearchParams::of::<CarePlan>()
+        .with_patient(&params.patient_id)
+        .with_include("CarePlan:performer")
+    ).await;
+    let requests = search(resolver, SearchParams::of::<MedicationRequest>()
+        .with_patient(&params.patient_id)
+        .with_include("MedicationRequest:requester")
+        .with_include("MedicationRequest:medication")
+    ).await;
+
+    Ok(build_treatments(&procedures, &care_plans, &requests, resolver).await)
+}
+
+/// Treatments of the search results, with the performers and requesters named
+/// from the included resources
+pub async fn build_treatments(
+    procedures: &SearchResult,
+    care_plans: &SearchResult,
+    requests: &SearchResult,
+    resolver: &ReferenceResolver,
+) -> Vec<Treatment> {
+    let mut treatments = vec![];
+
+    for procedure in procedures.resources::<Procedure>() {
+        if matches!(procedure.status.as_str(), "not-done" | "entered-in-error" | "preparation") {
+            continue;
+        }
+        let Some(date) = procedure.performed.as_ref().and_then(|p| p.start()).map(str::to_string) else {
+            continue;
+        };
+
+        let notes = procedure.code.as_ref().and_then(|c| c.display()).unwrap_or("Procedure").to_string();
+        let reasons: Vec<&str> = procedure.reason_code.iter().filter_map(|r| r.display()).collect();
+        treatments.push(Treatment {
+            date,
+            t_type: procedure.category.as_ref().and_then(|c| c.display()).unwrap_or("Procedure").to_string(),
+            provider: provider(resolver, procedure.performer.first().map(|p| &p.actor)).await,
+            notes: if reasons.is_empty() { notes } else { format!("{} for {}", notes, reasons.join(", ")) },
+        });
+    }
+
+    for plan in care_plans.resources::<CarePlan>() {
+        if matches!(plan.status.as_str(), "draft" | "entered-in-error" | "revoked") {
+            continue;
+        }
+        let t_type = plan.category.first().and_then(|c| c.display())
+            .or(plan.title.as_deref())
+            .unwrap_or("Care Plan")
+            .to_string();
+
+        for detail in plan.activity.iter().filter_map(|a| a.detail.as_ref()) {
+            if matches!(detail.status.as_str(), "cancelled" | "entered-in-error" | "stopped") {
+                continue;
+            }
+            let date = detail.scheduled.as_ref().and_then(|s| s.start())
+                .or(plan.period.as_ref().and_then(|p| p.start.as_deref()))
+                .or(plan.created.as_deref());
+            let Some(date) = date.map(str::to_string) else {
+                continue;
+            };
+            let notes = detail.code.as_ref().and_then(|c| c.display()).map(str::to_string)
+                .or_else(|| detail.description.clone())
+                .unwrap_or_else(|| "Care plan activity".to_string());
+
+            treatments.push(Treatment {
+                date,
+                t_type: t_type.clone(),
+                provider: provider(resolver, detail.performer.first().or(plan.author.as_ref())).await,
+                notes,
+            });
+        }
+    }
+
+    for request in requests.resources::<MedicationRequest>() {
+        let t_type = match request.status.as_str() {
+            "active" if request.prior_prescription.is_some() => "Medication Adjustment",
+            "active" => "Medication Started",
+            "stopped" | "completed" => "Medication Stopped",
+            "on-hold" => "Medication On Hold",
+            _ => continue,
+        };
+        // A stopped medication is dated when it ended, not when it was prescribed
+        let date = match t_type {
+            "Medication Stopped" => request.end_date()
+                .or(request.meta.as_ref().and_then(|m| m.last_updated.as_deref())),
+            _ => request.authored_on.as_deref(),
+        };
+        let Some(date) = date.map(str::to_string) else {
+            continue;
+        };
+        let Some(name) = medication_name(resolver, request.medication.as_ref(), &request.contained).await else {
+            continue;
+        };
+
+        let medication = to_medication(name, request.dosage_instruction.first());
+        let notes = match (medication.dosage.as_str(), medication.frequency.as_str()) {
+            ("n/a", _) => medication.name,
+            (dosage, "n/a") => format!("{} {}", medication.name, dosage),
+            (dosage, frequency) => format!("{} {}, {}", medication.name, dosage, frequency.to_lowercase()),
+        };
+
+        treatments.push(Treatment {
+            date,
+            t_type: t_type.to_string(),
+            provider: provider(resolver, request.requester.as_ref()).await,
+            notes,
+        });
+    }
+
+    treatments.sort_by(|a, b| a.date.cmp(&b.date));
+    for treatment in &mut treatments {
+        treatment.date.truncate(10);
+    }
+    treatments
+}
+
+/// Search with `_include`s, indexing the included resources; a failed search
+/// leaves the other treatments on the page
+async fn search(resolver: &ReferenceResolver, params: SearchParams) -> SearchResult {
+    match resolver.client().search(&params).await {
+        Ok(result) => {
+            resolver.add_search(&result);
+            result
+        }
+        Err(e) => {
+            error!("Error searching {}: {:?}", params.resource_type(), e);
+            SearchResult::default()
+        }
+    }
+}
+
+async fn provider(resolver: &ReferenceResolver, reference: Option<&Reference>) -> String {
+    match reference {
+        Some(reference) => reference_name(resolver, reference, &[]).await.unwrap_or_else(|| "n/a".to_string()),
+        None => "n/a".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scrab_fhir::FhirClient;
+    use serde_json::{Value, json};
+
+    // Nothing listens on the discard port, names come from the included resources
+    fn resolver() -> ReferenceResolver {
+        ReferenceResolver::new(FhirClient::new("http://127.0.0.1:9/r4", "token").unwrap())
+    }
+
+    fn search(resolver: &ReferenceResolver, resources: Vec<Value>) -> SearchResult {
+        let entries: Vec<Value> = resources.into_iter()
+            .map(|resource| {
+                let mode = if resource["resourceType"] == "Practitioner" { "include" } else { "match" };
+                json!({ "search": { "mode": mode }, "resource": resource })
+            })
+            .collect();
+        let mut result = SearchResult::default();
+        result.add_page(&json!({ "resourceType": "Bundle", "type": "searchset", "entry": entries }));
+        resolver.add_search(&result);
+        result
+    }
+
+    fn request(status: &str, extra: Value) -> Value {
+        let mut request = json!({
+            "resourceType": "MedicationRequest",
+            "status": status,
+            "intent": "order",
+            "medicationCodeableConcept": { "text": format!("Drug {}", status) },
+            "authoredOn": "2024-01-10",
+            "requester": { "reference": "Practitioner/pr-1" }
+        });
+        request.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        request
+    }
+
+    fn practitioner() -> Value {
+        json!({ "resourceType": "Practitioner", "id": "pr-1", "name": [{ "family": "Chen", "given": ["Emily"], "prefix": ["Dr."] }] })
+    }
+
+    #[tokio::test]
+    async fn dates_each_medication_change() {
+        let resolver = resolver();
+        let requests = search(&resolver, vec![
+            practitioner(),
+            request("active", json!({})),
+            request("on-hold", json!({ "authoredOn": "2024-02-01" })),
+            request("active", json!({ "authoredOn": "2024-03-01", "priorPrescription": { "reference": "MedicationRequest/1" } })),
+            request("stopped", json!({
+                "dosageInstruction": [{ "timing": { "repeat": { "boundsPeriod": { "start": "2024-01-10", "end": "2024-04-15" } } } }]
+            })),
+            request("completed", json!({
+                "dispenseRequest": { "validityPeriod": { "start": "2024-01-10", "end": "2024-05-20T00:00:00Z" } }
+            })),
+            request("draft", json!({})),
+        ]);
+
+        let treatments = build_treatments(&SearchResult::default(), &SearchResult::default(), &requests, &resolver).await;
+        let changes: Vec<(&str, &str)> = treatments.iter().map(|t| (t.date.as_str(), t.t_type.as_str())).collect();
+        assert_eq!(changes, [
+            ("2024-01-10", "Medication Started"),
+            ("2024-02-01", "Medication On Hold"),
+            ("2024-03-01", "Medication Adjustment"),
+            ("2024-04-15", "Medication Stopped"),
+            ("2024-05-20", "Medication Stopped"),
+        ]);
+        assert!(treatments.iter().all(|t| t.provider == "Dr. Emily Chen"));
+        assert_eq!(resolver.reads(), 0);
+    }
+
+    #[tokio::test]
+    async fn skips_stopped_medications_without_a_stop_date() {
+        let resolver = resolver();
+        let requests = search(&resolver, vec![request("stopped", json!({}))]);
+
+        let treatments = build_treatments(&SearchResult::default(), &SearchResult::default(), &requests, &resolver).await;
+        assert!(treatments.is_empty(), "dated with authoredOn: {:?}", treatments);
+    }
+
+    #[tokio::test]
+    async fn sorts_procedures_and_care_plan_activities_by_date() {
+        let resolver = resolver();
+        let procedures = search(&resolver, vec![
+            practitioner(),
+            json!({
+                "resourceType": "Procedure",
+                "status": "completed",
+                "code": { "text": "Appendectomy" },
+                "reasonCode": [{ "text": "Appendicitis" }],
+                "performedDateTime": "2023-08-02T10:00:00Z",
+                "performer": [{ "actor": { "reference": "Practitioner/pr-1" } }]
+            }),
+            json!({ "resourceType": "Procedure", "status": "not-done", "performedDateTime": "2023-01-01" }),
+        ]);
+        let care_plans = search(&resolver, vec![json!({
+            "resourceType": "CarePlan",
+            "status": "active",
+            "intent": "plan",
+            "title": "Rehabilitation",
+            "created": "2023-09-01",
+            "activity": [
+                { "detail": { "code": { "text": "Physical therapy" }, "status": "in-progress", "scheduledPeriod": { "start": "2023-07-01" } } },
+                { "detail": { "description": "Walking program", "status": "scheduled" } },
+                { "detail": { "description": "Cancelled session", "status": "cancelled" } }
+            ]
+        })]);
+
+        let treatments = build_treatments(&procedures, &care_plans, &SearchResult::default(), &resolver).await;
+        let rows: Vec<(&str, &str, &str, &str)> = treatments.iter()
+            .map(|t| (t.date.as_str(), t.t_type.as_str(), t.provider.as_str(), t.notes.as_str()))
+            .collect();
+        assert_eq!(rows, [
+            ("2023-07-01", "Rehabilitation", "n/a", "Physical therapy"),
+            ("2023-08-02", "Procedure", "Dr. Emily Chen", "Appendectomy for Appendicitis"),
+            ("2023-09-01", "Rehabilitation", "n/a", "Walking program"),
+        ]);
+    }
+}
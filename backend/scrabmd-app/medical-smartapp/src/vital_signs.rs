@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+use lambda_runtime::tracing::error;
+use scrab_fhir::{FhirClient, LOINC_SYSTEM, Observation, Quantity, SearchParams};
+use scrab_fhir::ucum::FAHRENHEIT;
+use crate::libs::{MainPageParams, VitalSign};
+use crate::scrab_errors::ScrabError;
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ LOINC CODES ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+const HEART_RATE: &str = "8867-4";
+const BLOOD_PRESSURE_PANEL: &str = "85354-9";
+const SYSTOLIC: &str = "8480-6";
+const DIASTOLIC: &str = "8462-4";
+const BODY_TEMPERATURE: &str = "8310-5";
+const RESPIRATORY_RATE: &str = "9279-1";
+const OXYGEN_SATURATION: &[&str] = &["2708-6", "59408-5"];
+
+pub async fn extract_vital_signs(
+    params: &MainPageParams,
+) -> Vec<VitalSign> {
+    match extract_vital_signs_handle(params).await {
+        Ok(vital_signs) => vital_signs,
+        Err(e) => {
+            error!("Error extracting vital signs: {:?}", e);
+            vec![]
+        }
+    }
+}
+
+/// Vital signs of the patient, one entry per effective date, oldest first
+pub async fn extract_vital_signs_handle(
+    params: &MainPageParams,
+) -> Result<Vec<VitalSign>, ScrabError> {
+    let client = FhirClient::new(&params.iss, &params.access_token)?;
+
+    let result = client.search(&SearchParams::of::<Observation>()
+        .with_patient(&params.patient_id)
+        .with("category", "vital-signs")
+        .with_sort("-date")
+        .with_count(100)
+    ).await?;
+
+    Ok(group_vital_signs(result.resources::<Observation>()))
+}
+
+/// Group vital-sign Observations by the date they were taken
+pub fn group_vital_signs(mut observations: Vec<Observation>) -> Vec<VitalSign> {
+    observations.retain(|o| !matches!(o.status.as_str(), "entered-in-error" | "cancelled"));
+    // Later readings of the same day replace earlier ones
+    observations.sort_by(|a, b| effective_start(a).cmp(&effective_start(b)));
+
+    let mut by_date: BTreeMap<String, VitalSign> = BTreeMap::new();
+    for observation in &observations {
+        let Some(start) = effective_start(observation) else {
+            continue;
+        };
+        let date = start.chars().take(10).collect::<String>();
+        let vital = by_date.entry(date.clone()).or_insert_with(|| VitalSign { date, ..Default::default() });
+        apply_observation(vital, observation);
+    }
+
+    by_date.into_values()
+        .filter(|v| v.heart_rate.is_some() || v.systolic.is_some() || v.diastolic.is_some()
+            || v.temperature.is_some() || v.respiratory_rate.is_some() || v.oxygen_saturation.is_some())
+        .collect()
+}
+
+fn effective_start(observation: &Observation) -> Option<&str> {
+    observation.effective.as_ref()
+        .and_then(|effective| effective.start())
+        .or(observation.issued.as_deref())
+}
+
+fn apply_observation(vital: &mut VitalSign, observation: &Observation) {
+    let code = &observation.code;
+    let quantity = observation.value.as_ref().and_then(|value| value.as_quantity());
+
+    if code.has_code(LOINC_SYSTEM, HEART_RATE) {
+        set(&mut vital.heart_rate, whole(quantity));
+    } else if code.has_code(LOINC_SYSTEM, RESPIRATORY_RATE) {
+        set(&mut vital.respiratory_rate, whole(quantity));
+    } else if OXYGEN_SATURATION.iter().any(|c| code.has_code(LOINC_SYSTEM, c)) {
+        set(&mut vital.oxygen_saturation, whole(quantity));
+    } else if code.has_code(LOINC_SYSTEM, BODY_TEMPERATURE) {
+        let fahrenheit = quantity.and_then(|q| q.value_in(FAHRENHEIT));
+        set(&mut vital.temperature, fahrenheit.map(|t| ((t * 10.0).round() / 10.0) as f32));
+    } else if code.has_code(LOINC_SYSTEM, BLOOD_PRESSURE_PANEL) {
+        let component = |loinc| observation.component(LOINC_SYSTEM, loinc)
+            .and_then(|c| c.value.as_ref())
+            .and_then(|value| value.as_quantity());
+        set(&mut vital.systolic, whole(component(SYSTOLIC)));
+        set(&mut vital.diastolic, whole(component(DIASTOLIC)));
+    } else if code.has_code(LOINC_SYSTEM, SYSTOLIC) {
+        set(&mut vital.systolic, whole(quantity));
+    } else if code.has_code(LOINC_SYSTEM, DIASTOLIC) {
+        set(&mut vital.diastolic, whole(quantity));
+    }
+
+    vital.blood_pressure = match (vital.systolic, vital.diastolic) {
+        (Some(systolic), Some(diastolic)) => Some(format!("{}/{}", systolic, diastolic)),
+        _ => None,
+    };
+}
+
+/// Keep the previous reading when this one has no value
+fn set<T>(field: &mut Option<T>, value: Option<T>) {
+    if value.is_some() {
+        *field = value;
+    }
+}
+
+fn whole(quantity: Option<&Quantity>) -> Option<u32> {
+    quantity.and_then(|q| q.value)
+        .filter(|value| *value >= 0.0)
+        .map(|value| value.round() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scrab_fhir::from_value;
+    use serde_json::{Value, json};
+
+    fn observation(loinc: &str, date: &str, extra: Value) -> Observation {
+        let mut observation = json!({
+            "resourceType": "Observation",
+            "status": "final",
+            "category": [{ "coding": [{ "code": "vital-signs" }] }],
+            "code": { "coding": [{ "system": LOINC_SYSTEM, "code": loinc }] },
+            "effectiveDateTime": date,
+        });
+        observation.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        from_value(observation).unwrap()
+    }
+
+    fn quantity(value: f64, code: &str) -> Value {
+        json!({ "valueQuantity": { "value": value, "system": "http://unitsofmeasure.org", "code": code } })
+    }
+
+    fn component(loinc: &str, value: f64) -> Value {
+        json!({
+            "code": { "coding": [{ "system": LOINC_SYSTEM, "code": loinc }] },
+            "valueQuantity": { "value": value, "system": "http://unitsofmeasure.org", "code": "mm[Hg]" }
+        })
+    }
+
+    #[test]
+    fn reads_blood_pressure_panel_components() {
+        let panel = observation(BLOOD_PRESSURE_PANEL, "2024-03-01T09:00:00Z", json!({
+            "component": [component(SYSTOLIC, 128.4), component(DIASTOLIC, 82.0)]
+        }));
+
+        let vitals = group_vital_signs(vec![panel]);
+        assert_eq!(vitals.len(), 1);
+        assert_eq!(vitals[0].date, "2024-03-01");
+        assert_eq!((vitals[0].systolic, vitals[0].diastolic), (Some(128), Some(82)));
+        assert_eq!(vitals[0].blood_pressure.as_deref(), Some("128/82"));
+    }
+
+    #[test]
+    fn pairs_systolic_and_diastolic_sent_separately() {
+        let vitals = group_vital_signs(vec![
+            observation(SYSTOLIC, "2024-03-01T09:00:00Z", quantity(135.0, "mm[Hg]")),
+            observation(DIASTOLIC, "2024-03-01T09:00:00Z", quantity(88.0, "mm[Hg]")),
+        ]);
+        assert_eq!(vitals[0].blood_pressure.as_deref(), Some("135/88"));
+    }
+
+    #[test]
+    fn leaves_missing_components_empty() {
+        let panel = observation(BLOOD_PRESSURE_PANEL, "2024-03-01", json!({
+            "component": [component(SYSTOLIC, 120.0), { "code": { "coding": [{ "system": LOINC_SYSTEM, "code": DIASTOLIC }] } }]
+        }));
+
+        let vitals = group_vital_signs(vec![panel]);
+        assert_eq!(vitals[0].systolic, Some(120));
+        assert_eq!(vitals[0].diastolic, None);
+        assert_eq!(vitals[0].blood_pressure, None);
+        assert_eq!((vitals[0].heart_rate, vitals[0].temperature), (None, None));
+    }
+
+    #[test]
+    fn converts_celsius_to_fahrenheit() {
+        let vitals = group_vital_signs(vec![
+            observation(BODY_TEMPERATURE, "2024-03-01", quantity(37.0, "Cel")),
+            observation(BODY_TEMPERATURE, "2024-03-02", quantity(100.4, "[degF]")),
+        ]);
+        assert_eq!(vitals[0].temperature, Some(98.6));
+        assert_eq!(vitals[1].temperature, Some(100.4));
+    }
+
+    #[test]
+    fn merges_readings_of_the_same_day() {
+        let vitals = group_vital_signs(vec![
+            observation(HEART_RATE, "2024-03-01T18:00:00Z", quantity(88.0, "/min")),
+            observation(HEART_RATE, "2024-03-01T08:00:00Z", quantity(72.0, "/min")),
+            observation(RESPIRATORY_RATE, "2024-03-01T08:00:00Z", quantity(16.0, "/min")),
+            observation(OXYGEN_SATURATION[1], "2024-03-01T08:00:00Z", quantity(97.0, "%")),
+            observation(HEART_RATE, "2024-02-28", quantity(70.0, "/min")),
+        ]);
+
+        assert_eq!(vitals.iter().map(|v| v.date.as_str()).collect::<Vec<_>>(), ["2024-02-28", "2024-03-01"]);
+        // The latest heart rate of the day wins
+        assert_eq!(vitals[1].heart_rate, Some(88));
+        assert_eq!(vitals[1].respiratory_rate, Some(16));
+        assert_eq!(vitals[1].oxygen_saturation, Some(97));
+    }
+
+    #[test]
+    fn skips_observations_entered_in_error() {
+        let vitals = group_vital_signs(vec![
+            observation(HEART_RATE, "2024-03-01T08:00:00Z", quantity(72.0, "/min")),
+            observation(HEART_RATE, "2024-03-01T09:00:00Z", json!({
+                "status": "entered-in-error",
+                "valueQuantity": { "value": 240.0, "code": "/min" }
+            })),
+            observation(HEART_RATE, "2024-03-02", json!({
+                "status": "cancelled",
+                "valueQuantity": { "value": 60.0, "code": "/min" }
+            })),
+        ]);
+        assert_eq!(vitals.len(), 1);
+        assert_eq!(vitals[0].heart_rate, Some(72));
+    }
+}
@@ -0,0 +1,286 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::fhir::{Coding, Resource};
+
+/// Response of a CDS service call
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CdsResponse {
+    pub cards: Vec<Card>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub system_actions: Vec<SystemAction>,
+}
+
+impl CdsResponse {
+    pub fn new(cards: Vec<Card>) -> Self {
+        Self { cards, system_actions: vec![] }
+    }
+
+    pub fn with_system_actions(mut self, system_actions: Vec<SystemAction>) -> Self {
+        self.system_actions = system_actions;
+        self
+    }
+
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_else(|_| serde_json::json!({ "cards": [] }))
+    }
+
+    pub fn to_json(&self) -> String {
+        self.to_value().to_string()
+    }
+}
+
+/// Urgency of a card
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Indicator {
+    #[default]
+    Info,
+    Warning,
+    Critical,
+}
+
+/// Where the card's content comes from
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Source {
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub topic: Option<Coding>,
+}
+
+impl Source {
+    pub fn new(label: &str) -> Self {
+        Self { label: label.to_string(), ..Default::default() }
+    }
+}
+
+/// How many suggestions of a card the user may accept
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SelectionBehavior {
+    AtMostOne,
+    Any,
+}
+
+/// Kind of change an action makes to the EHR
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ActionType {
+    Create,
+    Update,
+    Delete,
+}
+
+/// Change proposed by a suggestion or applied as a system action
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Action {
+    #[serde(rename = "type")]
+    pub action_type: ActionType,
+    pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource: Option<Resource>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_id: Option<Vec<String>>,
+}
+
+impl Action {
+    pub fn create(description: &str, resource: Resource) -> Self {
+        Self {
+            action_type: ActionType::Create,
+            description: description.to_string(),
+            resource: Some(resource),
+            resource_id: None,
+        }
+    }
+
+    pub fn update(description: &str, resource: Resource) -> Self {
+        Self {
+            action_type: ActionType::Update,
+            description: description.to_string(),
+            resource: Some(resource),
+            resource_id: None,
+        }
+    }
+
+    /// Delete the draft orders with the given `Type/id` references
+    pub fn delete(description: &str, resource_id: Vec<String>) -> Self {
+        Self {
+            action_type: ActionType::Delete,
+            description: description.to_string(),
+            resource: None,
+            resource_id: Some(resource_id),
+        }
+    }
+}
+
+/// Action applied by the EHR without user interaction
+pub type SystemAction = Action;
+
+/// Set of actions the user can accept together
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Suggestion {
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_recommended: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<Action>,
+}
+
+impl Suggestion {
+    pub fn new(label: &str, uuid: &str, is_recommended: bool, actions: Vec<Action>) -> Self {
+        Self {
+            label: label.to_string(),
+            uuid: Some(uuid.to_string()),
+            is_recommended: Some(is_recommended),
+            actions,
+        }
+    }
+}
+
+/// Whether a link opens a plain page or launches a SMART app
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkType {
+    Absolute,
+    Smart,
+}
+
+/// Link shown on a card
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Link {
+    pub label: String,
+    pub url: String,
+    #[serde(rename = "type")]
+    pub link_type: LinkType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_context: Option<String>,
+}
+
+impl Link {
+    pub fn absolute(label: &str, url: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            url: url.to_string(),
+            link_type: LinkType::Absolute,
+            app_context: None,
+        }
+    }
+
+    pub fn smart(label: &str, url: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            url: url.to_string(),
+            link_type: LinkType::Smart,
+            app_context: None,
+        }
+    }
+
+    pub fn with_app_context(mut self, app_context: &str) -> Self {
+        self.app_context = Some(app_context.to_string());
+        self
+    }
+}
+
+/// Card returned to the EHR
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Card {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    pub summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub indicator: Indicator,
+    pub source: Source,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<Suggestion>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selection_behavior: Option<SelectionBehavior>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub override_reasons: Vec<Coding>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<Link>,
+}
+
+impl Card {
+    pub fn new(summary: &str, indicator: Indicator, source: Source) -> Self {
+        Self {
+            summary: summary.to_string(),
+            indicator,
+            source,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_uuid(mut self, uuid: &str) -> Self {
+        self.uuid = Some(uuid.to_string());
+        self
+    }
+
+    pub fn with_detail(mut self, detail: &str) -> Self {
+        self.detail = Some(detail.to_string());
+        self
+    }
+
+    pub fn with_suggestions(mut self, suggestions: Vec<Suggestion>, selection_behavior: SelectionBehavior) -> Self {
+        self.suggestions = suggestions;
+        self.selection_behavior = Some(selection_behavior);
+        self
+    }
+
+    pub fn with_override_reasons(mut self, override_reasons: Vec<Coding>) -> Self {
+        self.override_reasons = override_reasons;
+        self
+    }
+
+    pub fn with_links(mut self, links: Vec<Link>) -> Self {
+        self.links = links;
+        self
+    }
+
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serializes_card_with_suggestions() {
+        let card = Card::new("Duplicate therapy", Indicator::Warning, Source::new("MediCompass"))
+            .with_uuid("card-1")
+            .with_suggestions(
+                vec![Suggestion::new(
+                    "Remove draft order",
+                    "suggestion-1",
+                    true,
+                    vec![Action::delete("Remove the draft", vec!["MedicationRequest/1".to_string()])],
+                )],
+                SelectionBehavior::AtMostOne,
+            )
+            .with_links(vec![Link::smart("Open app", "https://app.example.org/launch").with_app_context("abc")]);
+
+        let value = CdsResponse::new(vec![card]).to_value();
+
+        assert_eq!(value["cards"][0]["indicator"], "warning");
+        assert_eq!(value["cards"][0]["selectionBehavior"], "at-most-one");
+        assert_eq!(value["cards"][0]["suggestions"][0]["actions"][0]["type"], "delete");
+        assert_eq!(value["cards"][0]["suggestions"][0]["actions"][0]["resourceId"], json!(["MedicationRequest/1"]));
+        assert_eq!(value["cards"][0]["links"][0]["type"], "smart");
+        assert_eq!(value["cards"][0]["links"][0]["appContext"], "abc");
+        assert!(value.get("systemActions").is_none());
+    }
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Service advertised by the discovery endpoint
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceDescriptor {
+    pub hook: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub description: String,
+    pub id: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prefetch: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage_requirements: Option<String>,
+}
+
+/// Response of `GET /cds-services`
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Discovery {
+    pub services: Vec<ServiceDescriptor>,
+}
+
+impl Discovery {
+    pub fn new(services: Vec<ServiceDescriptor>) -> Self {
+        Self { services }
+    }
+
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+}
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use scrab_fhir::FhirResource;
+
+pub use scrab_fhir::Coding;
+
+/// Any FHIR resource, keeping every element besides `resourceType` and `id` as JSON
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Resource {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(flatten)]
+    pub data: Map<String, Value>,
+}
+
+impl Resource {
+    /// Element of the resource by its FHIR name
+    pub fn get(&self, element: &str) -> Option<&Value> {
+        self.data.get(element)
+    }
+
+    /// Relative reference `Type/id` to this resource
+    pub fn reference(&self) -> Option<String> {
+        self.id.as_ref().map(|id| format!("{}/{}", self.resource_type, id))
+    }
+
+    /// Typed R4 model of this resource, None when it has another type or does not parse
+    pub fn to_typed<T: FhirResource>(&self) -> Option<T> {
+        if self.resource_type != T::RESOURCE_TYPE {
+            return None;
+        }
+        serde_json::to_value(self).ok()
+            .and_then(|value| serde_json::from_value(value).ok())
+    }
+}
+
+/// Link within a bundle
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct BundleLink {
+    pub relation: String,
+    pub url: String,
+}
+
+/// Entry in a bundle
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub full_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource: Option<Resource>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<Value>,
+}
+
+/// Bundle of resources (e.g., a search result)
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Bundle {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "type")]
+    pub bundle_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub link: Vec<BundleLink>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entry: Vec<BundleEntry>,
+}
+
+impl Default for Bundle {
+    fn default() -> Self {
+        Self {
+            resource_type: "Bundle".to_string(),
+            id: None,
+            bundle_type: "collection".to_string(),
+            total: None,
+            link: vec![],
+            entry: vec![],
+        }
+    }
+}
+
+impl Bundle {
+    /// Resources of all entries
+    pub fn resources(&self) -> impl Iterator<Item = &Resource> {
+        self.entry.iter().filter_map(|entry| entry.resource.as_ref())
+    }
+
+    /// Resources of all entries with the given type
+    pub fn resources_of<'a>(&'a self, resource_type: &'a str) -> impl Iterator<Item = &'a Resource> {
+        self.resources().filter(move |r| r.resource_type == resource_type)
+    }
+
+    /// Typed R4 models of all entries of type `T`, skipping those that do not parse
+    pub fn typed<'a, T: FhirResource + 'a>(&'a self) -> impl Iterator<Item = T> + 'a {
+        self.resources_of(T::RESOURCE_TYPE).filter_map(Resource::to_typed)
+    }
+}
+
+/// Value of a prefetch key, either a search Bundle or a single resource
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum PrefetchValue {
+    Bundle(Bundle),
+    Resource(Resource),
+}
+
+impl<'de> Deserialize<'de> for PrefetchValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        let is_bundle = value.get("resourceType").and_then(|r| r.as_str()) == Some("Bundle");
+
+        if is_bundle {
+            serde_json::from_value(value).map(PrefetchValue::Bundle)
+        } else {
+            serde_json::from_value(value).map(PrefetchValue::Resource)
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
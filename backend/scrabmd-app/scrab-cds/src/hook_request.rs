@@ -0,0 +1,283 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use crate::fhir::{Bundle, PrefetchValue, Resource};
+
+/// Access token the EHR grants to the CDS service
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct FhirAuthorization {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: Option<u64>,
+    pub scope: Option<String>,
+    pub subject: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patient: Option<String>,
+}
+
+/// Context of the `patient-view` hook
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PatientViewContext {
+    pub user_id: String,
+    pub patient_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encounter_id: Option<String>,
+}
+
+/// Context of the `order-select` hook
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderSelectContext {
+    pub user_id: String,
+    pub patient_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encounter_id: Option<String>,
+    #[serde(default)]
+    pub selections: Vec<String>,
+    pub draft_orders: Bundle,
+}
+
+/// Context of the `order-sign` hook
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderSignContext {
+    pub user_id: String,
+    pub patient_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encounter_id: Option<String>,
+    pub draft_orders: Bundle,
+}
+
+/// Hook-specific context, selected by the `hook` field of the request
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum HookContext {
+    PatientView(PatientViewContext),
+    OrderSelect(OrderSelectContext),
+    OrderSign(OrderSignContext),
+    Other(Value),
+}
+
+impl HookContext {
+    fn parse(hook: &str, context: Value) -> Result<Self, serde_json::Error> {
+        let parsed = match hook {
+            "patient-view" => HookContext::PatientView(serde_json::from_value(context)?),
+            "order-select" => HookContext::OrderSelect(serde_json::from_value(context)?),
+            "order-sign" => HookContext::OrderSign(serde_json::from_value(context)?),
+            _ => HookContext::Other(context),
+        };
+        Ok(parsed)
+    }
+
+    pub fn patient_id(&self) -> Option<&str> {
+        match self {
+            HookContext::PatientView(c) => Some(&c.patient_id),
+            HookContext::OrderSelect(c) => Some(&c.patient_id),
+            HookContext::OrderSign(c) => Some(&c.patient_id),
+            HookContext::Other(v) => v.get("patientId").and_then(|p| p.as_str()),
+        }
+    }
+
+    pub fn user_id(&self) -> Option<&str> {
+        match self {
+            HookContext::PatientView(c) => Some(&c.user_id),
+            HookContext::OrderSelect(c) => Some(&c.user_id),
+            HookContext::OrderSign(c) => Some(&c.user_id),
+            HookContext::Other(v) => v.get("userId").and_then(|u| u.as_str()),
+        }
+    }
+
+    pub fn encounter_id(&self) -> Option<&str> {
+        match self {
+            HookContext::PatientView(c) => c.encounter_id.as_deref(),
+            HookContext::OrderSelect(c) => c.encounter_id.as_deref(),
+            HookContext::OrderSign(c) => c.encounter_id.as_deref(),
+            HookContext::Other(v) => v.get("encounterId").and_then(|e| e.as_str()),
+        }
+    }
+
+    pub fn draft_orders(&self) -> Option<&Bundle> {
+        match self {
+            HookContext::OrderSelect(c) => Some(&c.draft_orders),
+            HookContext::OrderSign(c) => Some(&c.draft_orders),
+            _ => None,
+        }
+    }
+
+    /// Context as JSON, used to expand `{{context.*}}` prefetch tokens
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+}
+
+/// Prefetched data keyed by the names declared in the discovery response.
+/// A key the EHR sent as `null` is kept as `None`.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct Prefetch(pub BTreeMap<String, Option<PrefetchValue>>);
+
+impl Prefetch {
+    pub fn get(&self, key: &str) -> Option<&PrefetchValue> {
+        self.0.get(key).and_then(|value| value.as_ref())
+    }
+
+    pub fn bundle(&self, key: &str) -> Option<&Bundle> {
+        match self.get(key) {
+            Some(PrefetchValue::Bundle(bundle)) => Some(bundle),
+            _ => None,
+        }
+    }
+
+    pub fn resource(&self, key: &str) -> Option<&Resource> {
+        match self.get(key) {
+            Some(PrefetchValue::Resource(resource)) => Some(resource),
+            _ => None,
+        }
+    }
+
+    /// True when the key was not sent or was sent as `null`
+    pub fn is_missing(&self, key: &str) -> bool {
+        self.get(key).is_none()
+    }
+
+    pub fn insert(&mut self, key: &str, value: PrefetchValue) {
+        self.0.insert(key.to_string(), Some(value));
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RawHookRequest {
+    hook: String,
+    hook_instance: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fhir_server: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fhir_authorization: Option<FhirAuthorization>,
+    context: Value,
+    #[serde(default)]
+    prefetch: Prefetch,
+}
+
+/// Body of a CDS Hooks service call
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(try_from = "RawHookRequest", into = "RawHookRequest")]
+pub struct HookRequest {
+    pub hook: String,
+    pub hook_instance: String,
+    pub fhir_server: Option<String>,
+    pub fhir_authorization: Option<FhirAuthorization>,
+    pub context: HookContext,
+    pub prefetch: Prefetch,
+}
+
+impl TryFrom<RawHookRequest> for HookRequest {
+    type Error = serde_json::Error;
+
+    fn try_from(raw: RawHookRequest) -> Result<Self, Self::Error> {
+        Ok(Self {
+            context: HookContext::parse(&raw.hook, raw.context)?,
+            hook: raw.hook,
+            hook_instance: raw.hook_instance,
+            fhir_server: raw.fhir_server,
+            fhir_authorization: raw.fhir_authorization,
+            prefetch: raw.prefetch,
+        })
+    }
+}
+
+impl From<HookRequest> for RawHookRequest {
+    fn from(request: HookRequest) -> Self {
+        Self {
+            context: request.context.to_value(),
+            hook: request.hook,
+            hook_instance: request.hook_instance,
+            fhir_server: request.fhir_server,
+            fhir_authorization: request.fhir_authorization,
+            prefetch: request.prefetch,
+        }
+    }
+}
+
+impl HookRequest {
+    pub fn from_json(body: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(body)
+    }
+
+    /// Access token from `fhirAuthorization`, if the EHR granted one
+    pub fn access_token(&self) -> Option<&str> {
+        self.fhir_authorization.as_ref().map(|auth| auth.access_token.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const PATIENT_VIEW: &str = include_str!("../../medical-app/x-events/cds-event-001.json");
+
+    #[test]
+    fn parses_patient_view_sample() {
+        let request = HookRequest::from_json(PATIENT_VIEW).unwrap();
+
+        assert_eq!(request.hook, "patient-view");
+        assert!(matches!(request.context, HookContext::PatientView(_)));
+        assert!(request.context.patient_id().is_some());
+        assert_eq!(request.prefetch.resource("patient").unwrap().resource_type, "Patient");
+        assert!(request.prefetch.bundle("conditions").is_some());
+        assert!(request.prefetch.is_missing("medications"));
+    }
+
+    #[test]
+    fn parses_order_sign_draft_orders() {
+        let body = json!({
+            "hook": "order-sign",
+            "hookInstance": "d1577c69-dfbe-44ad-ba6d-3e05e953b2ea",
+            "fhirServer": "https://ehr.example.org/fhir",
+            "context": {
+                "userId": "Practitioner/example",
+                "patientId": "1288992",
+                "draftOrders": {
+                    "resourceType": "Bundle",
+                    "type": "collection",
+                    "entry": [{
+                        "resource": {
+                            "resourceType": "MedicationRequest",
+                            "id": "smart-MedicationRequest-103",
+                            "status": "draft",
+                            "medicationCodeableConcept": { "text": "Ibuprofen 400 MG" }
+                        }
+                    }]
+                }
+            },
+            "prefetch": { "patient": null }
+        });
+
+        let request: HookRequest = serde_json::from_value(body).unwrap();
+        let drafts = request.context.draft_orders().unwrap();
+        let draft = drafts.resources_of("MedicationRequest").next().unwrap();
+
+        assert_eq!(draft.reference().as_deref(), Some("MedicationRequest/smart-MedicationRequest-103"));
+        assert_eq!(request.context.patient_id(), Some("1288992"));
+        assert!(request.prefetch.is_missing("patient"));
+    }
+
+    #[test]
+    fn rejects_context_missing_required_fields() {
+        let body = json!({
+            "hook": "order-select",
+            "hookInstance": "1",
+            "context": { "userId": "Practitioner/example", "patientId": "1" }
+        });
+        assert!(serde_json::from_value::<HookRequest>(body).is_err());
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let request = HookRequest::from_json(PATIENT_VIEW).unwrap();
+        let serialized = serde_json::to_string(&request).unwrap();
+        assert_eq!(HookRequest::from_json(&serialized).unwrap(), request);
+    }
+}
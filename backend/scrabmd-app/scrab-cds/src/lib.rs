@@ -0,0 +1,20 @@
+//! CDS Hooks 2.0 request, response and discovery model shared by the
+//! medical-app and medical-smartapp lambdas.
+
+pub mod fhir;
+pub mod hook_request;
+pub mod card;
+pub mod discovery;
+pub mod prefetch_template;
+
+pub use fhir::{Bundle, BundleEntry, Coding, PrefetchValue, Resource};
+pub use hook_request::{
+    FhirAuthorization, HookContext, HookRequest, OrderSelectContext,
+    OrderSignContext, PatientViewContext, Prefetch,
+};
+pub use card::{
+    Action, ActionType, Card, CdsResponse, Indicator, Link, LinkType,
+    SelectionBehavior, Source, Suggestion, SystemAction,
+};
+pub use discovery::{Discovery, ServiceDescriptor};
+pub use prefetch_template::{PrefetchTemplate, TemplateError};
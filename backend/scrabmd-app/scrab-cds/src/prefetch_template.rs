@@ -0,0 +1,376 @@
+use chrono::{Days, Months, NaiveDate};
+use serde_json::Value;
+use std::fmt;
+use thiserror::Error;
+use url::form_urlencoded;
+use crate::hook_request::HookContext;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum TemplateError {
+    #[error("Unterminated token in prefetch template: {0}")]
+    Unterminated(String),
+
+    #[error("Unknown prefetch token: {0}")]
+    UnknownToken(String),
+
+    #[error("Prefetch token cannot be resolved: {0}")]
+    Unresolved(String),
+}
+
+/// Unit of a `{{today - N unit}}` offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateUnit {
+    Days,
+    Weeks,
+    Months,
+    Years,
+}
+
+/// Token of a prefetch template
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    /// `{{context.patientId}}` or `{{context.draftOrders.MedicationRequest.id}}`
+    Context(Vec<String>),
+    /// `{{userPractitionerId}}`, `{{userPractitionerRoleId}}`, `{{userPatientId}}`,
+    /// `{{userRelatedPersonId}}`, keeping the resource type the userId must have
+    User(&'static str),
+    /// `{{today}}`, optionally shifted like `{{today - 90 days}}`
+    Today { offset: i64, unit: DateUnit },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Token(Token),
+}
+
+/// Parsed CDS Hooks prefetch template, e.g.
+/// `Observation?patient={{context.patientId}}&date=ge{{today - 1 year}}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrefetchTemplate {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+const USER_TOKENS: &[(&str, &str)] = &[
+    ("userPractitionerId", "Practitioner"),
+    ("userPractitionerRoleId", "PractitionerRole"),
+    ("userPatientId", "Patient"),
+    ("userRelatedPersonId", "RelatedPerson"),
+];
+
+fn parse_unit(unit: &str) -> Option<DateUnit> {
+    match unit {
+        "day" | "days" => Some(DateUnit::Days),
+        "week" | "weeks" => Some(DateUnit::Weeks),
+        "month" | "months" => Some(DateUnit::Months),
+        "year" | "years" => Some(DateUnit::Years),
+        _ => None,
+    }
+}
+
+fn parse_today(token: &str) -> Option<Token> {
+    let rest = token.strip_prefix("today")?.trim();
+    if rest.is_empty() {
+        return Some(Token::Today { offset: 0, unit: DateUnit::Days });
+    }
+
+    let (sign, rest) = match rest.chars().next()? {
+        '+' => (1, &rest[1..]),
+        '-' => (-1, &rest[1..]),
+        _ => return None,
+    };
+
+    let mut parts = rest.split_whitespace();
+    let amount: i64 = parts.next()?.parse().ok()?;
+    let unit = parse_unit(parts.next()?)?;
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(Token::Today { offset: sign * amount, unit })
+}
+
+fn parse_token(token: &str) -> Result<Token, TemplateError> {
+    if let Some(path) = token.strip_prefix("context.") {
+        let path: Vec<String> = path.split('.').map(|p| p.to_string()).collect();
+        if path.iter().any(|p| p.is_empty()) {
+            return Err(TemplateError::UnknownToken(token.to_string()));
+        }
+        return Ok(Token::Context(path));
+    }
+
+    if let Some((_, resource_type)) = USER_TOKENS.iter().find(|(name, _)| *name == token) {
+        return Ok(Token::User(resource_type));
+    }
+
+    parse_today(token).ok_or_else(|| TemplateError::UnknownToken(token.to_string()))
+}
+
+fn shift_date(today: NaiveDate, offset: i64, unit: DateUnit) -> Option<NaiveDate> {
+    let amount = offset.unsigned_abs();
+    match unit {
+        DateUnit::Days | DateUnit::Weeks => {
+            let days = if unit == DateUnit::Weeks { amount.checked_mul(7)? } else { amount };
+            if offset < 0 {
+                today.checked_sub_days(Days::new(days))
+            } else {
+                today.checked_add_days(Days::new(days))
+            }
+        }
+        DateUnit::Months | DateUnit::Years => {
+            let months = if unit == DateUnit::Years { amount.checked_mul(12)? } else { amount };
+            let months = Months::new(u32::try_from(months).ok()?);
+            if offset < 0 {
+                today.checked_sub_months(months)
+            } else {
+                today.checked_add_months(months)
+            }
+        }
+    }
+}
+
+/// Percent-encode a substituted value so it cannot alter the FHIR query
+fn encode(value: &str) -> String {
+    form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+/// `context.draftOrders.MedicationRequest.id` selects the `id` of every
+/// MedicationRequest in the draftOrders Bundle, each encoded and joined with
+/// commas
+fn resolve_context(path: &[String], context: &Value) -> Option<String> {
+    let (field, rest) = path.split_first()?;
+    let value = context.get(field)?;
+
+    match rest {
+        [] => match value {
+            Value::String(s) => Some(encode(s)),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        },
+        [resource_type, element] => {
+            let values: Vec<String> = value.get("entry")?
+                .as_array()?
+                .iter()
+                .filter_map(|entry| entry.get("resource"))
+                .filter(|r| r.get("resourceType").and_then(|t| t.as_str()) == Some(resource_type))
+                .filter_map(|r| r.get(element)?.as_str())
+                .map(encode)
+                .collect();
+
+            if values.is_empty() { None } else { Some(values.join(",")) }
+        }
+        _ => None,
+    }
+}
+
+/// Encoded id of the current user when `context.userId` is a reference to the
+/// given type
+fn resolve_user(resource_type: &str, context: &HookContext) -> Option<String> {
+    let (user_type, id) = context.user_id()?.rsplit_once('/')?;
+    let user_type = user_type.rsplit('/').next()?;
+
+    (user_type == resource_type && !id.is_empty()).then(|| encode(id))
+}
+
+impl PrefetchTemplate {
+    pub fn parse(template: &str) -> Result<Self, TemplateError> {
+        let mut segments = Vec::new();
+        let mut rest = template;
+
+        while let Some(start) = rest.find("{{") {
+            let end = rest[start..].find("}}")
+                .ok_or_else(|| TemplateError::Unterminated(template.to_string()))? + start;
+
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_string()));
+            }
+            segments.push(Segment::Token(parse_token(rest[start + 2..end].trim())?));
+            rest = &rest[end + 2..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+
+        Ok(Self { source: template.to_string(), segments })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn tokens(&self) -> impl Iterator<Item = &Token> {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Token(token) => Some(token),
+            Segment::Literal(_) => None,
+        })
+    }
+
+    /// Expand every token against the hook context, percent-encoding the
+    /// substituted ids. `today` is the date used for `{{today}}`. Fails when any token cannot be resolved, in which case
+    /// the prefetch must not be fetched.
+    pub fn expand(&self, context: &HookContext, today: NaiveDate) -> Result<String, TemplateError> {
+        let context_value = context.to_value();
+        let mut expanded = String::new();
+
+        for segment in &self.segments {
+            let value = match segment {
+                Segment::Literal(literal) => Some(literal.clone()),
+                Segment::Token(Token::Context(path)) => resolve_context(path, &context_value),
+                Segment::Token(Token::User(resource_type)) => resolve_user(resource_type, context),
+                Segment::Token(Token::Today { offset, unit }) => {
+                    shift_date(today, *offset, *unit).map(|date| date.format("%Y-%m-%d").to_string())
+                }
+            };
+
+            match value {
+                Some(value) => expanded.push_str(&value),
+                None => return Err(TemplateError::Unresolved(self.source.clone())),
+            }
+        }
+
+        Ok(expanded)
+    }
+}
+
+impl fmt::Display for PrefetchTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, 31).unwrap()
+    }
+
+    fn patient_view(user_id: &str) -> HookContext {
+        HookContext::PatientView(serde_json::from_value(json!({
+            "userId": user_id,
+            "patientId": "1288992",
+            "encounterId": "89284"
+        })).unwrap())
+    }
+
+    fn expand(template: &str, context: &HookContext) -> Result<String, TemplateError> {
+        PrefetchTemplate::parse(template)?.expand(context, today())
+    }
+
+    #[test]
+    fn expands_context_fields() {
+        let context = patient_view("Practitioner/example");
+        assert_eq!(
+            expand("Encounter?patient={{context.patientId}}&_id={{ context.encounterId }}", &context).unwrap(),
+            "Encounter?patient=1288992&_id=89284",
+        );
+        assert!(expand("Patient/{{context.missing}}", &context).is_err());
+    }
+
+    #[test]
+    fn expands_context_bundle_elements() {
+        let context = HookContext::OrderSign(serde_json::from_value(json!({
+            "userId": "Practitioner/example",
+            "patientId": "1288992",
+            "draftOrders": {
+                "resourceType": "Bundle",
+                "type": "collection",
+                "entry": [
+                    { "resource": { "resourceType": "MedicationRequest", "id": "a" } },
+                    { "resource": { "resourceType": "ServiceRequest", "id": "b" } },
+                    { "resource": { "resourceType": "MedicationRequest", "id": "c" } }
+                ]
+            }
+        })).unwrap());
+
+        assert_eq!(
+            expand("MedicationRequest?_id={{context.draftOrders.MedicationRequest.id}}", &context).unwrap(),
+            "MedicationRequest?_id=a,c",
+        );
+    }
+
+    #[test]
+    fn encodes_substituted_ids() {
+        let context = HookContext::OrderSign(serde_json::from_value(json!({
+            "userId": "Practitioner/dr smith#1",
+            "patientId": "12&_id=other",
+            "draftOrders": {
+                "resourceType": "Bundle",
+                "type": "collection",
+                "entry": [
+                    { "resource": { "resourceType": "MedicationRequest", "id": "a,b" } },
+                    { "resource": { "resourceType": "MedicationRequest", "id": "c" } }
+                ]
+            }
+        })).unwrap());
+
+        assert_eq!(
+            expand("Encounter?patient={{context.patientId}}&status=finished", &context).unwrap(),
+            "Encounter?patient=12%26_id%3Dother&status=finished",
+        );
+        assert_eq!(
+            expand("MedicationRequest?_id={{context.draftOrders.MedicationRequest.id}}", &context).unwrap(),
+            "MedicationRequest?_id=a%2Cb,c",
+        );
+        assert_eq!(
+            expand("Practitioner/{{userPractitionerId}}", &context).unwrap(),
+            "Practitioner/dr+smith%231",
+        );
+    }
+
+    #[test]
+    fn expands_user_practitioner_id() {
+        let template = "Practitioner/{{userPractitionerId}}";
+        assert_eq!(expand(template, &patient_view("Practitioner/example")).unwrap(), "Practitioner/example");
+        assert_eq!(
+            expand(template, &patient_view("https://ehr.example.org/fhir/Practitioner/42")).unwrap(),
+            "Practitioner/42",
+        );
+        assert!(expand(template, &patient_view("Patient/1288992")).is_err());
+    }
+
+    #[test]
+    fn expands_user_practitioner_role_id() {
+        let template = "PractitionerRole/{{userPractitionerRoleId}}";
+        assert_eq!(expand(template, &patient_view("PractitionerRole/r1")).unwrap(), "PractitionerRole/r1");
+        assert!(expand(template, &patient_view("Practitioner/example")).is_err());
+    }
+
+    #[test]
+    fn expands_user_patient_id() {
+        let template = "Patient/{{userPatientId}}";
+        assert_eq!(expand(template, &patient_view("Patient/1288992")).unwrap(), "Patient/1288992");
+        assert!(expand(template, &patient_view("RelatedPerson/p1")).is_err());
+    }
+
+    #[test]
+    fn expands_user_related_person_id() {
+        let template = "RelatedPerson/{{userRelatedPersonId}}";
+        assert_eq!(expand(template, &patient_view("RelatedPerson/p1")).unwrap(), "RelatedPerson/p1");
+        assert!(expand(template, &patient_view("Patient/1288992")).is_err());
+    }
+
+    #[test]
+    fn expands_today_with_date_arithmetic() {
+        let context = patient_view("Practitioner/example");
+        assert_eq!(expand("date=eq{{today}}", &context).unwrap(), "date=eq2025-03-31");
+        assert_eq!(expand("date=ge{{today - 90 days}}", &context).unwrap(), "date=ge2024-12-31");
+        assert_eq!(expand("date=ge{{today-2 weeks}}", &context).unwrap(), "date=ge2025-03-17");
+        assert_eq!(expand("date=ge{{today - 1 month}}", &context).unwrap(), "date=ge2025-02-28");
+        assert_eq!(expand("date=le{{today + 1 year}}", &context).unwrap(), "date=le2026-03-31");
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        assert!(matches!(PrefetchTemplate::parse("Patient/{{context.patientId"), Err(TemplateError::Unterminated(_))));
+        assert!(matches!(PrefetchTemplate::parse("Patient/{{patientId}}"), Err(TemplateError::UnknownToken(_))));
+        assert!(matches!(PrefetchTemplate::parse("date=ge{{today - 3 fortnights}}"), Err(TemplateError::UnknownToken(_))));
+        assert_eq!(
+            PrefetchTemplate::parse("Patient/{{context.patientId}}").unwrap().to_string(),
+            "Patient/{{context.patientId}}",
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use crate::datatypes::{CodeableConcept, Identifier, Meta, Period, Reference};
+use crate::FhirResource;
+
+/// Patient, practitioner or location taking part in an Appointment
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AppointmentParticipant {
+    #[serde(rename = "type", default, skip_serializing_if = "Vec::is_empty")]
+    pub participant_type: Vec<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actor: Option<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required: Option<String>,
+    #[serde(default)]
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<Period>,
+}
+
+/// Booking of a healthcare event for a specific date and time
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Appointment {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    #[serde(default)]
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub service_category: Vec<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub service_type: Vec<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub specialty: Vec<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub appointment_type: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reason_code: Vec<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minutes_duration: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub participant: Vec<AppointmentParticipant>,
+}
+
+impl FhirResource for Appointment {
+    const RESOURCE_TYPE: &'static str = "Appointment";
+}
+
+impl Appointment {
+    /// Actors whose reference points to the given resource type, e.g. `Practitioner`
+    pub fn actors(&self, resource_type: &str) -> impl Iterator<Item = &Reference> {
+        self.participant.iter()
+            .filter_map(|participant| participant.actor.as_ref())
+            .filter(move |actor| {
+                actor.reference_type.as_deref() == Some(resource_type)
+                    || actor.reference.as_deref().is_some_and(|r| {
+                        r.split('/').rev().nth(1) == Some(resource_type)
+                    })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::from_value;
+
+    #[test]
+    fn finds_participants_by_actor_type() {
+        let appointment: Appointment = from_value(json!({
+            "resourceType": "Appointment",
+            "status": "booked",
+            "appointmentType": { "text": "Follow-up" },
+            "start": "2026-11-15T10:00:00-05:00",
+            "participant": [
+                { "actor": { "reference": "Patient/1288992" }, "status": "accepted" },
+                { "actor": { "reference": "Practitioner/pr-1" }, "status": "accepted" },
+                { "actor": { "reference": "https://fhir.example.org/r4/Location/loc-1" }, "status": "accepted" },
+                { "actor": { "type": "Practitioner", "display": "Dr. Sarah Johnson" }, "status": "tentative" }
+            ]
+        })).unwrap();
+
+        let practitioners: Vec<&Reference> = appointment.actors("Practitioner").collect();
+        assert_eq!(practitioners.len(), 2);
+        assert_eq!(practitioners[1].display.as_deref(), Some("Dr. Sarah Johnson"));
+        assert_eq!(appointment.actors("Location").count(), 1);
+        assert_eq!(appointment.start.as_deref(), Some("2026-11-15T10:00:00-05:00"));
+    }
+}
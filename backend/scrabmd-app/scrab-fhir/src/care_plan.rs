@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use crate::datatypes::{Annotation, CodeableConcept, Identifier, Meta, Period, Reference, Timing};
+use crate::FhirResource;
+
+/// `scheduled[x]` of a CarePlan activity
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub enum Scheduled {
+    #[serde(rename = "scheduledTiming")]
+    Timing(Timing),
+    #[serde(rename = "scheduledPeriod")]
+    Period(Period),
+    #[serde(rename = "scheduledString")]
+    String(String),
+}
+
+impl Scheduled {
+    /// Start of the period, or the first event of the timing
+    pub fn start(&self) -> Option<&str> {
+        match self {
+            Scheduled::Timing(timing) => timing.event.first().map(String::as_str)
+                .or_else(|| timing.repeat.as_ref()?.bounds_period.as_ref()?.start.as_deref()),
+            Scheduled::Period(period) => period.start.as_deref(),
+            Scheduled::String(_) => None,
+        }
+    }
+}
+
+/// Planned activity described inline in a CarePlan
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CarePlanActivityDetail {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reason_code: Vec<CodeableConcept>,
+    #[serde(default)]
+    pub status: String,
+    #[serde(flatten)]
+    pub scheduled: Option<Scheduled>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub performer: Vec<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Activity of a CarePlan, described inline or by a reference to a request
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CarePlanActivity {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference: Option<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<CarePlanActivityDetail>,
+}
+
+/// Intended care for the patient, e.g. a diabetes management plan
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CarePlan {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub intent: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub category: Vec<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<Period>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub activity: Vec<CarePlanActivity>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub note: Vec<Annotation>,
+}
+
+impl FhirResource for CarePlan {
+    const RESOURCE_TYPE: &'static str = "CarePlan";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::from_value;
+
+    #[test]
+    fn reads_the_scheduled_start_of_activities() {
+        let plan: CarePlan = from_value(json!({
+            "resourceType": "CarePlan",
+            "status": "active",
+            "intent": "plan",
+            "subject": { "reference": "Patient/1288992" },
+            "activity": [
+                { "detail": { "code": { "text": "Physical therapy" }, "status": "in-progress", "scheduledPeriod": { "start": "2026-02-20" } } },
+                { "detail": { "status": "scheduled", "scheduledString": "Every morning" } }
+            ]
+        })).unwrap();
+
+        let scheduled: Vec<Option<&str>> = plan.activity.iter()
+            .map(|a| a.detail.as_ref().unwrap().scheduled.as_ref().and_then(Scheduled::start))
+            .collect();
+        assert_eq!(scheduled, [Some("2026-02-20"), None]);
+    }
+}
@@ -0,0 +1,97 @@
+//! `[x]` choice elements shared by several resources. Each one is flattened
+//! into its resource, so only the variant present in the JSON is kept.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::datatypes::{Period, Quantity, Range};
+
+/// `effective[x]` of MedicationStatement and Observation
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub enum Effective {
+    #[serde(rename = "effectiveDateTime")]
+    DateTime(String),
+    #[serde(rename = "effectivePeriod")]
+    Period(Period),
+    #[serde(rename = "effectiveInstant")]
+    Instant(String),
+}
+
+impl Effective {
+    /// Date time, or the start of the period
+    pub fn start(&self) -> Option<&str> {
+        match self {
+            Effective::DateTime(date_time) | Effective::Instant(date_time) => Some(date_time),
+            Effective::Period(period) => period.start.as_deref(),
+        }
+    }
+}
+
+impl fmt::Display for Effective {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Effective::DateTime(date_time) | Effective::Instant(date_time) => f.write_str(date_time),
+            Effective::Period(period) => write_period(f, period),
+        }
+    }
+}
+
+/// `onset[x]` of Condition and AllergyIntolerance
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub enum Onset {
+    #[serde(rename = "onsetDateTime")]
+    DateTime(String),
+    #[serde(rename = "onsetAge")]
+    Age(Quantity),
+    #[serde(rename = "onsetPeriod")]
+    Period(Period),
+    #[serde(rename = "onsetRange")]
+    Range(Range),
+    #[serde(rename = "onsetString")]
+    String(String),
+}
+
+impl Onset {
+    /// Date time, or the start of the period
+    pub fn start(&self) -> Option<&str> {
+        match self {
+            Onset::DateTime(date_time) => Some(date_time),
+            Onset::Period(period) => period.start.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// `abatement[x]` of Condition
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub enum Abatement {
+    #[serde(rename = "abatementDateTime")]
+    DateTime(String),
+    #[serde(rename = "abatementAge")]
+    Age(Quantity),
+    #[serde(rename = "abatementPeriod")]
+    Period(Period),
+    #[serde(rename = "abatementRange")]
+    Range(Range),
+    #[serde(rename = "abatementString")]
+    String(String),
+}
+
+impl Abatement {
+    /// Date time, or the start of the period
+    pub fn start(&self) -> Option<&str> {
+        match self {
+            Abatement::DateTime(date_time) => Some(date_time),
+            Abatement::Period(period) => period.start.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn write_period(f: &mut fmt::Formatter<'_>, period: &Period) -> fmt::Result {
+    write!(
+        f,
+        "from {} to {}",
+        period.start.as_deref().unwrap_or("Unknown"),
+        period.end.as_deref().unwrap_or("Unknown"),
+    )
+}
@@ -0,0 +1,169 @@
+use reqwest::header::{ACCEPT, AUTHORIZATION, HeaderMap, HeaderValue};
+use serde_json::Value;
+use std::time::Duration;
+use thiserror::Error;
+use crate::FhirResource;
+
+#[derive(Error, Debug)]
+pub enum FhirError {
+    #[error("FHIR request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("FHIR server returned {status} for {url}")]
+    Status { status: u16, url: String },
+
+    #[error("Invalid FHIR resource: {0}")]
+    InvalidResource(#[from] serde_json::Error),
+
+    #[error("Invalid access token")]
+    InvalidToken,
+
+    #[error("Link outside of the FHIR server: {0}")]
+    ForeignLink(String),
+}
+
+// Time to open a connection to the FHIR server
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Time a request may take unless [`FhirClient::with_timeout`] says otherwise
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Whether an absolute URL is under the base URL of a server
+pub(crate) fn same_server(base_url: &str, url: &str) -> bool {
+    url.strip_prefix(base_url.trim_end_matches('/'))
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?']))
+}
+
+/// Client for a FHIR server, authorized with a bearer token
+#[derive(Debug, Clone)]
+pub struct FhirClient {
+    base_url: String,
+    access_token: String,
+    timeout: Duration,
+    http: reqwest::Client,
+}
+
+impl FhirClient {
+    pub fn new(base_url: &str, access_token: &str) -> Result<Self, FhirError> {
+        let http = reqwest::Client::builder()
+            .use_rustls_tls()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()?;
+
+        Ok(Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            access_token: access_token.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            http,
+        })
+    }
+
+    /// Time each request may take, e.g. the part of a latency budget left for it
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Absolute URL of a query relative to the server base, e.g. `Patient/123`.
+    /// Absolute URLs must point to this server, the bearer token is sent along.
+    pub fn url(&self, query: &str) -> Result<String, FhirError> {
+        if query.starts_with("http://") || query.starts_with("https://") {
+            if !self.is_same_server(query) {
+                return Err(FhirError::ForeignLink(query.to_string()));
+            }
+            return Ok(query.to_string());
+        }
+        Ok(format!("{}/{}", self.base_url, query.trim_start_matches('/')))
+    }
+
+    /// Whether an absolute URL points to this server
+    pub fn is_same_server(&self, url: &str) -> bool {
+        same_server(&self.base_url, url)
+    }
+
+    fn headers(&self) -> Result<HeaderMap, FhirError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static("application/fhir+json"));
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", self.access_token))
+                .map_err(|_| FhirError::InvalidToken)?,
+        );
+        Ok(headers)
+    }
+
+    /// GET a query and return the response body as JSON
+    pub async fn get(&self, query: &str) -> Result<Value, FhirError> {
+        let url = self.url(query)?;
+        let response = self.http.get(&url)
+            .headers(self.headers()?)
+            .timeout(self.timeout)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(FhirError::Status { status: status.as_u16(), url });
+        }
+
+        Ok(response.json::<Value>().await?)
+    }
+
+    /// Read a resource by id, e.g. `client.read::<Patient>("123")`
+    pub async fn read<T: FhirResource>(&self, id: &str) -> Result<T, FhirError> {
+        let value = self.get(&format!("{}/{}", T::RESOURCE_TYPE, id)).await?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scrab_testing::MockServer;
+
+    #[test]
+    fn builds_urls_relative_to_the_base() {
+        let client = FhirClient::new("https://fhir.example.org/r4/", "token").unwrap();
+        assert_eq!(client.base_url(), "https://fhir.example.org/r4");
+        assert_eq!(client.url("Patient/123").unwrap(), "https://fhir.example.org/r4/Patient/123");
+        assert_eq!(client.url("/Observation?patient=123").unwrap(), "https://fhir.example.org/r4/Observation?patient=123");
+        assert_eq!(
+            client.url("https://fhir.example.org/r4/Medication/1").unwrap(),
+            "https://fhir.example.org/r4/Medication/1",
+        );
+        assert!(client.is_same_server("https://fhir.example.org/r4?_getpages=abc"));
+        assert!(client.is_same_server("https://fhir.example.org/r4/Observation?page=2"));
+        assert!(!client.is_same_server("https://fhir.example.org/r4-other/Observation"));
+        assert!(!client.is_same_server("https://evil.example.org/r4?_getpages=abc"));
+    }
+
+    #[tokio::test]
+    async fn never_sends_the_token_to_other_servers() {
+        let client = FhirClient::new("https://fhir.example.org/r4", "token").unwrap();
+        for url in ["https://other.example.org/fhir/Medication/1", "http://fhir.example.org/r4/Medication/1"] {
+            assert!(matches!(client.url(url), Err(FhirError::ForeignLink(_))), "accepted {}", url);
+            assert!(matches!(client.get(url).await, Err(FhirError::ForeignLink(_))), "fetched {}", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_a_server_that_does_not_answer() {
+        let server = MockServer::silent().await;
+
+        let client = FhirClient::new(&server.url("/r4"), "token").unwrap().with_timeout(Duration::from_millis(200));
+        match client.get("Patient/1").await {
+            Err(FhirError::Request(e)) => assert!(e.is_timeout(), "{}", e),
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_tokens_that_are_not_header_values() {
+        let client = FhirClient::new("https://fhir.example.org/r4", "bad\ntoken").unwrap();
+        assert!(matches!(client.headers(), Err(FhirError::InvalidToken)));
+    }
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use crate::choice::{Abatement, Onset};
+use crate::datatypes::{Annotation, CodeableConcept, Identifier, Meta, Reference};
+use crate::FhirResource;
+
+pub const CONDITION_CLINICAL_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/condition-clinical";
+pub const CONDITION_VERIFICATION_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/condition-ver-status";
+pub const CONDITION_CATEGORY_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/condition-category";
+
+/// Problem, diagnosis or other clinical concern
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Condition {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clinical_status: Option<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification_status: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub category: Vec<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub body_site: Vec<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<Reference>,
+    #[serde(flatten)]
+    pub onset: Option<Onset>,
+    #[serde(flatten)]
+    pub abatement: Option<Abatement>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recorded_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub note: Vec<Annotation>,
+}
+
+impl FhirResource for Condition {
+    const RESOURCE_TYPE: &'static str = "Condition";
+}
+
+impl Condition {
+    /// Whether the clinical status is the given condition-clinical code, e.g. `active`
+    pub fn has_clinical_status(&self, code: &str) -> bool {
+        self.clinical_status.as_ref()
+            .is_some_and(|status| status.has_code(CONDITION_CLINICAL_SYSTEM, code))
+    }
+
+    /// Whether the condition is active, including a recurrence or relapse
+    pub fn is_active(&self) -> bool {
+        ["active", "recurrence", "relapse"].iter().any(|code| self.has_clinical_status(code))
+    }
+
+    /// Whether the verification status is the given condition-ver-status code, e.g. `confirmed`
+    pub fn has_verification_status(&self, code: &str) -> bool {
+        self.verification_status.as_ref()
+            .is_some_and(|status| status.has_code(CONDITION_VERIFICATION_SYSTEM, code))
+    }
+
+    /// Whether the condition is in the given category, e.g. `problem-list-item`
+    pub fn has_category(&self, code: &str) -> bool {
+        self.category.iter().any(|category| category.has_code(CONDITION_CATEGORY_SYSTEM, code))
+    }
+}
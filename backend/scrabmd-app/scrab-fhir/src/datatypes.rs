@@ -0,0 +1,377 @@
+use serde::{Deserialize, Serialize};
+
+pub const RXNORM_SYSTEM: &str = "http://www.nlm.nih.gov/research/umls/rxnorm";
+pub const LOINC_SYSTEM: &str = "http://loinc.org";
+pub const SNOMED_SYSTEM: &str = "http://snomed.info/sct";
+pub const ICD10_SYSTEM: &str = "http://hl7.org/fhir/sid/icd-10";
+pub const ICD10_CM_SYSTEM: &str = "http://hl7.org/fhir/sid/icd-10-cm";
+
+/// Coding within a codeable concept
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Coding {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+}
+
+impl Coding {
+    pub fn new(system: &str, code: &str, display: &str) -> Self {
+        Self {
+            system: Some(system.to_string()),
+            code: Some(code.to_string()),
+            display: Some(display.to_string()),
+        }
+    }
+}
+
+/// Concept given by codings and/or text
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct CodeableConcept {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub coding: Vec<Coding>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+impl CodeableConcept {
+    /// Text of the concept, falling back to the first coding display
+    pub fn display(&self) -> Option<&str> {
+        self.text.as_deref()
+            .or_else(|| self.coding.iter().find_map(|c| c.display.as_deref()))
+    }
+
+    /// First code from the given code system
+    pub fn code_in(&self, system: &str) -> Option<&str> {
+        self.coding.iter()
+            .find(|c| c.system.as_deref() == Some(system))
+            .and_then(|c| c.code.as_deref())
+    }
+
+    /// Whether any coding has the given system and code
+    pub fn has_code(&self, system: &str, code: &str) -> bool {
+        self.coding.iter().any(|c| c.system.as_deref() == Some(system) && c.code.as_deref() == Some(code))
+    }
+
+    /// Whether any coding has the given code, whatever its system
+    pub fn has_any_code(&self, code: &str) -> bool {
+        self.coding.iter().any(|c| c.code.as_deref() == Some(code))
+    }
+}
+
+/// Reference to another resource
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Reference {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub reference_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+}
+
+impl Reference {
+    pub fn new(reference: &str) -> Self {
+        Self { reference: Some(reference.to_string()), ..Default::default() }
+    }
+}
+
+/// Time range, both ends optional
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Period {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<String>,
+}
+
+/// Measured amount, e.g. `120 mm[Hg]`
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Quantity {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comparator: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+impl Quantity {
+    /// Value followed by its unit, e.g. `98.6 degF`
+    pub fn display(&self) -> Option<String> {
+        let value = self.value?;
+        let unit = self.unit.as_deref().or(self.code.as_deref());
+        Some(match (self.comparator.as_deref(), unit) {
+            (Some(comparator), Some(unit)) => format!("{}{} {}", comparator, value, unit),
+            (Some(comparator), None) => format!("{}{}", comparator, value),
+            (None, Some(unit)) => format!("{} {}", value, unit),
+            (None, None) => value.to_string(),
+        })
+    }
+
+    /// Value converted to the given UCUM unit, e.g. `[degF]` for a temperature in `Cel`
+    pub fn value_in(&self, unit: &str) -> Option<f64> {
+        let from = self.code.as_deref().or(self.unit.as_deref())?;
+        crate::ucum::convert(self.value?, from, unit)
+    }
+}
+
+/// Range of quantities
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Range {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub low: Option<Quantity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub high: Option<Quantity>,
+}
+
+/// Ratio of two quantities, e.g. a strength of `5 mg / 1 mL`
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Ratio {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub numerator: Option<Quantity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub denominator: Option<Quantity>,
+}
+
+/// Business identifier of a resource
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Identifier {
+    #[serde(rename = "use", skip_serializing_if = "Option::is_none")]
+    pub identifier_use: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+/// Metadata for a resource
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Meta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_updated: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profile: Vec<String>,
+}
+
+/// Text note with its author and time
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Annotation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author_reference: Option<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author_string: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+    pub text: String,
+}
+
+/// Repetition of a Timing, e.g. twice (`frequency`) per 1 (`period`) day (`periodUnit`)
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TimingRepeat {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bounds_period: Option<Period>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_unit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period_unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub when: Vec<String>,
+}
+
+impl TimingRepeat {
+    /// Human-readable frequency, e.g. `Twice daily` or `Every 8 hours`
+    pub fn display(&self) -> Option<String> {
+        let frequency = self.frequency.unwrap_or(1);
+        let period = self.period?;
+        let unit = self.period_unit.as_deref()?;
+        let (noun, adverb) = match unit {
+            "s" => ("second", None),
+            "min" => ("minute", None),
+            "h" => ("hour", Some("hourly")),
+            "d" => ("day", Some("daily")),
+            "wk" => ("week", Some("weekly")),
+            "mo" => ("month", Some("monthly")),
+            "a" => ("year", Some("yearly")),
+            _ => return None,
+        };
+
+        let times = match frequency {
+            1 => "Once".to_string(),
+            2 => "Twice".to_string(),
+            n => format!("{} times", n),
+        };
+
+        Some(if period == 1.0 {
+            match adverb {
+                Some(adverb) => format!("{} {}", times, adverb),
+                None => format!("{} per {}", times, noun),
+            }
+        } else {
+            let every = format!("every {} {}s", period, noun);
+            if frequency == 1 { capitalize(&every) } else { format!("{} {}", times, every) }
+        })
+    }
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// When an event is to occur
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct Timing {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub event: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<TimingRepeat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<CodeableConcept>,
+}
+
+impl Timing {
+    /// Frequency from the repeat, falling back to the timing code, e.g. `BID`
+    pub fn display(&self) -> Option<String> {
+        self.repeat.as_ref()
+            .and_then(|repeat| repeat.display())
+            .or_else(|| self.code.as_ref().and_then(|code| code.display()).map(str::to_string))
+    }
+}
+
+/// Amount of medication per dose or rate
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DoseAndRate {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub dose_type: Option<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dose_quantity: Option<Quantity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dose_range: Option<Range>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_quantity: Option<Quantity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_ratio: Option<Ratio>,
+}
+
+/// How a medication is or should be taken
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Dosage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patient_instruction: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timing: Option<Timing>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub as_needed_boolean: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site: Option<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dose_and_rate: Vec<DoseAndRate>,
+}
+
+impl Dosage {
+    /// Amount of one dose, e.g. `500 mg` or `1-2 tablets`
+    pub fn dose(&self) -> Option<String> {
+        self.dose_and_rate.iter().find_map(|dose| {
+            if let Some(quantity) = &dose.dose_quantity {
+                return quantity.display();
+            }
+            let range = dose.dose_range.as_ref()?;
+            let low = range.low.as_ref()?;
+            let high = range.high.as_ref()?;
+            Some(format!("{}-{}", low.value?, high.display()?))
+        })
+    }
+
+    /// How often the dose is taken, e.g. `Twice daily, as needed`
+    pub fn frequency(&self) -> Option<String> {
+        let timing = self.timing.as_ref().and_then(|timing| timing.display());
+        match (timing, self.as_needed_boolean) {
+            (Some(timing), Some(true)) => Some(format!("{}, as needed", timing)),
+            (None, Some(true)) => Some("As needed".to_string()),
+            (timing, _) => timing,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn dosage(value: serde_json::Value) -> Dosage {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn renders_timing_repeats() {
+        let repeat = |frequency, period, unit: &str| TimingRepeat {
+            frequency: Some(frequency),
+            period: Some(period),
+            period_unit: Some(unit.to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(repeat(1, 1.0, "d").display().as_deref(), Some("Once daily"));
+        assert_eq!(repeat(2, 1.0, "d").display().as_deref(), Some("Twice daily"));
+        assert_eq!(repeat(3, 1.0, "wk").display().as_deref(), Some("3 times weekly"));
+        assert_eq!(repeat(1, 8.0, "h").display().as_deref(), Some("Every 8 hours"));
+        assert_eq!(repeat(2, 1.5, "d").display().as_deref(), Some("Twice every 1.5 days"));
+        assert_eq!(repeat(1, 1.0, "min").display().as_deref(), Some("Once per minute"));
+        assert_eq!(TimingRepeat::default().display(), None);
+    }
+
+    #[test]
+    fn renders_dose_and_frequency() {
+        let structured = dosage(json!({
+            "timing": { "repeat": { "frequency": 2, "period": 1, "periodUnit": "d" } },
+            "route": { "coding": [{ "system": "http://snomed.info/sct", "code": "26643006", "display": "Oral" }] },
+            "doseAndRate": [{ "doseQuantity": { "value": 500, "unit": "mg" } }]
+        }));
+        assert_eq!(structured.dose().as_deref(), Some("500 mg"));
+        assert_eq!(structured.frequency().as_deref(), Some("Twice daily"));
+
+        let ranged = dosage(json!({
+            "timing": { "code": { "text": "BID" } },
+            "asNeededBoolean": true,
+            "doseAndRate": [{ "doseRange": { "low": { "value": 1 }, "high": { "value": 2, "unit": "tablets" } } }]
+        }));
+        assert_eq!(ranged.dose().as_deref(), Some("1-2 tablets"));
+        assert_eq!(ranged.frequency().as_deref(), Some("BID, as needed"));
+
+        let free_text = dosage(json!({ "text": "Use as directed", "asNeededBoolean": true }));
+        assert_eq!(free_text.dose(), None);
+        assert_eq!(free_text.frequency().as_deref(), Some("As needed"));
+    }
+}
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use crate::datatypes::{CodeableConcept, Coding, Identifier, Meta, Period, Reference};
+use crate::FhirResource;
+
+/// Practitioner involved in an Encounter
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EncounterParticipant {
+    #[serde(rename = "type", default, skip_serializing_if = "Vec::is_empty")]
+    pub participant_type: Vec<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<Period>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub individual: Option<Reference>,
+}
+
+/// Location where an Encounter took place
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EncounterLocation {
+    pub location: Reference,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<Period>,
+}
+
+/// Admission and discharge details of an inpatient Encounter
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EncounterHospitalization {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admit_source: Option<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub re_admission: Option<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discharge_disposition: Option<CodeableConcept>,
+}
+
+/// Interaction between the patient and a provider, e.g. an office visit or an admission
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Encounter {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    #[serde(default)]
+    pub status: String,
+    /// Encounter class, e.g. `AMB`, `EMER` or `IMP` from v3 ActCode
+    pub class: Coding,
+    #[serde(rename = "type", default, skip_serializing_if = "Vec::is_empty")]
+    pub encounter_type: Vec<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_type: Option<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub participant: Vec<EncounterParticipant>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<Period>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reason_code: Vec<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hospitalization: Option<EncounterHospitalization>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub location: Vec<EncounterLocation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_provider: Option<Reference>,
+}
+
+impl FhirResource for Encounter {
+    const RESOURCE_TYPE: &'static str = "Encounter";
+}
+
+impl Encounter {
+    /// Whether the patient was admitted (`IMP`, `ACUTE` or `NONAC` class, or hospitalization details)
+    pub fn is_inpatient(&self) -> bool {
+        matches!(self.class.code.as_deref(), Some("IMP" | "ACUTE" | "NONAC")) || self.hospitalization.is_some()
+    }
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use crate::datatypes::{Annotation, CodeableConcept, Identifier, Meta, Quantity, Reference};
+use crate::FhirResource;
+
+/// `occurrence[x]` of an Immunization
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub enum Occurrence {
+    #[serde(rename = "occurrenceDateTime")]
+    DateTime(String),
+    #[serde(rename = "occurrenceString")]
+    String(String),
+}
+
+/// Who administered an Immunization
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImmunizationPerformer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function: Option<CodeableConcept>,
+    pub actor: Reference,
+}
+
+/// Vaccine administered to the patient
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Immunization {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    #[serde(default)]
+    pub status: String,
+    pub vaccine_code: CodeableConcept,
+    pub patient: Reference,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<Reference>,
+    #[serde(flatten)]
+    pub occurrence: Option<Occurrence>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recorded: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub primary_source: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lot_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site: Option<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route: Option<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dose_quantity: Option<Quantity>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub performer: Vec<ImmunizationPerformer>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub note: Vec<Annotation>,
+}
+
+impl FhirResource for Immunization {
+    const RESOURCE_TYPE: &'static str = "Immunization";
+}
@@ -0,0 +1,319 @@
+//! Typed FHIR R4 resources and data types used by the medical-app and
+//! medical-smartapp lambdas.
+
+pub mod datatypes;
+pub mod choice;
+pub mod medication;
+pub mod observation;
+pub mod condition;
+pub mod encounter;
+pub mod procedure;
+pub mod immunization;
+pub mod allergy;
+pub mod patient;
+pub mod practitioner;
+pub mod location;
+pub mod appointment;
+pub mod care_plan;
+pub mod client;
+pub mod search;
+pub mod resolver;
+pub mod ucum;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+pub use datatypes::{
+    Annotation, CodeableConcept, Coding, Dosage, DoseAndRate, Identifier, Meta, Period,
+    Quantity, Range, Ratio, Reference, Timing, TimingRepeat,
+    ICD10_CM_SYSTEM, ICD10_SYSTEM, LOINC_SYSTEM, RXNORM_SYSTEM, SNOMED_SYSTEM,
+};
+pub use choice::{Abatement, Effective, Onset};
+pub use medication::{
+    IngredientItem, Medication, MedicationChoice, MedicationIngredient, MedicationRequest,
+    MedicationRequestDispense, MedicationStatement,
+};
+pub use observation::{Observation, ObservationComponent, ObservationValue, ReferenceRange};
+pub use condition::Condition;
+pub use encounter::{Encounter, EncounterHospitalization, EncounterLocation, EncounterParticipant};
+pub use procedure::{Performed, Procedure, ProcedurePerformer};
+pub use immunization::{Immunization, ImmunizationPerformer, Occurrence};
+pub use allergy::{AllergyIntolerance, AllergyReaction};
+pub use patient::{
+    Address, ContactPoint, Extension, ExtensionItem, HumanName, Patient, PatientContact,
+    preferred_telecom,
+};
+pub use practitioner::Practitioner;
+pub use location::Location;
+pub use appointment::{Appointment, AppointmentParticipant};
+pub use care_plan::{CarePlan, CarePlanActivity, CarePlanActivityDetail, Scheduled};
+pub use client::{FhirClient, FhirError};
+pub use search::{ResourceIndex, SearchParams, SearchResult};
+pub use resolver::ReferenceResolver;
+
+/// Typed FHIR resource, identified by its `resourceType`
+pub trait FhirResource: Serialize + DeserializeOwned {
+    const RESOURCE_TYPE: &'static str;
+}
+
+/// Deserialize a resource, checking its `resourceType` first
+pub fn from_value<T: FhirResource>(value: serde_json::Value) -> Result<T, serde_json::Error> {
+    let resource_type = value.get("resourceType").and_then(|r| r.as_str()).unwrap_or_default();
+    if resource_type != T::RESOURCE_TYPE {
+        return Err(serde::de::Error::custom(format!(
+            "expected a {} resource, found {:?}",
+            T::RESOURCE_TYPE, resource_type,
+        )));
+    }
+    serde_json::from_value(value)
+}
+
+/// Resources of the given type in the entries of a searchset Bundle, skipping invalid ones
+pub fn bundle_resources<T: FhirResource>(bundle: &serde_json::Value) -> Vec<T> {
+    bundle.get("entry").and_then(|entry| entry.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.get("resource"))
+        .filter(|resource| resource["resourceType"] == T::RESOURCE_TYPE)
+        .filter_map(|resource| from_value(resource.clone()).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    fn sample(file: &str) -> Value {
+        let path = format!("{}/../medical-app/x-events/{}", env!("CARGO_MANIFEST_DIR"), file);
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    fn resources<T: FhirResource>(bundle: &Value) -> Vec<T> {
+        bundle["entry"].as_array().unwrap()
+            .iter()
+            .filter(|entry| entry["resource"]["resourceType"] == T::RESOURCE_TYPE)
+            .map(|entry| from_value(entry["resource"].clone()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn parses_sample_medication_statements() {
+        let statements: Vec<MedicationStatement> = resources(&sample("response.json")["prefetch"]["medications"]);
+        assert_eq!(statements.len(), 3);
+
+        let first = &statements[0];
+        assert_eq!(first.status, "active");
+        let medication = first.medication.as_ref().unwrap();
+        assert_eq!(medication.display(), Some("Darbepoetin Alfa 0.5 MG/ML"));
+        assert_eq!(medication.code_in(RXNORM_SYSTEM), Some("731184"));
+        assert_eq!(first.effective, Some(Effective::DateTime("2015-06-22T00:00:00.000000Z".to_string())));
+        assert_eq!(first.dosage[0].text.as_deref(), Some("As needed"));
+    }
+
+    #[test]
+    fn parses_sample_observations() {
+        let observations: Vec<Observation> = resources(&sample("csd-event.json")["prefetch"]["observations"]);
+        assert_eq!(observations.len(), 10);
+        assert!(observations.iter().all(|o| o.value.is_some()));
+        assert!(observations.iter().any(|o| matches!(o.value, Some(ObservationValue::Quantity(_)))));
+        assert!(observations.iter().any(|o| matches!(o.value, Some(ObservationValue::String(_)))));
+    }
+
+    #[test]
+    fn parses_sample_conditions_and_allergies() {
+        let prefetch = &sample("cds-event-001.json")["prefetch"];
+
+        let conditions: Vec<Condition> = resources(&prefetch["conditions"]);
+        assert_eq!(conditions.len(), 6);
+        assert!(conditions.iter().all(|c| c.code.as_ref().and_then(|c| c.display()).is_some()));
+        assert!(conditions.iter().any(|c| c.has_category("problem-list-item")));
+        assert_eq!(conditions.iter().filter(|c| c.is_active()).count(), 4);
+
+        let allergies: Vec<AllergyIntolerance> = resources(&prefetch["allergies"]);
+        assert_eq!(allergies.len(), 2);
+        assert!(allergies.iter().all(|a| !a.patient.reference.as_deref().unwrap_or_default().is_empty()));
+    }
+
+    #[test]
+    fn parses_observation_components() {
+        let observation: Observation = from_value(json!({
+            "resourceType": "Observation",
+            "status": "final",
+            "code": { "coding": [{ "system": LOINC_SYSTEM, "code": "85354-9" }] },
+            "effectivePeriod": { "start": "2025-03-01T10:00:00Z" },
+            "component": [
+                {
+                    "code": { "coding": [{ "system": LOINC_SYSTEM, "code": "8480-6" }] },
+                    "valueQuantity": { "value": 120.0, "unit": "mm[Hg]" }
+                },
+                {
+                    "code": { "coding": [{ "system": LOINC_SYSTEM, "code": "8462-4" }] },
+                    "valueQuantity": { "value": 80.0, "unit": "mm[Hg]" }
+                }
+            ]
+        })).unwrap();
+
+        assert!(observation.value.is_none());
+        assert_eq!(observation.effective.as_ref().and_then(Effective::start), Some("2025-03-01T10:00:00Z"));
+        let systolic = observation.component(LOINC_SYSTEM, "8480-6").unwrap();
+        assert_eq!(systolic.value.as_ref().unwrap().to_string(), "120 mm[Hg]");
+    }
+
+    #[test]
+    fn round_trips_choice_elements() {
+        let request = json!({
+            "resourceType": "MedicationRequest",
+            "id": "mr-1",
+            "status": "draft",
+            "intent": "order",
+            "medicationReference": { "reference": "Medication/med-1", "display": "Warfarin 5 MG" },
+            "subject": { "reference": "Patient/1288992" },
+            "dosageInstruction": [{
+                "text": "5 mg once daily",
+                "timing": { "repeat": { "frequency": 1, "period": 1.0, "periodUnit": "d" } },
+                "doseAndRate": [{ "doseQuantity": { "value": 5.0, "unit": "mg" } }]
+            }]
+        });
+
+        let typed: MedicationRequest = from_value(request.clone()).unwrap();
+        assert!(matches!(typed.medication, Some(MedicationChoice::Reference(_))));
+        assert_eq!(serde_json::to_value(&typed).unwrap(), request);
+    }
+
+    #[test]
+    fn reads_medications_from_search_bundles() {
+        let bundle = json!({
+            "resourceType": "Bundle",
+            "type": "searchset",
+            "entry": [
+                { "resource": { "resourceType": "MedicationRequest", "status": "active", "intent": "order" } },
+                { "resource": {
+                    "resourceType": "Medication",
+                    "id": "med-1",
+                    "ingredient": [
+                        { "itemCodeableConcept": { "text": "Amlodipine" } },
+                        { "itemCodeableConcept": { "text": "Benazepril" } }
+                    ]
+                } },
+                { "resource": { "resourceType": "OperationOutcome" } }
+            ]
+        });
+
+        let medications: Vec<Medication> = bundle_resources(&bundle);
+        assert_eq!(medications.len(), 1);
+        assert_eq!(medications[0].display().as_deref(), Some("Amlodipine / Benazepril"));
+        assert_eq!(bundle_resources::<MedicationRequest>(&bundle).len(), 1);
+        assert!(bundle_resources::<Patient>(&json!({})).is_empty());
+    }
+
+    #[test]
+    fn parses_encounters_procedures_and_immunizations() {
+        let encounter: Encounter = from_value(json!({
+            "resourceType": "Encounter",
+            "status": "finished",
+            "class": { "system": "http://terminology.hl7.org/CodeSystem/v3-ActCode", "code": "EMER" },
+            "period": { "start": "2024-11-02T08:00:00Z", "end": "2024-11-02T12:00:00Z" }
+        })).unwrap();
+        assert_eq!(encounter.class.code.as_deref(), Some("EMER"));
+        assert!(!encounter.is_inpatient());
+
+        let admission: Encounter = from_value(json!({
+            "resourceType": "Encounter",
+            "status": "finished",
+            "class": { "code": "AMB" },
+            "hospitalization": { "admitSource": { "text": "Emergency department" } }
+        })).unwrap();
+        assert!(admission.is_inpatient());
+
+        let procedure: Procedure = from_value(json!({
+            "resourceType": "Procedure",
+            "status": "completed",
+            "code": { "text": "Appendectomy" },
+            "subject": { "reference": "Patient/1288992" },
+            "performedPeriod": { "start": "2019-05-10", "end": "2019-05-10" }
+        })).unwrap();
+        assert_eq!(procedure.performed.as_ref().and_then(Performed::start), Some("2019-05-10"));
+
+        let immunization: Immunization = from_value(json!({
+            "resourceType": "Immunization",
+            "status": "completed",
+            "vaccineCode": { "text": "Influenza, seasonal" },
+            "patient": { "reference": "Patient/1288992" },
+            "occurrenceDateTime": "2024-10-01"
+        })).unwrap();
+        assert_eq!(immunization.occurrence, Some(Occurrence::DateTime("2024-10-01".to_string())));
+
+        assert!(from_value::<Procedure>(json!({ "resourceType": "Condition" })).is_err());
+    }
+
+    #[test]
+    fn reads_patient_ethnicity_extension() {
+        let patient: Patient = from_value(json!({
+            "resourceType": "Patient",
+            "id": "2694ec73-6151-4197-aabc-62ae04ed8962",
+            "extension": [
+                {
+                    "url": patient::US_CORE_RACE_URL,
+                    "extension": [{
+                        "url": "ombCategory",
+                        "valueCoding": { "system": "urn:oid:2.16.840.1.113883.6.238", "code": "2106-3", "display": "White" }
+                    }]
+                },
+                {
+                    "url": patient::US_CORE_ETHNICITY_URL,
+                    "extension": [
+                        {
+                            "url": "ombCategory",
+                            "valueCoding": { "system": "urn:oid:2.16.840.1.113883.6.238", "code": "2135-2", "display": "Hispanic or Latino" }
+                        },
+                        { "url": "text", "valueString": "Hispanic or Latino" }
+                    ]
+                }
+            ],
+            "name": [{ "family": "Centeno914", "given": ["Juan"] }],
+            "birthDate": "1980-04-12"
+        })).unwrap();
+
+        assert_eq!(patient.ethnicity().as_deref(), Some("Hispanic or Latino"));
+        assert_eq!(patient.extension_display(patient::US_CORE_RACE_URL).as_deref(), Some("White"));
+        assert_eq!(Patient::default().ethnicity(), None);
+    }
+
+    #[test]
+    fn picks_telecom_and_emergency_contact() {
+        let patient: Patient = from_value(json!({
+            "resourceType": "Patient",
+            "telecom": [
+                { "system": "phone", "value": "(555) 000-0000", "use": "old" },
+                { "system": "phone", "value": "(555) 111-1111", "use": "work" },
+                { "system": "phone", "value": "(555) 222-2222", "use": "mobile" },
+                { "system": "email", "value": "work@example.com", "use": "work", "rank": 2 },
+                { "system": "email", "value": "maria@example.com", "use": "home", "rank": 1 }
+            ],
+            "contact": [
+                {
+                    "relationship": [{ "coding": [{ "system": patient::CONTACT_ROLE_SYSTEM, "code": "N", "display": "Next-of-Kin" }] }],
+                    "name": { "text": "Ana Lopez" }
+                },
+                {
+                    "relationship": [
+                        { "coding": [{ "system": patient::CONTACT_ROLE_SYSTEM, "code": "C", "display": "Emergency Contact" }] },
+                        { "coding": [{ "system": "http://terminology.hl7.org/CodeSystem/v3-RoleCode", "code": "HUSB", "display": "Husband" }] }
+                    ],
+                    "name": { "family": "Smith", "given": ["John"] },
+                    "telecom": [{ "system": "phone", "value": "(555) 987-6543" }]
+                }
+            ]
+        })).unwrap();
+
+        assert_eq!(patient.phone(), Some("(555) 222-2222"));
+        assert_eq!(patient.email(), Some("maria@example.com"));
+
+        let contact = patient.emergency_contact().unwrap();
+        assert_eq!(contact.name.as_ref().and_then(HumanName::display).as_deref(), Some("John Smith"));
+        assert_eq!(contact.relationship_display(), Some("Husband"));
+        assert_eq!(contact.phone(), Some("(555) 987-6543"));
+        assert_eq!(Patient::default().emergency_contact(), None);
+    }
+}
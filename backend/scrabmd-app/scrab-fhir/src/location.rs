@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use crate::datatypes::{CodeableConcept, Identifier, Meta, Reference};
+use crate::patient::Address;
+use crate::FhirResource;
+
+/// Place where care is provided, e.g. a clinic room
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Location {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alias: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "type", default, skip_serializing_if = "Vec::is_empty")]
+    pub location_type: Vec<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<Address>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub managing_organization: Option<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub part_of: Option<Reference>,
+}
+
+impl FhirResource for Location {
+    const RESOURCE_TYPE: &'static str = "Location";
+}
+
+impl Location {
+    /// Name of the location, falling back to its address
+    pub fn display(&self) -> Option<String> {
+        self.name.clone()
+            .or_else(|| self.alias.first().cloned())
+            .or_else(|| {
+                let address = self.address.as_ref()?;
+                let parts: Vec<&str> = address.line.iter().flatten().map(String::as_str)
+                    .chain(address.city.as_deref())
+                    .collect();
+                (!parts.is_empty()).then(|| parts.join(", "))
+            })
+    }
+}
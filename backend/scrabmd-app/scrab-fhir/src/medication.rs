@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::choice::Effective;
+use crate::datatypes::{
+    Annotation, CodeableConcept, Dosage, Identifier, Meta, Period, Ratio, Reference,
+};
+use crate::FhirResource;
+
+/// `medication[x]` of MedicationStatement and MedicationRequest
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub enum MedicationChoice {
+    #[serde(rename = "medicationCodeableConcept")]
+    CodeableConcept(CodeableConcept),
+    #[serde(rename = "medicationReference")]
+    Reference(Reference),
+}
+
+impl MedicationChoice {
+    /// Name of the medication, from the concept or the reference display
+    pub fn display(&self) -> Option<&str> {
+        match self {
+            MedicationChoice::CodeableConcept(concept) => concept.display(),
+            MedicationChoice::Reference(reference) => reference.display.as_deref(),
+        }
+    }
+
+    /// Code in the given system, only available for a codeable concept
+    pub fn code_in(&self, system: &str) -> Option<&str> {
+        match self {
+            MedicationChoice::CodeableConcept(concept) => concept.code_in(system),
+            MedicationChoice::Reference(_) => None,
+        }
+    }
+}
+
+/// Medication the patient is, was or will be taking
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MedicationStatement {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contained: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    /// MedicationRequest or CarePlan the statement records
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub based_on: Vec<Reference>,
+    #[serde(default)]
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<CodeableConcept>,
+    #[serde(flatten)]
+    pub medication: Option<MedicationChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<Reference>,
+    #[serde(flatten)]
+    pub effective: Option<Effective>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_asserted: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reason_code: Vec<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub note: Vec<Annotation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dosage: Vec<Dosage>,
+}
+
+impl FhirResource for MedicationStatement {
+    const RESOURCE_TYPE: &'static str = "MedicationStatement";
+}
+
+/// Order for the supply and administration of a medication
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MedicationRequest {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contained: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub intent: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub category: Vec<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+    #[serde(flatten)]
+    pub medication: Option<MedicationChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authored_on: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requester: Option<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prior_prescription: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reason_code: Vec<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub note: Vec<Annotation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dosage_instruction: Vec<Dosage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dispense_request: Option<MedicationRequestDispense>,
+}
+
+impl FhirResource for MedicationRequest {
+    const RESOURCE_TYPE: &'static str = "MedicationRequest";
+}
+
+impl MedicationRequest {
+    /// Last day of the treatment: the end of the dosage bounds, or of the prescription validity
+    pub fn end_date(&self) -> Option<&str> {
+        self.dosage_instruction.iter()
+            .filter_map(|dosage| dosage.timing.as_ref()?.repeat.as_ref()?.bounds_period.as_ref()?.end.as_deref())
+            .max()
+            .or_else(|| self.dispense_request.as_ref()?.validity_period.as_ref()?.end.as_deref())
+    }
+}
+
+/// Supply authorized by a MedicationRequest
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MedicationRequestDispense {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validity_period: Option<Period>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number_of_repeats_allowed: Option<u32>,
+}
+
+/// Ingredient of a Medication with its strength
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MedicationIngredient {
+    #[serde(flatten)]
+    pub item: IngredientItem,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_active: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strength: Option<Ratio>,
+}
+
+/// `item[x]` of a Medication ingredient
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub enum IngredientItem {
+    #[serde(rename = "itemCodeableConcept")]
+    CodeableConcept(CodeableConcept),
+    #[serde(rename = "itemReference")]
+    Reference(Reference),
+}
+
+/// Medication definition, the target of a `medicationReference`
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Medication {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub form: Option<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Ratio>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ingredient: Vec<MedicationIngredient>,
+}
+
+impl Medication {
+    /// Name of the medication, falling back to its ingredients
+    pub fn display(&self) -> Option<String> {
+        if let Some(name) = self.code.as_ref().and_then(|code| code.display()) {
+            return Some(name.to_string());
+        }
+
+        let ingredients: Vec<&str> = self.ingredient.iter()
+            .filter_map(|ingredient| match &ingredient.item {
+                IngredientItem::CodeableConcept(concept) => concept.display(),
+                IngredientItem::Reference(reference) => reference.display.as_deref(),
+            })
+            .collect();
+        (!ingredients.is_empty()).then(|| ingredients.join(" / "))
+    }
+}
+
+impl FhirResource for Medication {
+    const RESOURCE_TYPE: &'static str = "Medication";
+}
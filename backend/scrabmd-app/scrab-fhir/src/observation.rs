@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use crate::choice::{Effective, write_period};
+use crate::datatypes::{
+    Annotation, CodeableConcept, Identifier, Meta, Period, Quantity, Range, Ratio, Reference,
+};
+use crate::FhirResource;
+use std::fmt;
+
+/// `value[x]` of an Observation or one of its components
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub enum ObservationValue {
+    #[serde(rename = "valueQuantity")]
+    Quantity(Quantity),
+    #[serde(rename = "valueCodeableConcept")]
+    CodeableConcept(CodeableConcept),
+    #[serde(rename = "valueString")]
+    String(String),
+    #[serde(rename = "valueBoolean")]
+    Boolean(bool),
+    #[serde(rename = "valueInteger")]
+    Integer(i64),
+    #[serde(rename = "valueRange")]
+    Range(Range),
+    #[serde(rename = "valueRatio")]
+    Ratio(Ratio),
+    #[serde(rename = "valueTime")]
+    Time(String),
+    #[serde(rename = "valueDateTime")]
+    DateTime(String),
+    #[serde(rename = "valuePeriod")]
+    Period(Period),
+}
+
+impl ObservationValue {
+    pub fn as_quantity(&self) -> Option<&Quantity> {
+        match self {
+            ObservationValue::Quantity(quantity) => Some(quantity),
+            _ => None,
+        }
+    }
+}
+
+fn write_quantity(f: &mut fmt::Formatter<'_>, quantity: Option<&Quantity>) -> fmt::Result {
+    f.write_str(&quantity.and_then(Quantity::display).unwrap_or_else(|| "?".to_string()))
+}
+
+impl fmt::Display for ObservationValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObservationValue::Quantity(quantity) => write_quantity(f, Some(quantity)),
+            ObservationValue::CodeableConcept(concept) => f.write_str(concept.display().unwrap_or("Unknown")),
+            ObservationValue::String(value)
+            | ObservationValue::Time(value)
+            | ObservationValue::DateTime(value) => f.write_str(value),
+            ObservationValue::Boolean(value) => write!(f, "{}", value),
+            ObservationValue::Integer(value) => write!(f, "{}", value),
+            ObservationValue::Range(range) => {
+                write_quantity(f, range.low.as_ref())?;
+                f.write_str(" - ")?;
+                write_quantity(f, range.high.as_ref())
+            }
+            ObservationValue::Ratio(ratio) => {
+                write_quantity(f, ratio.numerator.as_ref())?;
+                f.write_str(" / ")?;
+                write_quantity(f, ratio.denominator.as_ref())
+            }
+            ObservationValue::Period(period) => write_period(f, period),
+        }
+    }
+}
+
+/// Normal range of an Observation value
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferenceRange {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub low: Option<Quantity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub high: Option<Quantity>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub range_type: Option<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+/// Component of a multi-part Observation, e.g. the systolic pressure of a blood pressure panel
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ObservationComponent {
+    pub code: CodeableConcept,
+    #[serde(flatten)]
+    pub value: Option<ObservationValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_absent_reason: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interpretation: Vec<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reference_range: Vec<ReferenceRange>,
+}
+
+/// Measurement or assertion about the patient (vital signs, labs, ...)
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Observation {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    #[serde(default)]
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub category: Vec<CodeableConcept>,
+    pub code: CodeableConcept,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<Reference>,
+    #[serde(flatten)]
+    pub effective: Option<Effective>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issued: Option<String>,
+    #[serde(flatten)]
+    pub value: Option<ObservationValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_absent_reason: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interpretation: Vec<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub note: Vec<Annotation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reference_range: Vec<ReferenceRange>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub component: Vec<ObservationComponent>,
+}
+
+impl FhirResource for Observation {
+    const RESOURCE_TYPE: &'static str = "Observation";
+}
+
+impl Observation {
+    /// Component with the given code, e.g. LOINC `8480-6` for the systolic pressure
+    pub fn component(&self, system: &str, code: &str) -> Option<&ObservationComponent> {
+        self.component.iter().find(|c| c.code.has_code(system, code))
+    }
+}
@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::datatypes::{CodeableConcept, Coding, Identifier, Meta, Reference};
+use crate::FhirResource;
+
+pub const US_CORE_ETHNICITY_URL: &str = "http://hl7.org/fhir/us/core/StructureDefinition/us-core-ethnicity";
+pub const US_CORE_RACE_URL: &str = "http://hl7.org/fhir/us/core/StructureDefinition/us-core-race";
+pub const CONTACT_ROLE_SYSTEM: &str = "http://terminology.hl7.org/CodeSystem/v2-0131";
+
+/// Relationships of a contact to reach in an emergency, in order of preference:
+/// emergency contact, emergency contact person, next of kin
+pub const EMERGENCY_CONTACT_ROLES: [&str; 3] = ["C", "EP", "N"];
+
+/// Patient resource
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Patient {
+    pub resource_type: Option<String>,
+    pub id: Option<String>,
+    pub meta: Option<Meta>,
+    pub extension: Option<Vec<Extension>>,
+    pub identifier: Option<Vec<Identifier>>,
+    pub name: Option<Vec<HumanName>>,
+    pub telecom: Option<Vec<ContactPoint>>,
+    pub gender: Option<String>,
+    pub birth_date: Option<String>,
+    pub address: Option<Vec<Address>>,
+    pub contact: Option<Vec<PatientContact>>,
+    pub communication: Option<Vec<Value>>,
+    pub managing_organization: Option<Reference>,
+}
+
+impl FhirResource for Patient {
+    const RESOURCE_TYPE: &'static str = "Patient";
+}
+
+impl Patient {
+    /// Display of the first coded value of a complex extension, e.g. the US Core ethnicity
+    pub fn extension_display(&self, url: &str) -> Option<String> {
+        self.extension.as_ref()?
+            .iter()
+            .find(|ext| ext.url.as_deref() == Some(url))?
+            .extensions.as_ref()?
+            .iter()
+            .find_map(|sub_ext| sub_ext.value_coding.as_ref()?.display.clone())
+    }
+
+    /// US Core ethnicity, e.g. `Hispanic or Latino`
+    pub fn ethnicity(&self) -> Option<String> {
+        self.extension_display(US_CORE_ETHNICITY_URL)
+    }
+
+    /// Preferred phone number of the patient
+    pub fn phone(&self) -> Option<&str> {
+        preferred_telecom(self.telecom.as_deref().unwrap_or_default(), "phone")?.value.as_deref()
+    }
+
+    /// Preferred email address of the patient
+    pub fn email(&self) -> Option<&str> {
+        preferred_telecom(self.telecom.as_deref().unwrap_or_default(), "email")?.value.as_deref()
+    }
+
+    /// Contact to reach in an emergency, see [`EMERGENCY_CONTACT_ROLES`]
+    pub fn emergency_contact(&self) -> Option<&PatientContact> {
+        let contacts = self.contact.as_deref().unwrap_or_default();
+        EMERGENCY_CONTACT_ROLES.iter()
+            .find_map(|role| contacts.iter().find(|contact| contact.has_role(role)))
+    }
+}
+
+/// Contact point of the given system (`phone`, `email`, ...) to use first: lowest
+/// `rank`, then home, mobile and work numbers before the others; `old` ones are skipped
+pub fn preferred_telecom<'a>(telecom: &'a [ContactPoint], system: &str) -> Option<&'a ContactPoint> {
+    let use_order = |contact_use: Option<&str>| match contact_use {
+        Some("home") => 0,
+        Some("mobile") => 1,
+        Some("work") => 2,
+        None => 3,
+        _ => 4,
+    };
+
+    telecom.iter()
+        .filter(|t| t.system.as_deref() == Some(system) && t.value.is_some())
+        .filter(|t| t.contact_use.as_deref() != Some("old"))
+        .min_by_key(|t| (t.rank.unwrap_or(u32::MAX), use_order(t.contact_use.as_deref())))
+}
+
+/// Guardian, next of kin or other contact party of the patient
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PatientContact {
+    pub relationship: Option<Vec<CodeableConcept>>,
+    pub name: Option<HumanName>,
+    pub telecom: Option<Vec<ContactPoint>>,
+    pub address: Option<Address>,
+    pub gender: Option<String>,
+}
+
+impl PatientContact {
+    /// Whether the contact has the given v2-0131 role, e.g. `C` for emergency contact
+    pub fn has_role(&self, code: &str) -> bool {
+        self.relationship.iter().flatten().any(|r| r.has_code(CONTACT_ROLE_SYSTEM, code))
+    }
+
+    /// Relationship to show next to the name, e.g. `Husband`, preferring a
+    /// personal relationship over the contact role
+    pub fn relationship_display(&self) -> Option<&str> {
+        let relationships = self.relationship.as_deref().unwrap_or_default();
+        relationships.iter()
+            .filter(|r| !r.coding.iter().any(|c| c.system.as_deref() == Some(CONTACT_ROLE_SYSTEM)))
+            .chain(relationships.iter())
+            .find_map(CodeableConcept::display)
+    }
+
+    /// Preferred phone number of the contact
+    pub fn phone(&self) -> Option<&str> {
+        preferred_telecom(self.telecom.as_deref().unwrap_or_default(), "phone")?.value.as_deref()
+    }
+}
+
+/// Extension for additional data
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Extension {
+    #[serde(rename = "extension")]
+    pub extensions: Option<Vec<ExtensionItem>>,
+    pub url: Option<String>,
+}
+
+/// Nested extension of a complex extension
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionItem {
+    pub url: Option<String>,
+    pub value_string: Option<String>,
+    pub value_code: Option<String>,
+    pub value_coding: Option<Coding>,
+}
+
+/// Human name
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct HumanName {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    pub family: Option<String>,
+    pub given: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<Vec<String>>,
+}
+
+impl HumanName {
+    /// Text of the name, or its parts, e.g. `Dr. Emily Chen`
+    pub fn display(&self) -> Option<String> {
+        if let Some(text) = &self.text {
+            return Some(text.clone());
+        }
+
+        let parts: Vec<&str> = [&self.prefix, &self.given].into_iter()
+            .flatten()
+            .flatten()
+            .map(String::as_str)
+            .chain(self.family.as_deref())
+            .chain(self.suffix.iter().flatten().map(String::as_str))
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+}
+
+/// Contact point (e.g., phone, email)
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ContactPoint {
+    pub system: Option<String>,
+    pub value: Option<String>,
+    #[serde(rename = "use")]
+    pub contact_use: Option<String>,
+    pub rank: Option<u32>,
+}
+
+/// Address
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Address {
+    #[serde(rename = "use")]
+    pub address_use: Option<String>,
+    pub line: Option<Vec<String>>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+}
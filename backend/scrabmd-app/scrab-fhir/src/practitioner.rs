@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use crate::datatypes::{Identifier, Meta};
+use crate::patient::{Address, ContactPoint, HumanName};
+use crate::FhirResource;
+
+/// Person providing care, the actor of appointments and procedures
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Practitioner {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub name: Vec<HumanName>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub telecom: Vec<ContactPoint>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub address: Vec<Address>,
+}
+
+impl FhirResource for Practitioner {
+    const RESOURCE_TYPE: &'static str = "Practitioner";
+}
+
+impl Practitioner {
+    /// First name of the practitioner that can be displayed
+    pub fn display(&self) -> Option<String> {
+        self.name.iter().find_map(HumanName::display)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::from_value;
+
+    #[test]
+    fn displays_prefix_given_and_family_names() {
+        let practitioner: Practitioner = from_value(json!({
+            "resourceType": "Practitioner",
+            "name": [{ "family": "Chen", "given": ["Emily"], "prefix": ["Dr."] }]
+        })).unwrap();
+        assert_eq!(practitioner.display().as_deref(), Some("Dr. Emily Chen"));
+    }
+}
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use crate::datatypes::{Annotation, CodeableConcept, Identifier, Meta, Period, Reference};
+use crate::FhirResource;
+
+/// `performed[x]` of a Procedure
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub enum Performed {
+    #[serde(rename = "performedDateTime")]
+    DateTime(String),
+    #[serde(rename = "performedPeriod")]
+    Period(Period),
+    #[serde(rename = "performedString")]
+    String(String),
+}
+
+impl Performed {
+    /// Date time, or the start of the period
+    pub fn start(&self) -> Option<&str> {
+        match self {
+            Performed::DateTime(date_time) => Some(date_time),
+            Performed::Period(period) => period.start.as_deref(),
+            Performed::String(_) => None,
+        }
+    }
+}
+
+/// Who performed a Procedure and in which role
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcedurePerformer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function: Option<CodeableConcept>,
+    pub actor: Reference,
+}
+
+/// Action performed on or for the patient, e.g. a surgery or a counseling session
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Procedure {
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifier: Vec<Identifier>,
+    #[serde(default)]
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<Reference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encounter: Option<Reference>,
+    #[serde(flatten)]
+    pub performed: Option<Performed>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub performer: Vec<ProcedurePerformer>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Reference>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reason_code: Vec<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub body_site: Vec<CodeableConcept>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<CodeableConcept>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub note: Vec<Annotation>,
+}
+
+impl FhirResource for Procedure {
+    const RESOURCE_TYPE: &'static str = "Procedure";
+}
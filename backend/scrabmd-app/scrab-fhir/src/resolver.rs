@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use log::warn;
+use serde_json::Value;
+use crate::client::FhirClient;
+use crate::datatypes::Reference;
+use crate::search::{ResourceIndex, SearchResult, reference_key};
+use crate::{FhirResource, from_value};
+
+/// Follows references to other resources: `#contained` ones in the referring
+/// resource, then the Bundles already at hand, then a read from the FHIR server.
+///
+/// Reads are memoized, failures included, so one resolver per request never
+/// fetches the same resource twice. Absolute references to another server are
+/// not followed, the access token belongs to this one.
+#[derive(Debug)]
+pub struct ReferenceResolver {
+    client: FhirClient,
+    local: Mutex<ResourceIndex>,
+    fetched: Mutex<HashMap<String, Option<Value>>>,
+    reads: AtomicUsize,
+}
+
+impl ReferenceResolver {
+    pub fn new(client: FhirClient) -> Self {
+        Self {
+            local: Mutex::new(ResourceIndex::for_server(client.base_url())),
+            client,
+            fetched: Mutex::new(HashMap::new()),
+            reads: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn client(&self) -> &FhirClient {
+        &self.client
+    }
+
+    /// Index the entries of a Bundle, e.g. a searchset or a CDS Hooks prefetch
+    pub fn add_bundle(&self, bundle: &Value) {
+        let mut local = self.local.lock().unwrap();
+        for entry in bundle["entry"].as_array().into_iter().flatten() {
+            local.insert(entry["resource"].clone(), entry["fullUrl"].as_str());
+        }
+    }
+
+    /// Index the matches and included resources of a search
+    pub fn add_search(&self, result: &SearchResult) {
+        let mut local = self.local.lock().unwrap();
+        for (_, resource) in result.index.iter() {
+            local.insert(resource.clone(), None);
+        }
+    }
+
+    /// Index a single resource, e.g. the Patient read at launch
+    pub fn add_resource(&self, resource: &Value) {
+        self.local.lock().unwrap().insert(resource.clone(), None);
+    }
+
+    /// Reads sent to the server so far
+    pub fn reads(&self) -> usize {
+        self.reads.load(Ordering::Relaxed)
+    }
+
+    /// Target of a reference from a resource with the given `contained` resources
+    pub async fn resolve(&self, reference: &Reference, contained: &[Value]) -> Option<Value> {
+        let target = reference.reference.as_deref()?;
+
+        if let Some(id) = target.strip_prefix('#') {
+            return contained.iter().find(|resource| resource["id"] == id).cloned();
+        }
+
+        if let Some(resource) = self.local.lock().unwrap().get(target) {
+            return Some(resource.clone());
+        }
+
+        let absolute = target.starts_with("http://") || target.starts_with("https://");
+        if absolute && !self.client.is_same_server(target) {
+            return None;
+        }
+        let key = reference_key(target)?;
+
+        if let Some(resource) = self.fetched.lock().unwrap().get(&key) {
+            return resource.clone();
+        }
+
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        let resource = match self.client.get(if absolute { target } else { &key }).await {
+            Ok(resource) => Some(resource),
+            Err(e) => {
+                warn!("Unable to resolve {}: {}", target, e);
+                None
+            }
+        };
+        self.fetched.lock().unwrap().insert(key, resource.clone());
+        resource
+    }
+
+    /// Typed target of a reference, `None` if missing or of another type
+    pub async fn resolve_as<T: FhirResource>(&self, reference: &Reference, contained: &[Value]) -> Option<T> {
+        from_value(self.resolve(reference, contained).await?).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::{Medication, Practitioner};
+
+    // Nothing listens on the discard port, every read fails fast
+    fn resolver() -> ReferenceResolver {
+        ReferenceResolver::new(FhirClient::new("http://127.0.0.1:9/r4", "token").unwrap())
+    }
+
+    #[tokio::test]
+    async fn resolves_contained_and_local_references() {
+        let resolver = resolver();
+        resolver.add_bundle(&json!({
+            "resourceType": "Bundle",
+            "entry": [{
+                "fullUrl": "http://127.0.0.1:9/r4/Practitioner/pr-1",
+                "resource": { "resourceType": "Practitioner", "id": "pr-1", "name": [{ "text": "Dr. Emily Chen" }] }
+            }]
+        }));
+        let contained = vec![json!({ "resourceType": "Medication", "id": "med", "code": { "text": "Warfarin 5 MG" } })];
+
+        let medication: Medication = resolver.resolve_as(&Reference::new("#med"), &contained).await.unwrap();
+        assert_eq!(medication.display().as_deref(), Some("Warfarin 5 MG"));
+
+        for reference in ["Practitioner/pr-1", "http://127.0.0.1:9/r4/Practitioner/pr-1"] {
+            let practitioner: Practitioner = resolver.resolve_as(&Reference::new(reference), &[]).await.unwrap();
+            assert_eq!(practitioner.display().as_deref(), Some("Dr. Emily Chen"));
+        }
+        assert_eq!(resolver.reads(), 0);
+    }
+
+    #[tokio::test]
+    async fn memoizes_reads_and_skips_other_servers() {
+        let resolver = resolver();
+
+        assert!(resolver.resolve(&Reference::new("Practitioner/pr-2"), &[]).await.is_none());
+        assert!(resolver.resolve(&Reference::new("Practitioner/pr-2/_history/1"), &[]).await.is_none());
+        assert_eq!(resolver.reads(), 1);
+
+        assert!(resolver.resolve(&Reference::new("https://other.example.org/r4/Practitioner/pr-2"), &[]).await.is_none());
+        assert!(resolver.resolve(&Reference::new("#missing"), &[]).await.is_none());
+        assert!(resolver.resolve(&Reference::default(), &[]).await.is_none());
+        assert_eq!(resolver.reads(), 1);
+    }
+}
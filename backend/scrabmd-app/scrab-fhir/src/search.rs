@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+use log::warn;
+use serde_json::Value;
+use url::form_urlencoded;
+use crate::client::{FhirClient, FhirError, same_server};
+use crate::datatypes::Reference;
+use crate::{FhirResource, from_value};
+
+/// Pages followed by a search unless [`SearchParams::with_max_pages`] says otherwise
+pub const DEFAULT_MAX_PAGES: usize = 10;
+
+/// Search on one resource type, e.g. `MedicationRequest?patient=123&_include=MedicationRequest:medication`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchParams {
+    resource_type: String,
+    params: Vec<(String, String)>,
+    max_pages: usize,
+}
+
+impl SearchParams {
+    pub fn new(resource_type: &str) -> Self {
+        Self {
+            resource_type: resource_type.to_string(),
+            params: vec![],
+            max_pages: DEFAULT_MAX_PAGES,
+        }
+    }
+
+    /// Search on the resource type of `T`
+    pub fn of<T: FhirResource>() -> Self {
+        Self::new(T::RESOURCE_TYPE)
+    }
+
+    /// Add a search parameter; values are encoded when the query is built
+    pub fn with(mut self, name: &str, value: &str) -> Self {
+        self.params.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_patient(self, patient_id: &str) -> Self {
+        self.with("patient", patient_id)
+    }
+
+    /// Include the targets of a reference, e.g. `MedicationRequest:medication`
+    pub fn with_include(self, include: &str) -> Self {
+        self.with("_include", include)
+    }
+
+    /// Include the resources referring to the matches, e.g. `Provenance:target`
+    pub fn with_revinclude(self, revinclude: &str) -> Self {
+        self.with("_revinclude", revinclude)
+    }
+
+    /// Page size asked to the server
+    pub fn with_count(self, count: u32) -> Self {
+        self.with("_count", &count.to_string())
+    }
+
+    /// Sort order, e.g. `-date` for the most recent first
+    pub fn with_sort(self, sort: &str) -> Self {
+        self.with("_sort", sort)
+    }
+
+    /// Only return the given elements of the matches
+    pub fn with_elements(self, elements: &[&str]) -> Self {
+        self.with("_elements", &elements.join(","))
+    }
+
+    /// Stop following `next` links after this many pages
+    pub fn with_max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = max_pages.max(1);
+        self
+    }
+
+    pub fn resource_type(&self) -> &str {
+        &self.resource_type
+    }
+
+    pub fn max_pages(&self) -> usize {
+        self.max_pages
+    }
+
+    /// Query relative to the server base, with the parameters form-encoded
+    pub fn to_query(&self) -> String {
+        let mut serializer = form_urlencoded::Serializer::new(String::new());
+        for (name, value) in &self.params {
+            serializer.append_pair(name, value);
+        }
+        let params = serializer.finish();
+
+        let resource_type = form_urlencoded::byte_serialize(self.resource_type.as_bytes()).collect::<String>();
+        if params.is_empty() {
+            resource_type
+        } else {
+            format!("{}?{}", resource_type, params)
+        }
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ RESOURCE INDEX ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Resources of a search by `Type/id` and by `fullUrl`, to resolve references locally
+#[derive(Debug, Default, Clone)]
+pub struct ResourceIndex {
+    by_key: HashMap<String, Value>,
+    by_full_url: HashMap<String, String>,
+    /// Server the resources come from, absolute references elsewhere are not matched by `Type/id`
+    base_url: Option<String>,
+}
+
+impl ResourceIndex {
+    /// Index of resources read from the server at `base_url`
+    pub fn for_server(base_url: &str) -> Self {
+        Self { base_url: Some(base_url.to_string()), ..Self::default() }
+    }
+
+    pub fn insert(&mut self, resource: Value, full_url: Option<&str>) {
+        let (Some(resource_type), Some(id)) = (resource["resourceType"].as_str(), resource["id"].as_str()) else {
+            return;
+        };
+        let key = format!("{}/{}", resource_type, id);
+        if let Some(full_url) = full_url {
+            self.by_full_url.insert(full_url.to_string(), key.clone());
+        }
+        self.by_key.insert(key, resource);
+    }
+
+    /// Resource for a relative (`Patient/1`), versioned (`Patient/1/_history/2`)
+    /// or absolute reference. An absolute reference that is not a `fullUrl` of
+    /// the index only matches by `Type/id` when it points to the indexed server.
+    pub fn get(&self, reference: &str) -> Option<&Value> {
+        if let Some(key) = self.by_full_url.get(reference) {
+            return self.by_key.get(key);
+        }
+        let absolute = reference.starts_with("http://") || reference.starts_with("https://");
+        if absolute && !self.base_url.as_deref().is_some_and(|base| same_server(base, reference)) {
+            return None;
+        }
+        self.by_key.get(&reference_key(reference)?)
+    }
+
+    pub fn resolve(&self, reference: &Reference) -> Option<&Value> {
+        self.get(reference.reference.as_deref()?)
+    }
+
+    /// Typed target of a reference, `None` if missing or of another type
+    pub fn resolve_as<T: FhirResource>(&self, reference: &Reference) -> Option<T> {
+        from_value(self.resolve(reference)?.clone()).ok()
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_key.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_key.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.by_key.iter().map(|(key, resource)| (key.as_str(), resource))
+    }
+}
+
+/// `Type/id` of a reference, dropping the server base and `_history` version
+pub fn reference_key(reference: &str) -> Option<String> {
+    let reference = reference.split('#').next().unwrap_or_default();
+    let reference = match reference.find("/_history/") {
+        Some(index) => &reference[..index],
+        None => reference,
+    };
+    let mut segments = reference.trim_end_matches('/').rsplit('/');
+    let id = segments.next().filter(|id| !id.is_empty())?;
+    let resource_type = segments.next().filter(|t| t.starts_with(|c: char| c.is_ascii_uppercase()))?;
+    Some(format!("{}/{}", resource_type, id))
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~ SEARCH RESULT ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Matches and included resources of all the pages of a search
+#[derive(Debug, Default, Clone)]
+pub struct SearchResult {
+    /// Resources matching the search, in server order
+    pub matches: Vec<Value>,
+    /// Resources brought in by `_include` or `_revinclude`
+    pub included: Vec<Value>,
+    /// Every resource of the search, matches and included, for reference resolution
+    pub index: ResourceIndex,
+    /// `Bundle.total` of the first page, when the server reports it
+    pub total: Option<u64>,
+    /// Pages fetched
+    pub pages: usize,
+    /// Whether a `next` page was left unfetched, because of the page limit or a link off the server
+    pub truncated: bool,
+}
+
+impl SearchResult {
+    /// Empty result of a search on the server at `base_url`
+    pub fn for_server(base_url: &str) -> Self {
+        Self { index: ResourceIndex::for_server(base_url), ..Self::default() }
+    }
+
+    /// Add the entries of a searchset Bundle page
+    pub fn add_page(&mut self, bundle: &Value) {
+        if self.pages == 0 {
+            self.total = bundle["total"].as_u64();
+        }
+        self.pages += 1;
+
+        for entry in bundle["entry"].as_array().into_iter().flatten() {
+            let resource = &entry["resource"];
+            if !resource.is_object() {
+                continue;
+            }
+            // Without a search mode every resource but an OperationOutcome counts as a match
+            match entry["search"]["mode"].as_str() {
+                Some("match") => self.matches.push(resource.clone()),
+                Some("include") => self.included.push(resource.clone()),
+                Some(_) => continue,
+                None if resource["resourceType"] == "OperationOutcome" => continue,
+                None => self.matches.push(resource.clone()),
+            }
+            self.index.insert(resource.clone(), entry["fullUrl"].as_str());
+        }
+    }
+
+    /// Matches of the given type, skipping the ones that do not parse
+    pub fn resources<T: FhirResource>(&self) -> Vec<T> {
+        typed(&self.matches)
+    }
+
+    /// Included resources of the given type
+    pub fn included_resources<T: FhirResource>(&self) -> Vec<T> {
+        typed(&self.included)
+    }
+}
+
+fn typed<T: FhirResource>(resources: &[Value]) -> Vec<T> {
+    resources.iter()
+        .filter(|resource| resource["resourceType"] == T::RESOURCE_TYPE)
+        .filter_map(|resource| from_value(resource.clone()).ok())
+        .collect()
+}
+
+/// URL of the `next` page of a searchset Bundle
+pub fn next_link(bundle: &Value) -> Option<&str> {
+    bundle["link"].as_array()?
+        .iter()
+        .find(|link| link["relation"] == "next")?
+        ["url"].as_str()
+}
+
+impl FhirClient {
+    /// Run a search, following `next` links up to the page limit of the parameters
+    pub async fn search(&self, params: &SearchParams) -> Result<SearchResult, FhirError> {
+        let mut result = SearchResult::for_server(self.base_url());
+        let mut bundle = self.get(&params.to_query()).await?;
+
+        loop {
+            result.add_page(&bundle);
+
+            let Some(next) = next_link(&bundle) else {
+                break;
+            };
+            if result.pages >= params.max_pages() {
+                result.truncated = true;
+                break;
+            }
+            // The bearer token is only sent back to the server it was issued for,
+            // the pages fetched so far are still a valid result
+            if !self.is_same_server(next) {
+                warn!("Not following next link outside of the FHIR server: {}", next);
+                result.truncated = true;
+                break;
+            }
+            bundle = self.get(next).await?;
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use scrab_testing::MockServer;
+    use crate::{Medication, MedicationRequest, Patient};
+
+    #[test]
+    fn encodes_search_parameters() {
+        let params = SearchParams::of::<MedicationRequest>()
+            .with_patient("123 & co")
+            .with("status", "active,on-hold")
+            .with_include("MedicationRequest:medication")
+            .with_revinclude("Provenance:target")
+            .with_count(50)
+            .with_sort("-authoredon")
+            .with_elements(&["status", "medication"]);
+
+        assert_eq!(
+            params.to_query(),
+            "MedicationRequest?patient=123+%26+co&status=active%2Con-hold\
+            &_include=MedicationRequest%3Amedication&_revinclude=Provenance%3Atarget\
+            &_count=50&_sort=-authoredon&_elements=status%2Cmedication",
+        );
+        assert_eq!(SearchParams::new("Patient").to_query(), "Patient");
+        assert_eq!(SearchParams::new("Patient").with_max_pages(0).max_pages(), 1);
+    }
+
+    #[test]
+    fn resolves_references_from_the_index() {
+        let mut result = SearchResult::for_server("https://fhir.example.org/r4");
+        result.add_page(&json!({
+            "resourceType": "Bundle",
+            "type": "searchset",
+            "total": 2,
+            "link": [{ "relation": "next", "url": "https://fhir.example.org/r4?_getpages=abc" }],
+            "entry": [
+                {
+                    "fullUrl": "https://fhir.example.org/r4/MedicationRequest/mr-1",
+                    "resource": {
+                        "resourceType": "MedicationRequest",
+                        "id": "mr-1",
+                        "status": "active",
+                        "intent": "order",
+                        "medicationReference": { "reference": "Medication/med-1" }
+                    },
+                    "search": { "mode": "match" }
+                },
+                {
+                    "fullUrl": "urn:uuid:5f1f0e4c-1c2d-4a3b-9a8e-3c3f1b2a9d10",
+                    "resource": { "resourceType": "Medication", "id": "med-1", "code": { "text": "Warfarin 5 MG" } },
+                    "search": { "mode": "include" }
+                }
+            ]
+        }));
+        result.add_page(&json!({
+            "resourceType": "Bundle",
+            "entry": [{
+                "resource": { "resourceType": "MedicationRequest", "id": "mr-2", "status": "stopped", "intent": "order" },
+                "search": { "mode": "match" }
+            }]
+        }));
+
+        assert_eq!(result.total, Some(2));
+        assert_eq!(result.pages, 2);
+        assert_eq!(result.resources::<MedicationRequest>().len(), 2);
+        assert_eq!(result.included_resources::<Medication>().len(), 1);
+
+        let reference = Reference::new("https://fhir.example.org/r4/Medication/med-1/_history/3");
+        let medication: Medication = result.index.resolve_as(&reference).unwrap();
+        assert_eq!(medication.display().as_deref(), Some("Warfarin 5 MG"));
+        assert!(result.index.get("urn:uuid:5f1f0e4c-1c2d-4a3b-9a8e-3c3f1b2a9d10").is_some());
+        assert!(result.index.resolve_as::<Patient>(&reference).is_none());
+        assert!(result.index.get("MedicationRequest/mr-3").is_none());
+
+        // Same Type/id on another server is another resource
+        assert!(result.index.get("https://other.example.org/r4/Medication/med-1").is_none());
+        assert!(result.index.get("https://fhir.example.org/r4-other/Medication/med-1").is_none());
+    }
+
+    #[test]
+    fn matches_absolute_references_only_with_a_known_server() {
+        let medication = json!({ "resourceType": "Medication", "id": "med-1" });
+        let mut index = ResourceIndex::default();
+        index.insert(medication.clone(), Some("https://fhir.example.org/r4/Medication/med-1"));
+
+        assert!(index.get("Medication/med-1").is_some());
+        assert!(index.get("https://fhir.example.org/r4/Medication/med-1").is_some());
+        assert!(index.get("https://fhir.example.org/r4/Medication/med-1/_history/2").is_none());
+
+        let mut index = ResourceIndex::for_server("https://fhir.example.org/r4/");
+        index.insert(medication, None);
+        assert!(index.get("https://fhir.example.org/r4/Medication/med-1/_history/2").is_some());
+        assert!(index.get("https://other.example.org/r4/Medication/med-1").is_none());
+    }
+
+    #[test]
+    fn reads_next_links_and_reference_keys() {
+        let bundle = json!({ "link": [
+            { "relation": "self", "url": "https://fhir.example.org/r4/Observation?patient=1" },
+            { "relation": "next", "url": "https://fhir.example.org/r4?_getpages=abc&_getpagesoffset=50" }
+        ] });
+        assert_eq!(next_link(&bundle), Some("https://fhir.example.org/r4?_getpages=abc&_getpagesoffset=50"));
+        assert_eq!(next_link(&json!({})), None);
+
+        assert_eq!(reference_key("Patient/1").as_deref(), Some("Patient/1"));
+        assert_eq!(reference_key("https://fhir.example.org/r4/Patient/1/_history/2").as_deref(), Some("Patient/1"));
+        assert_eq!(reference_key("#med-1"), None);
+        assert_eq!(reference_key("urn:uuid:1234"), None);
+    }
+
+    #[tokio::test]
+    async fn stops_at_next_links_to_other_servers() {
+        let server = MockServer::start("200 OK", "application/fhir+json", r#"{
+            "resourceType": "Bundle",
+            "type": "searchset",
+            "total": 80,
+            "link": [{ "relation": "next", "url": "https://evil.example.org/r4?_getpages=abc" }],
+            "entry": [{ "fullUrl": "{url}/r4/Patient/1", "resource": { "resourceType": "Patient", "id": "1" } }]
+        }"#).await;
+        let client = FhirClient::new(&server.url("/r4"), "token").unwrap();
+
+        let result = client.search(&SearchParams::of::<Patient>()).await.unwrap();
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(result.pages, 1);
+        assert!(result.truncated);
+        assert_eq!(result.resources::<Patient>().len(), 1);
+        assert_eq!(result.total, Some(80));
+    }
+}
@@ -0,0 +1,58 @@
+//! Conversions between the UCUM units used by vital signs.
+
+pub const UCUM_SYSTEM: &str = "http://unitsofmeasure.org";
+
+pub const CELSIUS: &str = "Cel";
+pub const FAHRENHEIT: &str = "[degF]";
+pub const KELVIN: &str = "K";
+
+/// UCUM code of a unit, accepting the display forms servers put in `Quantity.unit`
+pub fn normalize(unit: &str) -> &str {
+    match unit.trim() {
+        "Cel" | "°C" | "C" | "degC" | "cel" => CELSIUS,
+        "[degF]" | "°F" | "F" | "degF" | "[degf]" => FAHRENHEIT,
+        "K" | "kelvin" => KELVIN,
+        "/min" | "{beats}/min" | "{beat}/min" | "beats/min" | "bpm" | "{breaths}/min" | "breaths/min" => "/min",
+        "mm[Hg]" | "mmHg" => "mm[Hg]",
+        other => other,
+    }
+}
+
+/// Convert a value between two units, `None` if they are not comparable
+pub fn convert(value: f64, from: &str, to: &str) -> Option<f64> {
+    let (from, to) = (normalize(from), normalize(to));
+    if from == to {
+        return Some(value);
+    }
+
+    let celsius = match from {
+        CELSIUS => value,
+        FAHRENHEIT => (value - 32.0) * 5.0 / 9.0,
+        KELVIN => value - 273.15,
+        _ => return None,
+    };
+    match to {
+        CELSIUS => Some(celsius),
+        FAHRENHEIT => Some(celsius * 9.0 / 5.0 + 32.0),
+        KELVIN => Some(celsius + 273.15),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Option<f64>, b: f64) -> bool {
+        a.is_some_and(|a| (a - b).abs() < 1e-9)
+    }
+
+    #[test]
+    fn converts_temperatures_both_ways() {
+        assert!(close(convert(37.0, CELSIUS, FAHRENHEIT), 98.6));
+        assert!(close(convert(98.6, "°F", "Cel"), 37.0));
+        assert!(close(convert(310.15, KELVIN, CELSIUS), 37.0));
+        assert!(close(convert(72.0, "/min", "{beats}/min"), 72.0));
+        assert_eq!(convert(37.0, CELSIUS, "mm[Hg]"), None);
+    }
+}
@@ -2,19 +2,19 @@ use futures::pin_mut;
 use futures::StreamExt;
 use log::error;
 use async_stream::stream;
-use crate::gemini::error::GeminiError;
-use crate::gemini::utils::{
+use crate::error::GeminiError;
+use crate::utils::{
     GetApiKey, get_mime_type, get_base64_bytes_length
 };
-use crate::gemini::libs::{
+use crate::libs::{
     ChatRequest, Content, Part, FileData, InlineData,
     ChatResponse, FunctionResponse, SafetySetting, GenerationConfig,
 };
-use crate::gemini::requests::{
+use crate::requests::{
     request_chat, upload_media, request_cache, 
     strem_chat,
 };
-use crate::gemini::{GEMINI_BASE_URL, UPLOAD_BASE_URL};
+use crate::{GEMINI_BASE_URL, UPLOAD_BASE_URL};
 use std::fs::File;
 use std::io::Read;
 use base64::{Engine as _, engine::general_purpose::STANDARD};
@@ -70,9 +70,9 @@ impl ChatGemini {
         };
         
         Self {
-            base_url: base_url,
+            base_url,
             model: model.to_string(),
-            request: request,
+            request,
             timeout: Duration::from_secs(300), // default: 5 minutes
             max_retries: 3,         // default: 3 times
         }
@@ -123,7 +123,7 @@ impl ChatGemini {
 
         if let Some(error) = chat_response.error {
             error!("Error {:?}", error);
-            return Err(GeminiError::ResponseContentError);
+            Err(GeminiError::ResponseContentError)
         } else {
             let format_response = ChatResponse {
                 candidates: chat_response.candidates,
@@ -203,7 +203,7 @@ impl ChatGemini {
         // -------- Read from local file --------
         if let Some(file_path) = file_path {
             if mime_type == "auto" {
-                let extension = match file_path.split('.').last() {
+                let extension = match file_path.split('.').next_back() {
                     Some(extension) => extension,
                     None => return Err(GeminiError::InvalidMimeType),
                 };
@@ -318,10 +318,10 @@ impl ChatGemini {
         file_uri: &str, 
         mut mime_type: &str
     ) -> Self {
-        if mime_type == "auto" {
-            if let Some(exetension) = file_uri.split('.').last() {
-                mime_type = get_mime_type(exetension);
-            }
+        if mime_type == "auto"
+            && let Some(exetension) = file_uri.split('.').next_back()
+        {
+            mime_type = get_mime_type(exetension);
         }
 
         let content = Content {
@@ -397,23 +397,17 @@ impl ChatGemini {
     }
 
     pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
-        match &mut self.request.generation_config {
-            Some(config) => {
-                config.max_output_tokens = Some(max_tokens);
-            }
-            None => ()
-        };
+        if let Some(config) = &mut self.request.generation_config {
+            config.max_output_tokens = Some(max_tokens);
+        }
         self
     }
 
     pub fn with_response_schema(mut self, response_schema: serde_json::Value) -> Self {
-        match &mut self.request.generation_config {
-            Some(config) => {
-                config.response_schema = Some(response_schema);
-                config.response_mime_type = Some("application/json".to_string());
-            }
-            None => ()
-        };
+        if let Some(config) = &mut self.request.generation_config {
+            config.response_schema = Some(response_schema);
+            config.response_mime_type = Some("application/json".to_string());
+        }
         self
     }
     
@@ -480,7 +474,7 @@ impl ChatGemini {
     pub fn with_multiple_parts(mut self, parts: Vec<Part>) -> Self {
         let content = Content {
             role: "user".to_string(),
-            parts: parts,
+            parts,
         };
 
         if let Some(contents) = &mut self.request.contents {
@@ -520,13 +514,10 @@ impl ChatGemini {
     }
 
     pub fn with_json_schema(mut self, response_schema: serde_json::Value) -> Self {
-        match &mut self.request.generation_config {
-            Some(config) => {
-                config.response_schema = Some(response_schema);
-                config.response_mime_type = Some("application/json".to_string());
-            }
-            None => ()
-        };
+        if let Some(config) = &mut self.request.generation_config {
+            config.response_schema = Some(response_schema);
+            config.response_mime_type = Some("application/json".to_string());
+        }
         self
     }
 
@@ -555,11 +546,10 @@ impl ChatGemini {
 
     pub fn get_last_content(self) -> Option<Content> {
         if let Some(contents) = self.request.contents {
-            let last_content = contents.last().cloned();
-            return last_content;
+            contents.last().cloned()
         } else {
             error!("Error No contents found");
-            return None;
+            None
         }
     }
 
@@ -594,4 +584,40 @@ impl ChatGemini {
     }
 }
 
-impl GetApiKey for ChatGemini {}
\ No newline at end of file
+impl GetApiKey for ChatGemini {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn builds_generation_config() {
+        let llm = ChatGemini::new("gemini-2.0-flash")
+            .with_api_key("test-key")
+            .with_max_tokens(256)
+            .with_json_schema(json!({ "type": "object" }))
+            .with_system_prompt("You are a clinical assistant");
+
+        assert_eq!(
+            llm.base_url,
+            format!("{}/models/gemini-2.0-flash:generateContent?key=test-key", GEMINI_BASE_URL),
+        );
+
+        let request = serde_json::to_value(&llm.request).unwrap();
+        assert_eq!(request["generationConfig"]["maxOutputTokens"], 256);
+        assert_eq!(request["generationConfig"]["responseMimeType"], "application/json");
+        assert_eq!(request["systemInstruction"]["parts"][0]["text"], "You are a clinical assistant");
+        assert!(request["contents"].is_null());
+    }
+
+    #[test]
+    fn keeps_the_last_content_of_the_history() {
+        let history = vec![
+            Content { role: "user".to_string(), parts: vec![] },
+            Content { role: "model".to_string(), parts: vec![] },
+        ];
+        let llm = ChatGemini::new("gemini-2.0-flash").with_chat_history(history);
+        assert_eq!(llm.get_last_content().map(|c| c.role).as_deref(), Some("model"));
+        assert!(ChatGemini::new("gemini-2.0-flash").get_last_content().is_none());
+    }
+}
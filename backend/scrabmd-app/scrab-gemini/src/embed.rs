@@ -1,9 +1,9 @@
-use crate::gemini::error::GeminiError;
-use crate::gemini::utils::GetApiKey;
-use crate::gemini::libs::{
+use crate::error::GeminiError;
+use crate::utils::GetApiKey;
+use crate::libs::{
     Content, Part, EmbedResponse,EmbedRequest, TaskType
 };
-use crate::gemini::requests::request_embed;
+use crate::requests::request_embed;
 use std::time::Duration;
 use log::error;
 
@@ -52,9 +52,9 @@ impl EmbedGemini {
         };
         
         Self {
-            base_url: base_url,
+            base_url,
             model: model.to_string(),
-            request: request,
+            request,
             max_retries: 0,
             timeout: Duration::from_secs(300), // default: 5 minutes
         }
@@ -103,7 +103,7 @@ impl EmbedGemini {
         };
         if let Some(error) = embed_response.error {
             error!("Error {:?}", error);
-            return Err(GeminiError::ResponseContentError);
+            Err(GeminiError::ResponseContentError)
         } else {
             Ok(embed_response)
         }
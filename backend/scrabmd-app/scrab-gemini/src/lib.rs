@@ -1,3 +1,5 @@
+//! Gemini API client shared by the medical-app and medical-smartapp lambdas.
+
 use std::time::Duration;
 
 pub mod chat;
@@ -13,4 +15,4 @@ pub static GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1
 pub static UPLOAD_BASE_URL: &str = "https://generativelanguage.googleapis.com/upload/v1beta";
 
 pub const DEBUG_PRE: bool = false;
-pub const DEBUG_POST: bool = false;
\ No newline at end of file
+pub const DEBUG_POST: bool = false;
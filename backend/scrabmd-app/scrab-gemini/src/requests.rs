@@ -3,11 +3,11 @@ use reqwest::{self, header::{HeaderMap, HeaderValue}};
 use log::{warn, error};
 use async_stream::stream;
 use futures::StreamExt;
-use crate::gemini::libs::{ChatRequest, Part, Content, ChatResponse};
-use crate::gemini::libs::{CacheRequest, InlineData, EmbedRequest};
-use crate::gemini::utils::print_pre;
-use crate::gemini::{DEBUG_PRE, DEBUG_POST, RETRY_BASE_DELAY};
-use crate::gemini::error::GeminiError;
+use crate::libs::{ChatRequest, Part, Content, ChatResponse};
+use crate::libs::{CacheRequest, InlineData, EmbedRequest};
+use crate::utils::print_pre;
+use crate::{DEBUG_PRE, DEBUG_POST, RETRY_BASE_DELAY};
+use crate::error::GeminiError;
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD;
 use serde_json::json;
@@ -252,8 +252,8 @@ pub async fn request_cache(
                 file_data: None,
             }],
         }],
-        system_instruction: system_instruction,
-        ttl: ttl,
+        system_instruction,
+        ttl,
     };
 
     // Serializes the request struct into a JSON byte vector
@@ -456,13 +456,13 @@ pub async fn make_request(
     request_body: &[u8],
     timeout: Duration,
 ) -> Result<Response, reqwest::Error> {
-    Ok(client
+    client
         .post(url)
         .timeout(timeout)
         .header("Content-Type", "application/json")
         .body(request_body.to_vec())
         .send()
-        .await?)
+        .await
 }
 
 pub async fn manage_error(
@@ -472,12 +472,12 @@ pub async fn manage_error(
 
     match response.json::<ChatResponse>().await {
         Ok(error_detail) => {
-            if let Some(error_message) = error_detail.error {
-                if let Some(message) = error_message.message {
-                    return GeminiError::GenericError {
-                        message,
-                        detail: "ERROR-req-9821".to_string(),
-                    }
+            if let Some(error_message) = error_detail.error
+                && let Some(message) = error_message.message
+            {
+                return GeminiError::GenericError {
+                    message,
+                    detail: "ERROR-req-9821".to_string(),
                 };
             }
             GeminiError::GenericError {
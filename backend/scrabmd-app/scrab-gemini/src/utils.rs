@@ -1,6 +1,6 @@
 use std::env;
-use crate::gemini::error::GeminiError;
-use crate::gemini::libs::Candidate;
+use crate::error::GeminiError;
+use crate::libs::Candidate;
 use log::{info, error};
 
 /// Gets the API key from the environment variables
@@ -53,11 +53,13 @@ pub fn print_pre(request: &impl serde::Serialize, active: bool) {
 /// 
 /// # Examples
 /// ```
+/// use scrab_gemini::utils::get_mime_type;
+///
 /// let mime = get_mime_type("jpg");
 /// assert_eq!(mime, "image/jpeg");
 /// ```
 pub fn get_mime_type(extension: &str) -> &'static str {
-    let mime = match extension {
+    match extension {
         "jpg" | "jpeg" => "image/jpeg",
         "png"   =>  "image/png",
         "webp"  =>  "image/webp",
@@ -90,8 +92,7 @@ pub fn get_mime_type(extension: &str) -> &'static str {
         "opus"  =>  "audio/opus",
         "pcm"   =>  "audio/pcm",
         _ => "text/plain",
-    };
-    mime
+    }
 }
 
 pub fn get_base64_bytes_length(base64_str: &str) -> usize {
@@ -105,27 +106,25 @@ pub fn get_grounding_response(candidate: &Candidate) -> String {
 
         if let Some(supports) = &grounding_metadata.grounding_supports {
             for support in supports {
-                if let (Some(segment), Some(chunk_indices)) = (&support.segment, &support.grounding_chunk_indices) {
-                    if let Some(_end_index) = segment.end_index {
-                        if let Some(text) = &segment.text {
-                            markdown_text.push_str(text);
-
-                            // Add footnotes
-                            for &chunk_index in chunk_indices {
-                                if let Some(chunks) = &grounding_metadata.grounding_chunks {
-                                    if let Some(chunk) = chunks.get(chunk_index as usize) {
-                                        if let Some(web_info) = &chunk.web {
-                                            markdown_text.push_str(&format!("[[{}]]({}))\n", 
-                                                chunk_index + 1, 
-                                                web_info.uri
-                                            ));
-                                        }
-                                    }
-                                }
-                            }
-                            markdown_text.push('\n');
+                if let (Some(segment), Some(chunk_indices)) = (&support.segment, &support.grounding_chunk_indices)
+                    && segment.end_index.is_some()
+                    && let Some(text) = &segment.text
+                {
+                    markdown_text.push_str(text);
+
+                    // Add footnotes
+                    for &chunk_index in chunk_indices {
+                        if let Some(chunks) = &grounding_metadata.grounding_chunks
+                            && let Some(chunk) = chunks.get(chunk_index as usize)
+                            && let Some(web_info) = &chunk.web
+                        {
+                            markdown_text.push_str(&format!("[[{}]]({}))\n", 
+                                chunk_index + 1, 
+                                web_info.uri
+                            ));
                         }
                     }
+                    markdown_text.push('\n');
                 }
             }
         }
@@ -136,10 +135,10 @@ pub fn get_grounding_response(candidate: &Candidate) -> String {
         if let Some(web_queries) = &grounding_metadata.web_search_queries {
             markdown_text.push_str(&format!("\n**Web Search Queries:** {:?}\n", web_queries));
             
-            if let Some(entry_point) = &grounding_metadata.search_entry_point {
-                if let Some(content) = &entry_point.rendered_content {
-                    markdown_text.push_str(&format!("\n**Search Entry Point:**\n {}\n", content));
-                }
+            if let Some(entry_point) = &grounding_metadata.search_entry_point
+                && let Some(content) = &entry_point.rendered_content
+            {
+                markdown_text.push_str(&format!("\n**Search Entry Point:**\n {}\n", content));
             }
         }
 
@@ -162,4 +161,34 @@ pub fn get_grounding_response(candidate: &Candidate) -> String {
     } else {
         String::from("No grounding metadata available")
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_extensions_to_mime_types() {
+        assert_eq!(get_mime_type("pdf"), "application/pdf");
+        assert_eq!(get_mime_type("jpeg"), "image/jpeg");
+        assert_eq!(get_mime_type("unknown"), "text/plain");
+    }
+
+    #[test]
+    fn computes_decoded_base64_length() {
+        assert_eq!(get_base64_bytes_length("aGVsbG8="), 5);
+        assert_eq!(get_base64_bytes_length("aGVsbG8h"), 6);
+    }
+
+    #[test]
+    fn renders_grounding_sources() {
+        let candidate: Candidate = serde_json::from_value(serde_json::json!({
+            "groundingMetadata": {
+                "webSearchQueries": ["warfarin ibuprofen interaction"]
+            }
+        })).unwrap();
+
+        let markdown = get_grounding_response(&candidate);
+        assert!(markdown.contains("## Grounding Sources"));
+        assert!(markdown.contains("warfarin ibuprofen interaction"));
+    }
+}
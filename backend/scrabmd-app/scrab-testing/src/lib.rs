@@ -0,0 +1,100 @@
+//! Test helpers shared by the workspace crates, only used as a dev-dependency.
+
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// HTTP server on a local port answering every request with the same
+/// response, e.g. a token endpoint or a FHIR server. The request bodies are
+/// recorded for the test to check.
+pub struct MockServer {
+    url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockServer {
+    /// Server answering `200 OK` with the JSON `body`
+    pub async fn json(body: &str) -> Self {
+        Self::start("200 OK", "application/json", body).await
+    }
+
+    /// Server answering with `status`, e.g. `401 Unauthorized`, and `body`,
+    /// in which `{url}` is replaced by the URL of the server
+    pub async fn start(status: &str, content_type: &str, body: &str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(vec![]));
+
+        let seen = requests.clone();
+        let body = body.replace("{url}", &url);
+        let reply = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body,
+        );
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                if let Some(body) = read_body(&mut socket).await {
+                    seen.lock().unwrap().push(body);
+                }
+                let _ = socket.write_all(reply.as_bytes()).await;
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    /// Server accepting connections and never answering, to test timeouts
+    pub async fn silent() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let mut sockets = vec![];
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+
+        Self { url, requests: Arc::default() }
+    }
+
+    /// URL of `path` on the server, e.g. `/token` or `/r4`
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.url, path)
+    }
+
+    /// Bodies of the requests received so far, in order
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Body of a request, read up to its `Content-Length`
+async fn read_body(socket: &mut TcpStream) -> Option<String> {
+    let mut request = vec![];
+    let mut buffer = [0; 4096];
+
+    loop {
+        let read = socket.read(&mut buffer).await.ok()?;
+        request.extend_from_slice(&buffer[..read]);
+
+        let text = String::from_utf8_lossy(&request).to_string();
+        if let Some((head, body)) = text.split_once("\r\n\r\n") {
+            let length = head.lines()
+                .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|l| l.trim().to_string()))
+                .and_then(|l| l.parse::<usize>().ok())
+                .unwrap_or(0);
+            if body.len() >= length {
+                return Some(body.to_string());
+            }
+        }
+        if read == 0 {
+            return None;
+        }
+    }
+}